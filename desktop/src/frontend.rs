@@ -45,7 +45,14 @@ use gbc_plus::cpu::{
         apu::NUM_SAMPLES,
         cartridge::mbc::{mbc3::RtcFile, MBC},
         joypad::JoypadButtons,
+        infrared::tcp_infrared_link::TcpInfraredLink,
+        serial::{
+            four_player_adapter::MAX_PLAYERS,
+            printer::{PrintedImage, Printer},
+            tcp_link_cable::TcpLinkCable
+        },
         ppu::{
+            Renderer as PpuRenderer,
             SCREEN_HEIGHT,
             SCREEN_WIDTH
         }
@@ -67,7 +74,7 @@ use sdl2::{
         AudioDevice,
         AudioSpecDesired
     },
-    controller::GameController,
+    controller::{Axis, GameController},
     event::{
         Event,
         WindowEvent
@@ -121,6 +128,9 @@ impl ButtonIndex {
 }
 
 const WAVEFORM_LENGTH: usize = 683;
+const LINK_CABLE_PORT: u16 = 7253;
+const INFRARED_PORT: u16 = 7254;
+const FOUR_PLAYER_SCALE: usize = 2;
 const WAVEFORM_HEIGHT: usize = 256;
 
 const THEME_NAMES: [&str; 10] = [
@@ -161,6 +171,10 @@ struct EmuConfig {
     button_to_keys: HashMap<JoypadButtons, String>,
     button_to_index: HashMap<JoypadButtons, ButtonIndex>,
     keyboard_map: HashMap<String, JoypadButtons>,
+    #[serde(default)]
+    pixel_fifo: bool,
+    #[serde(default)]
+    colorization: bool
 }
 
 impl EmuConfig {
@@ -170,13 +184,18 @@ impl EmuConfig {
             button_map: HashMap::new(),
             keyboard_map: HashMap::new(),
             button_to_index: HashMap::new(),
-            button_to_keys: HashMap::new()
+            button_to_keys: HashMap::new(),
+            pixel_fifo: false,
+            colorization: false
         }
     }
+
+    pub fn ppu_renderer(&self) -> PpuRenderer {
+        if self.pixel_fifo { PpuRenderer::PixelFifo } else { PpuRenderer::Scanline }
+    }
 }
 
 pub struct Frontend {
-    controller: Option<GameController>,
     device: AudioDevice<GbcAudioCallback>,
     event_pump: EventPump,
     button_map: HashMap<u8, JoypadButtons>,
@@ -210,7 +229,15 @@ pub struct Frontend {
     current_input: Option<JoypadButtons>,
     current_joy_input: Option<JoypadButtons>,
     current_action: Option<usize>,
-    button_to_index: HashMap<JoypadButtons, ButtonIndex>
+    button_to_index: HashMap<JoypadButtons, ButtonIndex>,
+    printed_images: Option<Arc<Mutex<Vec<PrintedImage>>>>,
+    num_players: usize,
+    player_keyboard_maps: Vec<HashMap<Keycode, JoypadButtons>>,
+    player_controllers: Vec<GameController>,
+    // which player each held controller button was pressed for, keyed by controller and button index
+    controller_presses: HashMap<(u32, u8), usize>,
+    tilt: (f32, f32),
+    rumble_strength: f32
 }
 
 pub struct GbcAudioCallback {
@@ -363,7 +390,8 @@ impl Frontend {
         cpu: &mut CPU,
         consumer: Caching<Arc<SharedRb<Heap<f32>>>, false, true>,
         wave_consumer: Caching<Arc<SharedRb<Heap<f32>>>, false, true>,
-        game_name: String
+        game_name: String,
+        num_players: usize
     ) -> Self {
         let sdl_context = sdl2::init().unwrap();
         let video_subsystem = sdl_context.video().unwrap();
@@ -379,17 +407,14 @@ impl Frontend {
         gl_attr.set_context_version(3, 3);
         gl_attr.set_context_profile(GLProfile::Core);
 
-        let controller = (0..available)
-            .find_map(|id| {
-            match game_controller_subsystem.open(id) {
-                Ok(c) => {
-                    Some(c)
-                }
-                Err(_) => {
-                    None
-                }
-            }
-        });
+        /*
+         * every controller plugged in goes to the next player in line. with the four player adapter
+         * that's one per game boy, otherwise they're the SGB's players 1-4 for games using MLT_REQ
+         */
+        let player_controllers = (0..available)
+            .filter_map(|id| game_controller_subsystem.open(id).ok())
+            .take(Self::max_controllers(num_players))
+            .collect();
 
         let audio_subsystem = sdl_context.audio().unwrap();
 
@@ -407,8 +432,15 @@ impl Frontend {
 
         device.resume();
 
+        let (window_width, window_height) = if num_players > 1 {
+            (SCREEN_WIDTH * FOUR_PLAYER_SCALE * num_players, SCREEN_HEIGHT * FOUR_PLAYER_SCALE)
+        } else {
+            // the SGB picture includes the border, so it's bigger than the game screen
+            (cpu.picture().width * 3, cpu.picture().height * 3)
+        };
+
         let window = video_subsystem
-            .window("GBC+", (SCREEN_WIDTH * 3) as u32, (SCREEN_HEIGHT * 3) as u32)
+            .window("GBC+", window_width as u32, window_height as u32)
             .opengl()
             .position_centered()
             .build()
@@ -447,11 +479,12 @@ impl Frontend {
             gl.tex_parameter_i32(TEXTURE_2D, TEXTURE_MIN_FILTER, NEAREST as i32);
             gl.tex_parameter_i32(TEXTURE_2D, TEXTURE_MAG_FILTER, NEAREST as i32);
 
+            // big enough for four screens side by side, or a single SGB picture with its border
             gl.tex_storage_2d(
                 TEXTURE_2D,
                 1,
                 RGBA8,
-                SCREEN_WIDTH as i32 * 2,
+                (SCREEN_WIDTH * MAX_PLAYERS) as i32,
                 SCREEN_HEIGHT as i32 * 2
             );
 
@@ -579,9 +612,10 @@ impl Frontend {
         }
 
         cpu.bus.ppu.set_dmg_palette(config.current_palette);
+        cpu.bus.ppu.set_colorization(config.colorization);
+        cpu.bus.ppu.set_renderer(config.ppu_renderer());
 
         Self {
-            controller,
             device,
             event_pump,
             button_map,
@@ -624,7 +658,85 @@ impl Frontend {
             button_to_index,
             current_input: None,
             current_action: None,
-            current_joy_input: None
+            current_joy_input: None,
+            printed_images: None,
+            num_players,
+            player_keyboard_maps: (1..MAX_PLAYERS).map(Self::default_player_keyboard_map).collect(),
+            player_controllers,
+            controller_presses: HashMap::new(),
+            tilt: (0.0, 0.0),
+            rumble_strength: 0.0
+        }
+    }
+
+    // players 2-4 don't have configurable keys, player 1 uses the regular keyboard map
+    fn default_player_keyboard_map(player: usize) -> HashMap<Keycode, JoypadButtons> {
+        match player {
+            1 => HashMap::from([
+                (Keycode::Up, JoypadButtons::Up),
+                (Keycode::Down, JoypadButtons::Down),
+                (Keycode::Left, JoypadButtons::Left),
+                (Keycode::Right, JoypadButtons::Right),
+                (Keycode::Comma, JoypadButtons::B),
+                (Keycode::Period, JoypadButtons::A),
+                (Keycode::RShift, JoypadButtons::Select),
+                (Keycode::Slash, JoypadButtons::Start)
+            ]),
+            2 => HashMap::from([
+                (Keycode::T, JoypadButtons::Up),
+                (Keycode::G, JoypadButtons::Down),
+                (Keycode::F, JoypadButtons::Left),
+                (Keycode::H, JoypadButtons::Right),
+                (Keycode::Z, JoypadButtons::B),
+                (Keycode::X, JoypadButtons::A),
+                (Keycode::C, JoypadButtons::Select),
+                (Keycode::V, JoypadButtons::Start)
+            ]),
+            _ => HashMap::from([
+                (Keycode::Kp8, JoypadButtons::Up),
+                (Keycode::Kp2, JoypadButtons::Down),
+                (Keycode::Kp4, JoypadButtons::Left),
+                (Keycode::Kp6, JoypadButtons::Right),
+                (Keycode::Kp1, JoypadButtons::B),
+                (Keycode::Kp3, JoypadButtons::A),
+                (Keycode::Kp7, JoypadButtons::Select),
+                (Keycode::Kp9, JoypadButtons::Start)
+            ])
+        }
+    }
+
+    fn player_keyboard_map(&self, player: usize) -> &HashMap<Keycode, JoypadButtons> {
+        if player == 0 {
+            &self.keyboard_map
+        } else {
+            &self.player_keyboard_maps[player - 1]
+        }
+    }
+
+    fn controller_player(&self, instance_id: u32) -> Option<usize> {
+        self.player_controllers
+            .iter()
+            .position(|controller| controller.instance_id() == instance_id)
+    }
+
+    // controllers only get split up between players while a game has asked for more than one through MLT_REQ
+    fn joypad_player(&self, cpu: &CPU, instance_id: u32) -> usize {
+        if cpu.bus.joypad.num_players() > 1 {
+            self.controller_player(instance_id).unwrap_or(0)
+        } else {
+            0
+        }
+    }
+
+    // one controller per game boy with the four player adapter, otherwise one for each of the SGB's players
+    fn max_controllers(num_players: usize) -> usize {
+        if num_players > 1 { num_players } else { MAX_PLAYERS }
+    }
+
+    // SDL also reports controllers that were already connected at startup, so skip the ones we have
+    fn add_player_controller(&mut self, controller: GameController) {
+        if self.player_controllers.len() < Self::max_controllers(self.num_players) && self.controller_player(controller.instance_id()).is_none() {
+            self.player_controllers.push(controller);
         }
     }
 
@@ -633,7 +745,10 @@ impl Frontend {
 
         cpu.bus.ppu.frame_finished = false;
 
-        let screen = &cpu.bus.ppu.picture.data;
+        let picture = cpu.picture();
+
+        let width = picture.width as i32;
+        let height = picture.height as i32;
 
         unsafe {
             self.gl.clear(glow::COLOR_BUFFER_BIT);
@@ -644,28 +759,73 @@ impl Frontend {
                     0,
                     0,
                     0,
-                    SCREEN_WIDTH as i32,
-                    SCREEN_HEIGHT as i32,
+                    width,
+                    height,
                     RGBA,
                     UNSIGNED_BYTE,
-                    PixelUnpackData::Slice(&screen)
+                    PixelUnpackData::Slice(&picture.data)
             );
 
             self.gl.blit_framebuffer(
                     0,
-                    SCREEN_HEIGHT as i32,
-                    SCREEN_WIDTH as i32,
+                    height,
+                    width,
                     0,
                     0,
                     0,
-                    SCREEN_WIDTH as i32 * 3,
-                    SCREEN_HEIGHT as i32 * 3,
+                    width * 3,
+                    height * 3,
                     COLOR_BUFFER_BIT,
                     NEAREST
             );
         }
     }
 
+    // draws every player's screen side by side
+    pub fn render_screens(&mut self, cpus: &mut [CPU]) {
+        cpus[0].bus.ppu.cap_fps();
+
+        unsafe {
+            self.gl.clear(glow::COLOR_BUFFER_BIT);
+            self.gl.bind_texture(TEXTURE_2D, Some(self.texture));
+        }
+
+        for (i, cpu) in cpus.iter_mut().enumerate() {
+            cpu.bus.ppu.frame_finished = false;
+
+            let screen = &cpu.bus.ppu.picture.data;
+
+            unsafe {
+                self.gl.tex_sub_image_2d(
+                    TEXTURE_2D,
+                    0,
+                    (SCREEN_WIDTH * i) as i32,
+                    0,
+                    SCREEN_WIDTH as i32,
+                    SCREEN_HEIGHT as i32,
+                    RGBA,
+                    UNSIGNED_BYTE,
+                    PixelUnpackData::Slice(screen)
+                );
+            }
+        }
+
+        unsafe {
+            self.gl.blit_framebuffer(
+                0,
+                SCREEN_HEIGHT as i32,
+                (SCREEN_WIDTH * self.num_players) as i32,
+                0,
+                0,
+                0,
+                (SCREEN_WIDTH * FOUR_PLAYER_SCALE * self.num_players) as i32,
+                (SCREEN_HEIGHT * FOUR_PLAYER_SCALE) as i32,
+                COLOR_BUFFER_BIT,
+                NEAREST
+            );
+        }
+    }
+
     pub fn end_frame(&mut self) {
         self.window.gl_swap_window();
     }
@@ -690,6 +850,24 @@ impl Frontend {
                     self.update_rtc(cpu, true, true);
                 }
             }
+            MBC::HuC3(huc3) => {
+                let bytes = {
+                    let mut cloud_service = self.cloud_service.lock().unwrap();
+                    let mut rtc_name = cloud_service.game_name.strip_suffix(".sav").unwrap().to_string();
+
+                    rtc_name.push_str(".rtc");
+
+                    cloud_service.get_file(Some(rtc_name))
+                };
+
+                let json_str = str::from_utf8(&bytes).unwrap();
+
+                if json_str != "" {
+                    huc3.load_rtc(json_str.to_string());
+                } else {
+                    self.update_rtc(cpu, true, true);
+                }
+            }
             _ => ()
         }
     }
@@ -734,6 +912,37 @@ impl Frontend {
                         .as_millis());
                 }
             }
+            MBC::HuC3(huc3) => {
+                let current_time = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .expect("an error occurred")
+                    .as_millis();
+                if (self.last_check.is_some() && current_time - self.last_check.unwrap() >= 30 * 60 * 1000) ||
+                    is_initial ||
+                    huc3.is_dirty
+                {
+                    huc3.is_dirty = false;
+                    if logged_in {
+                        let json_str = huc3.save_rtc_web_mobile();
+
+                        let mut cloud_service = self.cloud_service.lock().unwrap();
+
+                        let mut rtc_name = cloud_service.game_name.strip_suffix(".sav").unwrap().to_string();
+
+                        rtc_name.push_str(".rtc");
+
+                        cloud_service.upload_file(json_str.as_bytes(), Some(rtc_name));
+                    } else {
+                        huc3.save_rtc();
+                    }
+                    self.last_check = None;
+                } else {
+                    self.last_check = Some(SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .expect("an error occurred")
+                        .as_millis());
+                }
+            }
             _ => ()
         }
     }
@@ -784,6 +993,26 @@ impl Frontend {
                     mbc.backup_file.save_file();
                 }
             }
+            MBC::MBC2(mbc) => if mbc.check_save(logged_in) {
+                if logged_in {
+                    let data = mbc.backup_file.ram.clone();
+
+                    mbc.backup_file.is_dirty = false;
+                    mbc.backup_file.last_updated = 0;
+
+                    mbc.backup_file.last_saved = SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .expect("an error occurred")
+                        .as_millis();
+
+                    let cloud_service = self.cloud_service.clone();
+                    thread::spawn(move || {
+                        cloud_service.lock().unwrap().upload_file(&data, None);
+                    });
+                } else {
+                    mbc.backup_file.save_file();
+                }
+            }
             MBC::MBC5(mbc) => if mbc.check_save(logged_in) {
                 if logged_in {
                     let data = mbc.backup_file.ram.clone();
@@ -804,21 +1033,192 @@ impl Frontend {
                     mbc.backup_file.save_file();
                 }
             }
+            MBC::MBC7(mbc) => if mbc.check_save(logged_in) {
+                if logged_in {
+                    let data = mbc.backup_file.ram.clone();
+
+                    mbc.backup_file.is_dirty = false;
+                    mbc.backup_file.last_updated = 0;
+
+                    mbc.backup_file.last_saved = SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .expect("an error occurred")
+                        .as_millis();
+
+                    let cloud_service = self.cloud_service.clone();
+                    thread::spawn(move || {
+                        cloud_service.lock().unwrap().upload_file(&data, None);
+                    });
+                } else {
+                    mbc.backup_file.save_file();
+                }
+            }
+            MBC::MMM01(mbc) => if mbc.check_save(logged_in) {
+                if logged_in {
+                    let data = mbc.backup_file.ram.clone();
+
+                    mbc.backup_file.is_dirty = false;
+                    mbc.backup_file.last_updated = 0;
+
+                    mbc.backup_file.last_saved = SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .expect("an error occurred")
+                        .as_millis();
+
+                    let cloud_service = self.cloud_service.clone();
+                    thread::spawn(move || {
+                        cloud_service.lock().unwrap().upload_file(&data, None);
+                    });
+                } else {
+                    mbc.backup_file.save_file();
+                }
+            }
+            MBC::HuC1(mbc) => if mbc.check_save(logged_in) {
+                if logged_in {
+                    let data = mbc.backup_file.ram.clone();
+
+                    mbc.backup_file.is_dirty = false;
+                    mbc.backup_file.last_updated = 0;
+
+                    mbc.backup_file.last_saved = SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .expect("an error occurred")
+                        .as_millis();
+
+                    let cloud_service = self.cloud_service.clone();
+                    thread::spawn(move || {
+                        cloud_service.lock().unwrap().upload_file(&data, None);
+                    });
+                } else {
+                    mbc.backup_file.save_file();
+                }
+            }
+            MBC::HuC3(mbc) => if mbc.check_save(logged_in) {
+                if logged_in {
+                    let data = mbc.backup_file.ram.clone();
+
+                    mbc.backup_file.is_dirty = false;
+                    mbc.backup_file.last_updated = 0;
+
+                    mbc.backup_file.last_saved = SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .expect("an error occurred")
+                        .as_millis();
+
+                    let cloud_service = self.cloud_service.clone();
+                    thread::spawn(move || {
+                        cloud_service.lock().unwrap().upload_file(&data, None);
+                    });
+                } else {
+                    mbc.backup_file.save_file();
+                }
+            }
+            MBC::PocketCamera(mbc) => if mbc.check_save(logged_in) {
+                if logged_in {
+                    let data = mbc.backup_file.ram.clone();
+
+                    mbc.backup_file.is_dirty = false;
+                    mbc.backup_file.last_updated = 0;
+
+                    mbc.backup_file.last_saved = SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .expect("an error occurred")
+                        .as_millis();
+
+                    let cloud_service = self.cloud_service.clone();
+                    thread::spawn(move || {
+                        cloud_service.lock().unwrap().upload_file(&data, None);
+                    });
+                } else {
+                    mbc.backup_file.save_file();
+                }
+            }
             _ => ()
         }
     }
 
+    // saves anything the game boy printer finished printing next to the game's save states
+    pub fn check_prints(&mut self, save_name: &str) {
+        if let Some(printed_images) = &self.printed_images {
+            Self::save_prints(printed_images, save_name);
+        }
+    }
+
+    fn save_prints(printed_images: &Mutex<Vec<PrintedImage>>, save_name: &str) {
+        let images: Vec<PrintedImage> = printed_images.lock().unwrap().drain(..).collect();
+
+        if images.is_empty() {
+            return;
+        }
+
+        let game_path = save_name.replace(".sav", "");
+
+        let mut split: Vec<&str> = game_path.split('/').collect();
+
+        let game_name = split.pop().unwrap();
+
+        let mut dir = data_dir().unwrap();
+
+        dir.push("GBC+");
+        dir.push(game_name);
+
+        if let Err(err) = fs::create_dir_all(&dir) {
+            println!("couldn't create print directory: {err}");
+
+            return;
+        }
+
+        let now = Local::now();
+
+        for (i, image) in images.iter().enumerate() {
+            let mut path = dir.clone();
+
+            path.push(format!("print_{}_{}.png", now.format("%Y%m%d%H%M%S"), i));
+
+            if let Err(err) = fs::write(&path, image.to_png()) {
+                println!("couldn't save print to {}: {err}", path.display());
+            }
+        }
+    }
+
     pub fn check_controller_status(&mut self) {
         if let Some(controller_id) = self.controller_id {
-            self.controller = self.reconnect_controller(controller_id);
+            let controller = self.reconnect_controller(controller_id);
 
-            if self.controller.is_some() || self.retry_attempts >= 5 {
+            let reconnected = controller.is_some();
+
+            if let Some(controller) = controller {
+                self.add_player_controller(controller);
+            }
+
+            if reconnected || self.retry_attempts >= 5 {
                 self.controller_id = None;
                 self.retry_attempts = 0;
             }
         }
     }
 
+    /*
+     * passes MBC5's rumble motor on to the controller. the rumble gets renewed every frame
+     * for a little longer than a frame, so it stops on its own if the emulator stalls.
+     */
+    pub fn update_rumble(&mut self, cpu: &CPU) {
+        let strength = cpu.rumble_strength();
+
+        if strength == 0.0 && self.rumble_strength == 0.0 {
+            return;
+        }
+
+        self.rumble_strength = strength;
+
+        // the rumble pak is in player 1's game
+        if let Some(controller) = self.player_controllers.first_mut() {
+            let intensity = (strength * u16::MAX as f32) as u16;
+
+            controller.set_rumble(intensity, intensity, 100).ok();
+        }
+    }
+
     pub fn clear_framebuffer(&mut self) {
         unsafe {
             self.gl.clear(glow::COLOR_BUFFER_BIT);
@@ -866,6 +1266,7 @@ impl Frontend {
     fn reload_cpu(
         cpu: &mut CPU,
         current_palette: usize,
+        renderer: PpuRenderer,
         producer: Caching<Arc<SharedRb<Heap<f32>>>, true, false>,
         waveform_producer: Caching<Arc<SharedRb<Heap<f32>>>, true, false>,
         rom_bytes: &[u8],
@@ -874,11 +1275,26 @@ impl Frontend {
         cloud_service: Arc<Mutex<CloudService>>,
         fetch_save: bool
     ) -> Vec<u8> {
-        *cpu = CPU::new(producer, Some(waveform_producer), Some(rom_path), false, true);
+        let link_cable = cpu.disconnect_link_cable();
+        let infrared_link = cpu.disconnect_infrared_link();
+        let colorization = cpu.bus.ppu.colorization;
+        let model = cpu.bus.model;
+        let boot_rom = cpu.bus.boot_rom().to_vec();
+
+        *cpu = CPU::new(producer, Some(waveform_producer), Some(rom_path), false, true, model);
+
+        if !boot_rom.is_empty() {
+            cpu.load_boot_rom(&boot_rom);
+        }
+
+        cpu.connect_link_cable(link_cable);
+        cpu.connect_infrared_link(infrared_link);
 
         cpu.load_rom(rom_bytes, logged_in);
 
         cpu.bus.ppu.set_dmg_palette(current_palette);
+        cpu.bus.ppu.set_colorization(colorization);
+        cpu.bus.ppu.set_renderer(renderer);
 
         if logged_in && fetch_save {
             let bytes = cloud_service.lock().unwrap().get_file(None);
@@ -980,8 +1396,10 @@ impl Frontend {
 
                     if ui.color_button(theme_name, color_normalized) {
                         cpu.bus.ppu.set_dmg_palette(i);
+                        cpu.bus.ppu.set_colorization(false);
 
                         self.config.current_palette = i;
+                        self.config.colorization = false;
 
                         Self::write_config_file(&self.config, &mut self.config_file);
 
@@ -990,6 +1408,26 @@ impl Frontend {
                     }
                 }
 
+                // the palettes a CGB would pick for the game that's loaded
+                let color = cpu.bus.ppu.colorization_palettes.bg[1];
+
+                let color_normalized = [color.r as f32 / 255.0, color.g as f32 / 255.0, color.b as f32 / 255.0, 1.0];
+
+                ui.text("CGB colorization");
+
+                ui.same_line();
+
+                if ui.color_button("CGB colorization", color_normalized) {
+                    cpu.bus.ppu.set_colorization(true);
+
+                    self.config.colorization = true;
+
+                    Self::write_config_file(&self.config, &mut self.config_file);
+
+                    self.show_palette_picker_popup = false;
+                    ui.close_current_popup();
+                }
+
                 token.end();
             }
             if let Some(token) = ui.begin_popup("confirm_delete") {
@@ -1246,6 +1684,63 @@ impl Frontend {
                     if ui.menu_item("Controller bindings") {
                         self.show_bindings_popup = true;
                     }
+                    if ui.menu_item_config("Pixel FIFO renderer").selected(self.config.pixel_fifo).build() {
+                        self.config.pixel_fifo = !self.config.pixel_fifo;
+
+                        cpu.bus.ppu.set_renderer(self.config.ppu_renderer());
+
+                        Self::write_config_file(&self.config, &mut self.config_file);
+                    }
+                    if let Some(menu) = ui.begin_menu("Link cable") {
+                        if ui.menu_item("Host session") {
+                            match TcpLinkCable::host(LINK_CABLE_PORT) {
+                                Ok(link_cable) => cpu.connect_link_cable(Box::new(link_cable)),
+                                Err(err) => println!("couldn't host link cable session: {err}")
+                            }
+                        }
+                        if ui.menu_item("Join session") {
+                            match TcpLinkCable::connect(&format!("127.0.0.1:{LINK_CABLE_PORT}")) {
+                                Ok(link_cable) => cpu.connect_link_cable(Box::new(link_cable)),
+                                Err(err) => println!("couldn't join link cable session: {err}")
+                            }
+                        }
+                        if ui.menu_item("Connect Game Boy Printer") {
+                            let printer = Printer::new();
+
+                            let finished_prints = printer.finished_prints();
+
+                            // the old printer tears off its last page as it gets unplugged, so save that before moving on
+                            cpu.connect_link_cable(Box::new(printer));
+
+                            if let Some(printed_images) = &self.printed_images {
+                                Self::save_prints(printed_images, save_name);
+                            }
+
+                            self.printed_images = Some(finished_prints);
+                        }
+                        if ui.menu_item("Disconnect") {
+                            cpu.disconnect_link_cable();
+                        }
+                        menu.end();
+                    }
+                    if let Some(menu) = ui.begin_menu("Infrared") {
+                        if ui.menu_item("Host session") {
+                            match TcpInfraredLink::host(INFRARED_PORT) {
+                                Ok(link) => cpu.connect_infrared_link(Box::new(link)),
+                                Err(err) => println!("couldn't host infrared session: {err}")
+                            }
+                        }
+                        if ui.menu_item("Join session") {
+                            match TcpInfraredLink::connect(&format!("127.0.0.1:{INFRARED_PORT}")) {
+                                Ok(link) => cpu.connect_infrared_link(Box::new(link)),
+                                Err(err) => println!("couldn't join infrared session: {err}")
+                            }
+                        }
+                        if ui.menu_item("Disconnect") {
+                            cpu.disconnect_infrared_link();
+                        }
+                        menu.end();
+                    }
                     menu.end();
                 }
             });
@@ -1273,6 +1768,7 @@ impl Frontend {
             let new_save_bytes = Self::reload_cpu(
                 cpu,
                 self.config.current_palette,
+                self.config.ppu_renderer(),
                 producer,
                 waveform_producer,
                 rom_bytes,
@@ -1346,6 +1842,28 @@ impl Frontend {
                     }
                 }
             }
+            MBC::MBC2(mbc) => {
+                if mbc.backup_file.is_dirty {
+                    if logged_in {
+                        let data = mbc.backup_file.ram.clone();
+
+                        mbc.backup_file.is_dirty = false;
+                        mbc.backup_file.last_updated = 0;
+
+                        mbc.backup_file.last_saved = SystemTime::now()
+                            .duration_since(UNIX_EPOCH)
+                            .expect("an error occurred")
+                            .as_millis();
+
+                        let cloud_service = self.cloud_service.clone();
+                        thread::spawn(move || {
+                            cloud_service.lock().unwrap().upload_file(&data, None);
+                        });
+                    } else {
+                        mbc.backup_file.save_file();
+                    }
+                }
+            }
             MBC::MBC5(mbc) => {
                 if mbc.backup_file.is_dirty {
                     if logged_in {
@@ -1368,6 +1886,116 @@ impl Frontend {
                     }
                 }
             }
+            MBC::MBC7(mbc) => {
+                if mbc.backup_file.is_dirty {
+                    if logged_in {
+                        let data = mbc.backup_file.ram.clone();
+
+                        mbc.backup_file.is_dirty = false;
+                        mbc.backup_file.last_updated = 0;
+
+                        mbc.backup_file.last_saved = SystemTime::now()
+                            .duration_since(UNIX_EPOCH)
+                            .expect("an error occurred")
+                            .as_millis();
+
+                        let cloud_service = self.cloud_service.clone();
+                        thread::spawn(move || {
+                            cloud_service.lock().unwrap().upload_file(&data, None);
+                        });
+                    } else {
+                        mbc.backup_file.save_file();
+                    }
+                }
+            }
+            MBC::MMM01(mbc) => {
+                if mbc.backup_file.is_dirty {
+                    if logged_in {
+                        let data = mbc.backup_file.ram.clone();
+
+                        mbc.backup_file.is_dirty = false;
+                        mbc.backup_file.last_updated = 0;
+
+                        mbc.backup_file.last_saved = SystemTime::now()
+                            .duration_since(UNIX_EPOCH)
+                            .expect("an error occurred")
+                            .as_millis();
+
+                        let cloud_service = self.cloud_service.clone();
+                        thread::spawn(move || {
+                            cloud_service.lock().unwrap().upload_file(&data, None);
+                        });
+                    } else {
+                        mbc.backup_file.save_file();
+                    }
+                }
+            }
+            MBC::HuC1(mbc) => {
+                if mbc.backup_file.is_dirty {
+                    if logged_in {
+                        let data = mbc.backup_file.ram.clone();
+
+                        mbc.backup_file.is_dirty = false;
+                        mbc.backup_file.last_updated = 0;
+
+                        mbc.backup_file.last_saved = SystemTime::now()
+                            .duration_since(UNIX_EPOCH)
+                            .expect("an error occurred")
+                            .as_millis();
+
+                        let cloud_service = self.cloud_service.clone();
+                        thread::spawn(move || {
+                            cloud_service.lock().unwrap().upload_file(&data, None);
+                        });
+                    } else {
+                        mbc.backup_file.save_file();
+                    }
+                }
+            }
+            MBC::HuC3(mbc) => {
+                if mbc.backup_file.is_dirty {
+                    if logged_in {
+                        let data = mbc.backup_file.ram.clone();
+
+                        mbc.backup_file.is_dirty = false;
+                        mbc.backup_file.last_updated = 0;
+
+                        mbc.backup_file.last_saved = SystemTime::now()
+                            .duration_since(UNIX_EPOCH)
+                            .expect("an error occurred")
+                            .as_millis();
+
+                        let cloud_service = self.cloud_service.clone();
+                        thread::spawn(move || {
+                            cloud_service.lock().unwrap().upload_file(&data, None);
+                        });
+                    } else {
+                        mbc.backup_file.save_file();
+                    }
+                }
+            }
+            MBC::PocketCamera(mbc) => {
+                if mbc.backup_file.is_dirty {
+                    if logged_in {
+                        let data = mbc.backup_file.ram.clone();
+
+                        mbc.backup_file.is_dirty = false;
+                        mbc.backup_file.last_updated = 0;
+
+                        mbc.backup_file.last_saved = SystemTime::now()
+                            .duration_since(UNIX_EPOCH)
+                            .expect("an error occurred")
+                            .as_millis();
+
+                        let cloud_service = self.cloud_service.clone();
+                        thread::spawn(move || {
+                            cloud_service.lock().unwrap().upload_file(&data, None);
+                        });
+                    } else {
+                        mbc.backup_file.save_file();
+                    }
+                }
+            }
             _=> ()
         }
     }
@@ -1431,7 +2059,9 @@ impl Frontend {
     }
 
     pub fn handle_events(&mut self, cpu: &mut CPU, logged_in: bool, save_name: &str, rom_bytes: &[u8]) {
-        for event in self.event_pump.poll_iter() {
+        let events: Vec<Event> = self.event_pump.poll_iter().collect();
+
+        for event in events {
             self.platform.handle_event(&mut self.imgui, &event);
             match event {
                 Event::Quit { .. } => {
@@ -1501,16 +2131,25 @@ impl Frontend {
                         if let Some(keycode) = keycode {
                             if let Some(button) = self.keyboard_map.get(&keycode) {
                                 self.display_ui = false;
-                                cpu.bus.joypad.press_button(*button);
+                                cpu.bus.joypad.press_button(*button, &mut cpu.bus.IF);
                             } else if keycode == Keycode::G {
 
                                 cpu.bus.ppu.debug_on = !cpu.bus.ppu.debug_on;
                                 cpu.bus.debug_on = !cpu.bus.debug_on;
                                 cpu.debug_on = !cpu.debug_on;
                             } else if keycode == Keycode::F2 {
-                                cpu.bus.ppu.current_palette = (cpu.bus.ppu.current_palette + 1) % cpu.bus.ppu.palette_colors.len();
+                                // CGB colorization comes after the last theme
+                                if cpu.bus.ppu.colorization {
+                                    cpu.bus.ppu.set_colorization(false);
+                                    cpu.bus.ppu.current_palette = 0;
+                                } else if cpu.bus.ppu.current_palette == cpu.bus.ppu.palette_colors.len() - 1 {
+                                    cpu.bus.ppu.set_colorization(true);
+                                } else {
+                                    cpu.bus.ppu.current_palette += 1;
+                                }
 
                                 self.config.current_palette = cpu.bus.ppu.current_palette;
+                                self.config.colorization = cpu.bus.ppu.colorization;
 
                                 // fuck you......... "cannot borrow self as mutable more than once" SHUT THE FUCK UP
                                 Self::write_config_file(&self.config, &mut self.config_file);
@@ -1554,7 +2193,7 @@ impl Frontend {
                         }
                     }
                 }
-                Event::JoyButtonDown { button_idx, .. } => {
+                Event::JoyButtonDown { which, button_idx, .. } => {
                     if let Some(current_input) = self.current_joy_input {
                         if let Some(old_index) = self.button_to_index.get(&current_input) {
                             let old_index_u8 = *old_index as u8;
@@ -1595,8 +2234,12 @@ impl Frontend {
                         Self::write_config_file(&self.config, &mut self.config_file);
                     } else {
                         if let Some(button) = self.button_map.get(&button_idx) {
+                            let player = self.joypad_player(cpu, which);
+
+                            self.controller_presses.insert((which, button_idx), player);
+
                             self.display_ui = false;
-                            cpu.bus.joypad.press_button(*button);
+                            cpu.bus.joypad.press_player_button(player, *button, &mut cpu.bus.IF);
                         } else if button_idx == ButtonIndex::LeftThumbstick as u8 {
                             Self::create_quick_state(cpu, save_name.to_string());
                         } else if button_idx == ButtonIndex::RightThumbstick as u8 {
@@ -1617,20 +2260,34 @@ impl Frontend {
                         }
                     }
                 }
-                Event::JoyButtonUp { button_idx, .. } => {
+                Event::JoyButtonUp { which, button_idx, .. } => {
                     if let Some(button) = self.button_map.get(&button_idx) {
-                        cpu.bus.joypad.release_button(*button);
+                        // release on the player that got the press, MLT_REQ may have changed the player count since
+                        let player = self.controller_presses
+                            .remove(&(which, button_idx))
+                            .unwrap_or_else(|| self.joypad_player(cpu, which));
+
+                        cpu.bus.joypad.release_player_button(player, *button);
                     }
                 }
+                // the left stick stands in for tilting the cartridge in MBC7 games
+                Event::ControllerAxisMotion { axis, value, .. } => {
+                    let tilt = value as f32 / i16::MAX as f32;
+
+                    match axis {
+                        Axis::LeftX => self.tilt.0 = tilt,
+                        Axis::LeftY => self.tilt.1 = tilt,
+                        _ => ()
+                    }
+
+                    cpu.set_tilt(self.tilt.0, self.tilt.1);
+                }
                 Event::JoyDeviceAdded { which, .. } => {
-                    self.controller = match self.game_controller_subsystem.open(which) {
-                        Ok(c) => {
-                            Some(c)
-                        }
+                    match self.game_controller_subsystem.open(which) {
+                        Ok(c) => self.add_player_controller(c),
                         Err(_) => {
                             self.controller_id = Some(which);
                             self.retry_attempts = 0;
-                            None
                         }
                     }
                 }
@@ -1638,4 +2295,53 @@ impl Frontend {
             }
         }
     }
-}
\ No newline at end of file
+
+    pub fn handle_four_player_events(&mut self, cpus: &mut [CPU]) {
+        let events: Vec<Event> = self.event_pump.poll_iter().collect();
+
+        for event in events {
+            match event {
+                Event::Quit { .. } => {
+                    for cpu in cpus.iter_mut() {
+                        self.save_game(&mut cpu.bus.cartridge.mbc, false);
+                    }
+                    exit(0);
+                }
+                Event::KeyDown { keycode: Some(keycode), .. } => {
+                    for (player, cpu) in cpus.iter_mut().enumerate() {
+                        if let Some(button) = self.player_keyboard_map(player).get(&keycode) {
+                            cpu.bus.joypad.press_button(*button, &mut cpu.bus.IF);
+                        }
+                    }
+                }
+                Event::KeyUp { keycode: Some(keycode), .. } => {
+                    for (player, cpu) in cpus.iter_mut().enumerate() {
+                        if let Some(button) = self.player_keyboard_map(player).get(&keycode) {
+                            cpu.bus.joypad.release_button(*button);
+                        }
+                    }
+                }
+                Event::JoyButtonDown { which, button_idx, .. } => {
+                    if let (Some(player), Some(button)) = (self.controller_player(which), self.button_map.get(&button_idx)) {
+                        if let Some(cpu) = cpus.get_mut(player) {
+                            cpu.bus.joypad.press_button(*button, &mut cpu.bus.IF);
+                        }
+                    }
+                }
+                Event::JoyButtonUp { which, button_idx, .. } => {
+                    if let (Some(player), Some(button)) = (self.controller_player(which), self.button_map.get(&button_idx)) {
+                        if let Some(cpu) = cpus.get_mut(player) {
+                            cpu.bus.joypad.release_button(*button);
+                        }
+                    }
+                }
+                Event::JoyDeviceAdded { which, .. } => {
+                    if let Ok(controller) = self.game_controller_subsystem.open(which) {
+                        self.add_player_controller(controller);
+                    }
+                }
+                _ => { /* do nothing */ }
+            }
+        }
+    }
+}
@@ -2,27 +2,67 @@ use std::{
     env,
     fs,
     io::Read,
-    path::Path
+    path::Path,
+    sync::Arc
 };
 
 extern crate gbc_plus;
 
 use frontend::Frontend;
-use gbc_plus::cpu::{bus::apu::NUM_SAMPLES, CPU};
-use ringbuf::{traits::Split, HeapRb};
+use gbc_plus::cpu::{
+    bus::{
+        apu::NUM_SAMPLES,
+        cartridge::mbc::pocket_camera::{image_source::StillImageSource, photos::extract_photos},
+        serial::four_player_adapter::{FourPlayerAdapter, MAX_PLAYERS}
+    },
+    hardware_model::HardwareModel,
+    CPU
+};
+use ringbuf::{storage::Heap, traits::Split, wrap::caching::Caching, HeapRb, SharedRb};
 use zip::ZipArchive;
 
 pub mod frontend;
 pub mod cloud_service;
 
+const CYCLES_PER_SCANLINE: usize = 456;
+
 fn main() {
 
     let args: Vec<String> = env::args().collect();
 
     if args.len() < 2 {
-        panic!("syntax: ./gbc-plus <rom name>");
+        panic!("syntax: ./gbc-plus <rom name> [--players <2-4>] [--model <dmg|mgb|sgb|cgb|agb>] [--boot-rom <path>] [--camera-image <png>] [--export-photos]");
     }
 
+    let num_players = match args.iter().position(|arg| arg == "--players") {
+        Some(index) => args
+            .get(index + 1)
+            .and_then(|players| players.parse::<usize>().ok())
+            .filter(|players| (1..=MAX_PLAYERS).contains(players))
+            .expect("number of players must be between 1 and 4"),
+        None => 1
+    };
+
+    let model = match args.iter().position(|arg| arg == "--model") {
+        Some(index) => args
+            .get(index + 1)
+            .and_then(|model| HardwareModel::from_name(model))
+            .expect("model must be one of dmg, mgb, sgb, cgb or agb"),
+        None => HardwareModel::Cgb
+    };
+
+    let boot_rom = args
+        .iter()
+        .position(|arg| arg == "--boot-rom")
+        .map(|index| fs::read(args.get(index + 1).expect("missing boot ROM path")).expect("couldn't read boot ROM"));
+
+    // what the Pocket Camera sees, a test pattern is used if this isn't given
+    let camera_image = args
+        .iter()
+        .position(|arg| arg == "--camera-image")
+        .map(|index| fs::read(args.get(index + 1).expect("missing camera image path")).expect("couldn't read camera image"))
+        .map(|bytes| StillImageSource::from_png(&bytes).expect("camera image must be a PNG"));
+
     let mut rom_path = args[1].clone();
 
     // let audio_buffer = Arc::new(Mutex::new(VecDeque::new()));
@@ -79,9 +119,34 @@ fn main() {
 
     let save_name = split_vec.pop().unwrap();
 
-    let mut cpu = CPU::new(producer, Some(waveform_producer), Some(filename.clone()), false, true);
+    if args.iter().any(|arg| arg == "--export-photos") {
+        export_photos(&filename);
 
-    let mut frontend = Frontend::new(&mut cpu, consumer, waveform_consumer, save_name.to_string());
+        return;
+    }
+
+    if num_players > 1 {
+        run_four_player_adapter(
+            num_players,
+            &rom_bytes,
+            &filename,
+            save_name,
+            producer,
+            consumer,
+            waveform_producer,
+            waveform_consumer,
+            model,
+            boot_rom.as_deref()
+        );
+    }
+
+    let mut cpu = CPU::new(producer, Some(waveform_producer), Some(filename.clone()), false, true, model);
+
+    if let Some(boot_rom) = &boot_rom {
+        cpu.load_boot_rom(boot_rom);
+    }
+
+    let mut frontend = Frontend::new(&mut cpu, consumer, waveform_consumer, save_name.to_string(), 1);
 
     let cloud_service_clone = frontend.cloud_service.clone();
 
@@ -93,6 +158,10 @@ fn main() {
 
     cpu.load_rom(&rom_bytes, logged_in);
 
+    if let Some(camera_image) = camera_image {
+        cpu.connect_image_source(Box::new(camera_image));
+    }
+
     if logged_in {
         cpu.bus.cartridge.clear_save_file();
 
@@ -116,6 +185,7 @@ fn main() {
 
         frontend.update_rtc(&mut cpu, logged_in, false);
         frontend.check_saves(&mut cpu, logged_in);
+        frontend.check_prints(&filename);
         frontend.render_screen(&mut cpu);
         frontend.render_ui(
             &mut cpu,
@@ -126,6 +196,7 @@ fn main() {
 
         );
         frontend.check_controller_status();
+        frontend.update_rumble(&cpu);
         frontend.end_frame();
 
         frontend.handle_events(&mut cpu, logged_in, &filename, &rom_bytes);
@@ -136,3 +207,102 @@ fn main() {
 
     }
 }
+
+// writes every photo in a Pocket Camera save out as a PNG next to it
+fn export_photos(filename: &str) {
+    let save = match fs::read(filename) {
+        Ok(save) => save,
+        Err(err) => {
+            println!("couldn't read save file {filename}: {err}");
+
+            return;
+        }
+    };
+
+    let photos = extract_photos(&save);
+
+    if photos.is_empty() {
+        println!("{filename} isn't a Pocket Camera save");
+
+        return;
+    }
+
+    let base_name = filename.replace(".sav", "");
+
+    for photo in photos.iter() {
+        let path = format!("{}_photo_{:02}.png", base_name, photo.slot + 1);
+
+        if let Err(err) = fs::write(&path, photo.to_png()) {
+            println!("couldn't save photo to {path}: {err}");
+        }
+    }
+}
+
+/*
+ * runs several instances of the same game linked up through the four player adapter.
+ * the instances are stepped one scanline at a time so they stay in lockstep, and only
+ * player 1's audio is played. cloud saves and save states are disabled in this mode.
+ */
+#[allow(clippy::too_many_arguments)]
+fn run_four_player_adapter(
+    num_players: usize,
+    rom_bytes: &[u8],
+    filename: &str,
+    save_name: &str,
+    producer: Caching<Arc<SharedRb<Heap<f32>>>, true, false>,
+    consumer: Caching<Arc<SharedRb<Heap<f32>>>, false, true>,
+    waveform_producer: Caching<Arc<SharedRb<Heap<f32>>>, true, false>,
+    waveform_consumer: Caching<Arc<SharedRb<Heap<f32>>>, false, true>,
+    model: HardwareModel,
+    boot_rom: Option<&[u8]>
+) -> ! {
+    let mut cpus = vec![CPU::new(producer, Some(waveform_producer), Some(filename.to_string()), false, true, model)];
+
+    for player in 1..num_players {
+        let (producer, _) = HeapRb::<f32>::new(NUM_SAMPLES).split();
+
+        // every player gets their own save file so they don't overwrite each other
+        let player_filename = filename.replace(".sav", &format!("_p{}.sav", player + 1));
+
+        cpus.push(CPU::new(producer, None, Some(player_filename), false, true, model));
+    }
+
+    let mut frontend = Frontend::new(&mut cpus[0], consumer, waveform_consumer, save_name.to_string(), num_players);
+
+    let palette = cpus[0].bus.ppu.current_palette;
+    let colorization = cpus[0].bus.ppu.colorization;
+    let renderer = cpus[0].bus.ppu.renderer;
+
+    let ports = FourPlayerAdapter::create_ports();
+
+    for (cpu, port) in cpus.iter_mut().zip(ports) {
+        cpu.bus.ppu.set_dmg_palette(palette);
+        cpu.bus.ppu.set_colorization(colorization);
+        cpu.bus.ppu.set_renderer(renderer);
+
+        if let Some(boot_rom) = boot_rom {
+            cpu.load_boot_rom(boot_rom);
+        }
+
+        cpu.load_rom(rom_bytes, false);
+        cpu.connect_link_cable(Box::new(port));
+    }
+
+    loop {
+        while !cpus[0].bus.ppu.frame_finished {
+            for cpu in cpus.iter_mut() {
+                cpu.run_for(CYCLES_PER_SCANLINE);
+            }
+        }
+
+        for cpu in cpus.iter_mut() {
+            frontend.check_saves(cpu, false);
+        }
+
+        frontend.render_screens(&mut cpus);
+        frontend.check_controller_status();
+        frontend.end_frame();
+
+        frontend.handle_four_player_events(&mut cpus);
+    }
+}
@@ -1,6 +1,6 @@
 use std::{collections::HashMap, sync::Arc, thread::sleep, time::Duration};
 
-use gbc_plus::cpu::{bus::{cartridge::mbc::MBC, joypad::JoypadButtons}, CPU};
+use gbc_plus::cpu::{bus::{cartridge::mbc::MBC, joypad::JoypadButtons}, hardware_model::HardwareModel, CPU};
 use ringbuf::{
     storage::Heap,
     traits::{
@@ -49,6 +49,9 @@ mod ffi {
         #[swift_bridge(swift_name="loadRtc")]
         fn load_rtc(&mut self, json: String);
 
+        #[swift_bridge(swift_name="loadBootRom")]
+        fn load_boot_rom(&mut self, data: &[u8]);
+
         #[swift_bridge(swift_name="loadRom")]
         fn load_rom(&mut self, data: &[u8]);
 
@@ -61,6 +64,12 @@ mod ffi {
         #[swift_bridge(swift_name="getScreenLength")]
         fn get_screen_length(&self) -> usize;
 
+        #[swift_bridge(swift_name="getScreenWidth")]
+        fn get_screen_width(&self) -> usize;
+
+        #[swift_bridge(swift_name="getScreenHeight")]
+        fn get_screen_height(&self) -> usize;
+
         #[swift_bridge(swift_name="loadSave")]
         fn load_save(&mut self, buf: &[u8]);
 
@@ -109,6 +118,15 @@ mod ffi {
         #[swift_bridge(swift_name="setPalette")]
         fn set_palette(&mut self, value: usize);
 
+        #[swift_bridge(swift_name="setColorization")]
+        fn set_colorization(&mut self, value: bool);
+
+        #[swift_bridge(swift_name="setTilt")]
+        fn set_tilt(&mut self, x: f32, y: f32);
+
+        #[swift_bridge(swift_name="rumbleStrength")]
+        fn rumble_strength(&self) -> f32;
+
         #[swift_bridge(swift_name="clearRtcDirty")]
         fn clear_rtc_dirty(&mut self);
 
@@ -147,7 +165,7 @@ impl GBCMobileEmulator {
         let (producer, consumer) = ringbuffer.split();
 
         Self {
-            cpu: CPU::new(producer, None, None, true, false),
+            cpu: CPU::new(producer, None, None, true, false, HardwareModel::Cgb),
             joypad_map,
             sample_buffer: Vec::new(),
             paused: false,
@@ -160,6 +178,19 @@ impl GBCMobileEmulator {
         self.cpu.bus.ppu.set_dmg_palette(value);
     }
 
+    pub fn set_colorization(&mut self, value: bool) {
+        self.cpu.bus.ppu.set_colorization(value);
+    }
+
+    pub fn set_tilt(&mut self, x: f32, y: f32) {
+        self.cpu.set_tilt(x, y);
+    }
+
+    // 0 to 1, for driving haptics
+    pub fn rumble_strength(&self) -> f32 {
+        self.cpu.rumble_strength()
+    }
+
     pub fn set_paused_audio(&mut self, value: bool) {
         self.cpu.bus.apu.is_paused = value;
     }
@@ -197,6 +228,7 @@ impl GBCMobileEmulator {
     pub fn has_timer(&self) -> bool {
         match &self.cpu.bus.cartridge.mbc {
             MBC::MBC3(mbc) => mbc.has_timer,
+            MBC::HuC3(_) => true,
             _ => false
         }
     }
@@ -204,6 +236,7 @@ impl GBCMobileEmulator {
     pub fn fetch_rtc(&self) -> String {
         match &self.cpu.bus.cartridge.mbc {
             MBC::MBC3(mbc) => mbc.save_rtc_web_mobile(),
+            MBC::HuC3(mbc) => mbc.save_rtc_web_mobile(),
             _ => "".to_string()
         }
     }
@@ -211,6 +244,7 @@ impl GBCMobileEmulator {
     pub fn has_rtc(&self) -> bool {
         match &self.cpu.bus.cartridge.mbc {
             MBC::MBC3(mbc) => mbc.has_timer,
+            MBC::HuC3(_) => true,
             _ => false
         }
     }
@@ -218,10 +252,15 @@ impl GBCMobileEmulator {
     pub fn load_rtc(&mut self, json: String) {
         match &mut self.cpu.bus.cartridge.mbc {
             MBC::MBC3(mbc) => mbc.load_rtc(json),
+            MBC::HuC3(mbc) => mbc.load_rtc(json),
             _ => ()
         }
     }
 
+    pub fn load_boot_rom(&mut self, data: &[u8]) {
+        self.cpu.load_boot_rom(data);
+    }
+
     pub fn load_rom(&mut self, data: &[u8]) {
         self.cpu.load_rom(data, false);
     }
@@ -243,18 +282,32 @@ impl GBCMobileEmulator {
     }
 
     pub fn get_screen(&self) -> *const u8 {
-        self.cpu.bus.ppu.picture.data.as_ptr()
+        self.cpu.picture().data.as_ptr()
     }
 
     pub fn get_screen_length(&self) -> usize {
-        self.cpu.bus.ppu.picture.data.len()
+        self.cpu.picture().data.len()
+    }
+
+    pub fn get_screen_width(&self) -> usize {
+        self.cpu.picture().width
+    }
+
+    pub fn get_screen_height(&self) -> usize {
+        self.cpu.picture().height
     }
 
     pub fn load_save(&mut self, buf: &[u8]) {
         match &mut self.cpu.bus.cartridge.mbc {
             MBC::MBC1(mbc) => mbc.backup_file.load_save(buf),
+            MBC::MBC2(mbc) => mbc.backup_file.load_save(buf),
             MBC::MBC3(mbc) => mbc.backup_file.load_save(buf),
             MBC::MBC5(mbc) => mbc.backup_file.load_save(buf),
+            MBC::MBC7(mbc) => mbc.backup_file.load_save(buf),
+            MBC::MMM01(mbc) => mbc.backup_file.load_save(buf),
+            MBC::HuC1(mbc) => mbc.backup_file.load_save(buf),
+            MBC::HuC3(mbc) => mbc.backup_file.load_save(buf),
+            MBC::PocketCamera(mbc) => mbc.backup_file.load_save(buf),
             _ => ()
         }
     }
@@ -262,8 +315,14 @@ impl GBCMobileEmulator {
     pub fn has_saved(&mut self) -> bool {
         match &mut self.cpu.bus.cartridge.mbc {
             MBC::MBC1(mbc) => mbc.has_saved(),
+            MBC::MBC2(mbc) => mbc.has_saved(),
             MBC::MBC3(mbc) => mbc.has_saved(),
             MBC::MBC5(mbc) => mbc.has_saved(),
+            MBC::MBC7(mbc) => mbc.has_saved(),
+            MBC::MMM01(mbc) => mbc.has_saved(),
+            MBC::HuC1(mbc) => mbc.has_saved(),
+            MBC::HuC3(mbc) => mbc.has_saved(),
+            MBC::PocketCamera(mbc) => mbc.has_saved(),
             _ => false
         }
     }
@@ -271,8 +330,14 @@ impl GBCMobileEmulator {
     pub fn get_save_length(&self) -> usize {
         match &self.cpu.bus.cartridge.mbc {
             MBC::MBC1(mbc) => mbc.backup_file.ram.len(),
+            MBC::MBC2(mbc) => mbc.backup_file.ram.len(),
             MBC::MBC3(mbc) => mbc.backup_file.ram.len(),
             MBC::MBC5(mbc) => mbc.backup_file.ram.len(),
+            MBC::MBC7(mbc) => mbc.backup_file.ram.len(),
+            MBC::MMM01(mbc) => mbc.backup_file.ram.len(),
+            MBC::HuC1(mbc) => mbc.backup_file.ram.len(),
+            MBC::HuC3(mbc) => mbc.backup_file.ram.len(),
+            MBC::PocketCamera(mbc) => mbc.backup_file.ram.len(),
             _ => 0
         }
     }
@@ -280,8 +345,14 @@ impl GBCMobileEmulator {
     pub fn save_game(&mut self) -> *const u8 {
         match &mut self.cpu.bus.cartridge.mbc {
             MBC::MBC1(mbc) => mbc.backup_file.ram.as_ptr(),
+            MBC::MBC2(mbc) => mbc.backup_file.ram.as_ptr(),
             MBC::MBC3(mbc) => mbc.backup_file.ram.as_ptr(),
             MBC::MBC5(mbc) => mbc.backup_file.ram.as_ptr(),
+            MBC::MBC7(mbc) => mbc.backup_file.ram.as_ptr(),
+            MBC::MMM01(mbc) => mbc.backup_file.ram.as_ptr(),
+            MBC::HuC1(mbc) => mbc.backup_file.ram.as_ptr(),
+            MBC::HuC3(mbc) => mbc.backup_file.ram.as_ptr(),
+            MBC::PocketCamera(mbc) => mbc.backup_file.ram.as_ptr(),
             _ => {
                 let vec = Vec::new();
 
@@ -315,7 +386,7 @@ impl GBCMobileEmulator {
     pub fn update_input(&mut self, button: usize, pressed: bool) {
         if pressed {
             if let Some(joypad_button) = self.joypad_map.get(&button) {
-                self.cpu.bus.joypad.press_button(*joypad_button);
+                self.cpu.bus.joypad.press_button(*joypad_button, &mut self.cpu.bus.IF);
             }
         } else {
             if let Some(joypad_button) = self.joypad_map.get(&button) {
@@ -327,6 +398,7 @@ impl GBCMobileEmulator {
     pub fn is_rtc_dirty(&self) -> bool {
         match &self.cpu.bus.cartridge.mbc {
             MBC::MBC3(mbc3) => mbc3.is_dirty,
+            MBC::HuC3(huc3) => huc3.is_dirty,
             _ => false
         }
     }
@@ -334,6 +406,7 @@ impl GBCMobileEmulator {
     pub fn clear_rtc_dirty(&mut self) {
         match &mut self.cpu.bus.cartridge.mbc {
             MBC::MBC3(mbc3) => mbc3.is_dirty = false,
+            MBC::HuC3(huc3) => huc3.is_dirty = false,
             _ => ()
         }
     }
@@ -1,13 +1,20 @@
 use std::sync::Arc;
 
 use apu::{sound_panning_register::SoundPanningRegister, APU};
-use cartridge::{mbc::MBC, Cartridge};
+use cartridge::{mbc::MBC, unlicensed, Cartridge};
+use infrared::Infrared;
 use joypad::Joypad;
-use ppu::PPU;
+use oam_dma::{MemoryBus, OamDma};
+use ppu::{lcd_control_register::LCDControlRegister, LCDMode, PPU};
 use interrupt_register::InterruptRegister;
 use ringbuf::{storage::Heap, wrap::caching::Caching, SharedRb};
 use serde::{Deserialize, Serialize};
+use serial::Serial;
+use sgb::Sgb;
 use timer::Timer;
+use vram_dma::{HdmaMode, VramDma, BLOCK_CYCLES, BLOCK_SIZE};
+
+use super::hardware_model::HardwareModel;
 
 pub mod interrupt_register;
 pub mod ppu;
@@ -15,16 +22,18 @@ pub mod cartridge;
 pub mod apu;
 pub mod timer;
 pub mod joypad;
+pub mod serial;
+pub mod infrared;
+pub mod oam_dma;
+pub mod vram_dma;
+pub mod sgb;
 
 const CARTRIDGE_TYPE_ADDR: usize = 0x147;
 const ROM_SIZE_ADDR: usize = 0x148;
 const RAM_SIZE_ADDR: usize = 0x149;
 
-#[derive(Copy, Clone, PartialEq)]
-pub enum HdmaMode {
-    General,
-    Hblank
-}
+// the CPU sits for 2050 m-cycles while switching speeds
+const SPEED_SWITCH_CYCLES: usize = 2050 * 4;
 
 #[derive(Serialize, Deserialize)]
 pub struct Bus {
@@ -38,16 +47,24 @@ pub struct Bus {
     pub apu: APU,
     pub joypad: Joypad,
     pub timer: Timer,
+    pub serial: Serial,
+    pub infrared: Infrared,
+    pub oam_dma: OamDma,
     wram_bank: usize,
     pub double_speed: bool,
-    pub vram_dma_source: u16,
-    pub vram_dma_destination: u16,
-    hdma_hblank: bool,
-    hdma_length: isize,
-    curr_dma_source: u16,
-    curr_dma_dest: u16,
-    hdma_finished: bool,
-    pub debug_on: bool
+    speed_switch_armed: bool,
+    pub key0: u8,
+    key0_locked: bool,
+    undocumented: [u8; 4],
+    pub vram_dma: VramDma,
+    pub sgb: Sgb,
+    pub debug_on: bool,
+    pub model: HardwareModel,
+    // like the cartridge ROM, the boot ROM isn't part of save states
+    #[serde(skip_serializing)]
+    #[serde(skip_deserializing)]
+    boot_rom: Vec<u8>,
+    boot_rom_mapped: bool
 }
 
 impl Bus {
@@ -56,7 +73,8 @@ impl Bus {
         waveform_producer: Option<Caching<Arc<SharedRb<Heap<f32>>>, true, false>>,
         save_path: Option<String>,
         is_ios: bool,
-        is_desktop: bool
+        is_desktop: bool,
+        model: HardwareModel
     ) -> Self {
         Self {
             cartridge: Cartridge::new(save_path, is_desktop),
@@ -78,75 +96,269 @@ impl Bus {
             apu: APU::new(producer, waveform_producer, is_ios),
             joypad: Joypad::new(),
             timer: Timer::new(),
+            serial: Serial::new(),
+            infrared: Infrared::new(),
+            oam_dma: OamDma::new(),
             wram_bank: 1,
             double_speed: false,
-            vram_dma_destination: 0,
-            vram_dma_source: 0,
-            hdma_hblank: false,
-            hdma_length: 0,
-            curr_dma_source: 0,
-            curr_dma_dest: 0,
-            hdma_finished: false,
-            debug_on: false
+            speed_switch_armed: false,
+            key0: 0,
+            key0_locked: true,
+            undocumented: [0; 4],
+            vram_dma: VramDma::new(),
+            sgb: Sgb::new(),
+            debug_on: false,
+            model,
+            boot_rom: Vec::new(),
+            boot_rom_mapped: false
+        }
+    }
+
+    pub fn load_boot_rom(&mut self, bytes: &[u8]) {
+        self.boot_rom = bytes.to_vec();
+        self.boot_rom_mapped = true;
+
+        // KEY0 stays writable until the boot ROM is unmapped
+        self.key0_locked = false;
+    }
+
+    pub fn boot_rom(&self) -> &[u8] {
+        &self.boot_rom
+    }
+
+    pub fn reload_boot_rom(&mut self, bytes: Vec<u8>) {
+        self.boot_rom = bytes;
+    }
+
+    pub fn is_boot_rom_mapped(&self) -> bool {
+        self.boot_rom_mapped
+    }
+
+    // 0x0100-0x01ff is always the cartridge header, even with a CGB boot ROM mapped
+    fn boot_rom_contains(&self, address: u16) -> bool {
+        self.boot_rom_mapped && !(0x100..=0x1ff).contains(&address) && (address as usize) < self.boot_rom.len()
+    }
+
+    /*
+     * writing 0xff50 unmaps the boot ROM for good. on CGB, this is also when KEY0 gets locked,
+     * and if the boot ROM set KEY0 to DMG compatibility mode the CGB features switch off.
+     */
+    fn unmap_boot_rom(&mut self) {
+        self.key0_locked = true;
+
+        if !self.boot_rom_mapped {
+            return;
+        }
+
+        self.boot_rom_mapped = false;
+
+        if self.model.is_cgb() && self.key0 & 0x4 != 0 {
+            self.ppu.cgb_mode = false;
+
+            // the boot ROM just picked colors for the game, so show them like a real CGB would
+            self.ppu.load_compatibility_palettes();
+            self.ppu.set_colorization(true);
         }
     }
 
+    // IO registers as the boot ROM would have left them
+    pub fn skip_boot_rom(&mut self) {
+        self.ppu.bgp.write(0xfc);
+
+        // DIV reads 0xab right after the DMG boot ROM finishes
+        if matches!(self.model, HardwareModel::Dmg | HardwareModel::Mgb) {
+            self.timer.counter = 0xabcc;
+        }
+    }
+
+    /*
+     * cycles are always in CPU clocks. the timer and serial port run off the CPU clock,
+     * so they speed up in double speed mode, while the PPU, APU and HDMA stay on the
+     * system clock and get half as many cycles.
+     */
     pub fn tick(&mut self, cycles: usize) {
-        let actual_cycles = if self.double_speed { cycles / 2 } else { cycles };
+        self.tick_components(cycles, false);
+    }
+
+    // HBlank DMA doesn't run while the CPU is halted
+    pub fn tick_halted(&mut self, cycles: usize) {
+        self.tick_components(cycles, true);
+    }
+
+    fn tick_components(&mut self, cycles: usize, cpu_halted: bool) {
+        let actual_cycles = self.to_system_cycles(cycles);
+
+        let previous_mode = self.ppu.mode;
+        let frame_finished = self.ppu.frame_finished;
 
-        if self.hdma_hblank {
-            self.ppu.hdma_init = true;
+        self.timer.tick(cycles, &mut self.IF, self.double_speed);
+        self.serial.tick(cycles, &mut self.IF);
+        self.ppu.tick(actual_cycles, &mut self.IF);
+        self.apu.tick(actual_cycles);
+        self.cartridge.tick(cycles);
+
+        self.apu.cartridge_sample = self.cartridge.speaker_sample();
+
+        self.clock_frame_sequencer();
+
+        self.tick_oam_dma(cycles);
+
+        let entered_hblank = previous_mode != LCDMode::HBlank && self.ppu.mode == LCDMode::HBlank;
+
+        if entered_hblank && self.vram_dma.hblank_active() && !cpu_halted {
+            self.transfer_vram_dma_block();
         }
 
-        let hdma_cycles = if self.hdma_hblank && self.ppu.entering_hblank(actual_cycles) {
-            self.do_hdma_hblank()
-        } else {
-            0
-        };
+        if !frame_finished && self.ppu.frame_finished {
+            self.cartridge.finish_frame();
+
+            if self.model == HardwareModel::Sgb {
+                self.sgb.finish_frame(&self.ppu.shades);
+            }
+        }
+    }
+
+    // OAM DMA copies a byte every m-cycle, so it runs off the CPU clock
+    fn tick_oam_dma(&mut self, cycles: usize) {
+        for _ in 0..cycles / 4 {
+            if let Some((source, offset)) = self.oam_dma.tick() {
+                let value = self.oam_dma_read8(source);
+
+                self.oam_dma.last_value = value;
+
+                self.ppu.write_oam(0xfe00 + offset, value);
+            }
+        }
+    }
+
+    // the DMA isn't affected by the PPU locking VRAM, and sources past 0xdfff read from WRAM
+    fn oam_dma_read8(&mut self, address: u16) -> u8 {
+        match address {
+            0x8000..=0x9fff => {
+                let bank = if self.ppu.cgb_mode { self.ppu.vram_bank as usize } else { 0 };
 
-        self.timer.tick(cycles + hdma_cycles, &mut self.IF);
-        self.ppu.tick(actual_cycles + hdma_cycles, &mut self.IF);
-        self.apu.tick(actual_cycles + hdma_cycles);
+                self.ppu.vram[bank][(address - 0x8000) as usize]
+            }
+            0xe000..=0xffff => self.mem_read8(address - 0x2000),
+            _ => self.mem_read8(address)
+        }
     }
 
-    fn do_hdma_hblank(&mut self) -> usize {
-        let actual_destination = self.curr_dma_dest | 0x8000;
+    // returns true if a CPU access to the address collides with a running OAM DMA
+    fn oam_dma_conflict(&self, address: u16) -> bool {
+        if !self.oam_dma.is_active() {
+            return false;
+        }
 
-        for i in 0..0x10 {
-            let value = self.mem_read8(self.curr_dma_source + i as u16);
-            self.mem_write8(actual_destination + i as u16, value);
+        match MemoryBus::from_address(address, self.ppu.cgb_mode) {
+            Some(MemoryBus::Oam) => true,
+            Some(bus) => Some(bus) == self.oam_dma.source_bus(self.ppu.cgb_mode),
+            None => false
         }
+    }
 
-        self.curr_dma_source += 0x10;
-        self.curr_dma_dest += 0x10;
+    /*
+     * reads coming from the CPU. while OAM DMA is running, reading from the same bus the DMA
+     * is using returns whatever byte the DMA is copying, and OAM itself reads 0xff.
+     */
+    pub fn cpu_read8(&mut self, address: u16) -> u8 {
+        if self.oam_dma_conflict(address) {
+            return if (0xfe00..=0xfeff).contains(&address) { 0xff } else { self.oam_dma.last_value };
+        }
 
-        self.hdma_length -= 0x10;
+        self.mem_read8(address)
+    }
 
-        self.ppu.hdma_init = false;
+    // writes from the CPU to a bus in use by OAM DMA get dropped
+    pub fn cpu_write8(&mut self, address: u16, value: u8) {
+        if !self.oam_dma_conflict(address) {
+            self.mem_write8(address, value);
+        }
+    }
 
-        if self.hdma_length == 0 {
-            self.hdma_length = 0;
-            self.hdma_hblank = false;
-            self.hdma_finished = true;
+    fn clock_frame_sequencer(&mut self) {
+        for _ in 0..self.timer.take_frame_sequencer_ticks() {
+            self.apu.clock_frame_sequencer();
+        }
+    }
+
+    fn to_system_cycles(&self, cycles: usize) -> usize {
+        if self.double_speed { cycles / 2 } else { cycles }
+    }
+
+    fn to_cpu_cycles(&self, cycles: usize) -> usize {
+        if self.double_speed { cycles * 2 } else { cycles }
+    }
+
+    // called by STOP, returns whether a speed switch was armed through KEY1
+    pub fn switch_speed(&mut self) -> bool {
+        if !self.ppu.cgb_mode || !self.speed_switch_armed {
+            return false;
+        }
+
+        self.double_speed = !self.double_speed;
+        self.speed_switch_armed = false;
+
+        self.mem_write8(0xff04, 0);
+
+        // the CPU is stopped during the switch, but everything else keeps going one m-cycle at a time
+        for _ in 0..SPEED_SWITCH_CYCLES / 4 {
+            self.tick_halted(4);
+        }
+
+        true
+    }
+
+    // the CPU is stalled while a block gets copied, but everything else keeps running
+    fn transfer_vram_dma_block(&mut self) {
+        let (source, destination) = self.vram_dma.next_block();
+
+        // each pair of bytes takes one m-cycle in normal speed or two in double speed
+        let pair_cycles = self.to_cpu_cycles(BLOCK_CYCLES) / (BLOCK_SIZE as usize / 2);
+
+        for i in (0..BLOCK_SIZE).step_by(2) {
+            for offset in i..i + 2 {
+                let value = self.mem_read8(source.wrapping_add(offset));
+
+                self.mem_write8(destination + offset, value);
+            }
+
+            for _ in 0..pair_cycles / 4 {
+                self.tick(4);
+            }
+        }
+    }
+
+    fn write_hdma_control(&mut self, value: u8) {
+        match self.vram_dma.write_control(value) {
+            Some(HdmaMode::General) => while self.vram_dma.remaining_blocks() > 0 {
+                self.transfer_vram_dma_block();
+            }
+            // with the LCD off there aren't any HBlanks, so the first block gets copied right away
+            Some(HdmaMode::Hblank) if !self.ppu.lcdc.contains(LCDControlRegister::LCD_AND_PPU_ENABLE) => {
+                self.transfer_vram_dma_block();
+            }
+            _ => ()
         }
-        32
     }
 
     pub fn mem_read8(&mut self, address: u16) -> u8 {
         match address {
+            0x0000..=0x08ff if self.boot_rom_contains(address) => self.boot_rom[address as usize],
             0x0000..=0x7fff => match self.cartridge.mbc {
                 MBC::None => self.cartridge.rom[address as usize],
                 _ => self.cartridge.mbc_read8(address)
             }
+            0xa000..=0xbfff if self.cartridge.is_infrared_mapped() => self.infrared.read_cartridge(),
             0xa000..=0xbfff => self.cartridge.mbc_read8(address),
             0x8000..=0x9fff => if self.ppu.cgb_mode {
-                 if self.ppu.vram_enabled {
+                 if self.ppu.vram_accessible() {
                     self.ppu.vram[self.ppu.vram_bank as usize][(address - 0x8000) as usize]
                 } else {
                     0xff
                 }
             } else {
-                if self.ppu.vram_enabled {
+                if self.ppu.vram_accessible() {
                     self.ppu.vram[0][(address - 0x8000) as usize]
                 } else {
                     0xff
@@ -161,12 +373,15 @@ impl Bus {
             // echo ram, for some reason zelda oracle of seasons tries to access it.
             // TODO: properly emulate? probably not worth it haha
             0xe000..=0xfdff => 0xff,
+            0xfe00..=0xfe9f => if self.ppu.oam_accessible() { self.ppu.read_oam(address) } else { 0xff },
+            0xfea0..=0xfeff => if self.ppu.oam_accessible() { 0 } else { 0xff },
             0xff00 => self.joypad.read(),
-            0xff01..=0xff02 => 0, // serial ports, can safely ignore (hopefully!)
-            0xff04 => self.timer.div,
+            0xff01 => self.serial.sb,
+            0xff02 => self.serial.read_control(self.ppu.cgb_mode),
+            0xff04 => self.timer.div(),
             0xff05 => self.timer.tima,
             0xff06 => self.timer.tma,
-            0xff07 => self.timer.tac.bits(),
+            0xff07 => self.timer.read_tac(),
             0xff0f => self.IF.bits(),
             0xff10 => self.apu.channel1.nrx0.as_ref().unwrap().read(),
             0xff11 => self.apu.channel1.read_length(),
@@ -214,19 +429,35 @@ impl Bus {
             0xff43 => self.ppu.scx,
             0xff44 => self.ppu.line_y,
             0xff45 => self.ppu.lyc,
+            0xff46 => self.oam_dma.read(),
             0xff47 => self.ppu.bgp.read(),
             0xff48 => self.ppu.obp0.read(),
             0xff49 => self.ppu.obp1.read(),
             0xff4a => self.ppu.wy,
             0xff4b => self.ppu.wx,
-            0xff4d => (self.double_speed as u8) << 7,
+            0xff4c => if self.ppu.cgb_mode && !self.key0_locked { self.key0 } else { 0xff },
+            0xff4d => if self.ppu.cgb_mode {
+                (self.double_speed as u8) << 7 | 0x7e | self.speed_switch_armed as u8
+            } else {
+                0xff
+            },
             0xff4f => self.ppu.vram_bank as u8,
-            0xff55 => if self.hdma_length == 0 && self.hdma_finished { 0xff } else { ((self.hdma_length - 1) / 0x10) as u8 },
+            0xff51..=0xff54 => 0xff,
+            0xff55 => if self.ppu.cgb_mode { self.vram_dma.read_status() } else { 0xff },
+            0xff56 => self.infrared.read(self.ppu.cgb_mode),
             0xff68 => self.ppu.bgpi.read(),
-            0xff69 => self.ppu.bgpd_byte,
+            0xff69 => self.ppu.read_bg_palette_color(),
             0xff6a => self.ppu.obpi.read(),
-            0xff6b => self.ppu.obpd_byte,
+            0xff6b => self.ppu.read_obj_palette_color(),
+            0xff6c => if self.ppu.cgb_mode { 0xfe | self.ppu.coordinate_priority as u8 } else { 0xff },
             0xff70 => self.wram_bank as u8,
+            // these exist on CGB hardware even when running a DMG game, except 0xff74
+            0xff72 | 0xff73 => if self.model.is_cgb() { self.undocumented[(address - 0xff72) as usize] } else { 0xff },
+            0xff74 => if self.ppu.cgb_mode { self.undocumented[2] } else { 0xff },
+            // only bits 4-6 are writable
+            0xff75 => if self.model.is_cgb() { 0x8f | self.undocumented[3] } else { 0xff },
+            0xff76 => if self.model.is_cgb() { self.apu.read_pcm12() } else { 0xff },
+            0xff77 => if self.model.is_cgb() { self.apu.read_pcm34() } else { 0xff },
             0xff80..=0xfffe => self.hram[(address - 0xff80) as usize],
             0xffff => self.ie.bits(),
             _ => {
@@ -238,15 +469,21 @@ impl Bus {
 
     pub fn mem_read16(&mut self, address: u16) -> u16 {
         match address {
+            0x0000..=0x08ff if self.boot_rom_contains(address) => self.mem_read8(address) as u16 | (self.mem_read8(address + 1) as u16) << 8,
             0x0000..=0x7fff => match self.cartridge.mbc {
                 MBC::None => unsafe { *(&self.cartridge.rom[address as usize] as *const u8 as *const u16) },
                 _ => self.cartridge.mbc_read16(address)
             }
             0x8000..=0x9fff => if self.ppu.cgb_mode {
-                if self.ppu.vram_enabled { unsafe { *(&self.ppu.vram[self.ppu.vram_bank as usize][(address - 0x8000) as usize] as *const u8 as *const u16) } } else { 0xff }
+                if self.ppu.vram_accessible() { unsafe { *(&self.ppu.vram[self.ppu.vram_bank as usize][(address - 0x8000) as usize] as *const u8 as *const u16) } } else { 0xff }
             } else {
-                if self.ppu.vram_enabled { unsafe { *(&self.ppu.vram[0][(address - 0x8000) as usize] as *const u8 as *const u16) } } else { 0xff }
+                if self.ppu.vram_accessible() { unsafe { *(&self.ppu.vram[0][(address - 0x8000) as usize] as *const u8 as *const u16) } } else { 0xff }
             },
+            0xa000..=0xbfff if self.cartridge.is_infrared_mapped() => {
+                let value = self.infrared.read_cartridge() as u16;
+
+                value | value << 8
+            }
             0xa000..=0xbfff => self.cartridge.mbc_read16(address),
             0xc000..=0xcfff => unsafe { *(&self.wram[0][(address - 0xc000) as usize] as *const u8 as *const u16) },
             0xd000..=0xdfff => if self.ppu.cgb_mode {
@@ -265,14 +502,15 @@ impl Bus {
     pub fn mem_write16(&mut self, address: u16, value: u16) {
         match address {
             0x8000..=0x9fff => if self.ppu.cgb_mode {
-                if self.ppu.vram_enabled {
+                if self.ppu.vram_accessible() {
                     unsafe { *(&mut self.ppu.vram[self.ppu.vram_bank as usize][(address - 0x8000) as usize] as *mut u8 as *mut u16) = value }
                 }
             } else {
-                if self.ppu.vram_enabled {
+                if self.ppu.vram_accessible() {
                     unsafe { *(&mut self.ppu.vram[0][(address - 0x8000) as usize] as *mut u8 as *mut u16) = value }
                 }
             }
+            0xa000..=0xbfff if self.cartridge.is_infrared_mapped() => self.infrared.write_cartridge(value as u8),
             0xa000..=0xbfff | 0x0000..=0x7fff => self.cartridge.mbc_write16(address, value),
             0xc000..=0xcfff => unsafe { *(&mut self.wram[0][(address - 0xc000) as usize] as *mut u8 as *mut u16) = value },
             0xd000..=0xdfff => if self.ppu.cgb_mode {
@@ -287,21 +525,19 @@ impl Bus {
         }
     }
 
-    pub fn handle_dma(&mut self, value: u8) {
-        let address = (value as u16) << 8;
+    pub fn check_header(&mut self, logged_in: bool) {
+        // unlicensed carts can't be trusted to have a valid header, so they get picked out first
+        if let Some(mapper) = unlicensed::detect(&self.cartridge.rom) {
+            self.cartridge.set_unlicensed(mapper, self.boot_rom_mapped);
 
-        for i in 0..0xa0 {
-            let value = self.mem_read8(address + i);
-            self.mem_write8(0xfe00 + i, value);
+            return;
         }
 
-        self.tick( 640);
-    }
+        let header_offset = self.cartridge.header_offset();
 
-    pub fn check_header(&mut self, logged_in: bool) {
-        let cartridge_type = self.cartridge.rom[CARTRIDGE_TYPE_ADDR];
+        let cartridge_type = self.cartridge.rom[header_offset + CARTRIDGE_TYPE_ADDR];
 
-        let rom_size_header = self.cartridge.rom[ROM_SIZE_ADDR];
+        let rom_size_header = self.cartridge.rom[header_offset + ROM_SIZE_ADDR];
 
         self.cartridge.rom_size = match rom_size_header {
             0 => 0x8000,
@@ -316,7 +552,7 @@ impl Bus {
             _ => panic!("unsupported rom size: {rom_size_header}")
         };
 
-        let ram_size_header = self.cartridge.rom[RAM_SIZE_ADDR];
+        let ram_size_header = self.cartridge.rom[header_offset + RAM_SIZE_ADDR];
 
         self.cartridge.ram_size = match ram_size_header {
             0 => 0,
@@ -332,6 +568,11 @@ impl Bus {
             0x01 => self.cartridge.set_mbc1(false, false),
             0x02 => self.cartridge.set_mbc1(true, false),
             0x03 => self.cartridge.set_mbc1(true, true),
+            0x05 => self.cartridge.set_mbc2(false),
+            0x06 => self.cartridge.set_mbc2(true),
+            0x0b => self.cartridge.set_mmm01(false),
+            0x0c => self.cartridge.set_mmm01(false),
+            0x0d => self.cartridge.set_mmm01(true),
             0x0f => self.cartridge.set_mbc3(false, true, true, logged_in),
             0x10 => self.cartridge.set_mbc3(true, true, true, logged_in),
             0x11 => self.cartridge.set_mbc3(false, false, false, logged_in),
@@ -343,65 +584,24 @@ impl Bus {
             0x1c => self.cartridge.set_mbc5(false, false, true),
             0x1d => self.cartridge.set_mbc5(true, false, true),
             0x1e => self.cartridge.set_mbc5(true, true, true),
+            0x22 => self.cartridge.set_mbc7(),
+            0xfc => self.cartridge.set_pocket_camera(),
+            0xfe => self.cartridge.set_huc3(logged_in),
+            0xff => self.cartridge.set_huc1(),
             _ => panic!("unsupported mbc type: 0x{:x}", cartridge_type)
         }
     }
 
-    fn start_hdma(&mut self, value: u8) {
-        let length = (((value as u16) & 0x7f) + 1) * 0x10;
-
-        let mode = match (value >> 7) & 0x1 {
-            0 => HdmaMode::General,
-            1 => HdmaMode::Hblank,
-            _ => unreachable!()
-        };
-
-        self.vram_dma_destination &= !(0xf);
-        self.vram_dma_source &= !(0xf);
-        self.vram_dma_destination &= 0x1fff;
-
-        if mode == HdmaMode::General {
-            self.do_hdma_general(length);
-        } else {
-            self.restart_hdma_hblank(length);
-        }
-    }
-
-    fn do_hdma_general(&mut self, length: u16) {
-        self.hdma_hblank = false;
-        // do transfer immediately
-        let actual_address = self.vram_dma_destination | 0x8000;
-
-        let cycles = (length / 0x10) * 32;
-
-        for i in 0..length {
-            let value = self.mem_read8(self.vram_dma_source + i);
-
-            self.mem_write8(actual_address + i, value);
-        }
-
-        self.tick(if self.double_speed { cycles * 2 } else { cycles } as usize);
-    }
-
-    fn restart_hdma_hblank(&mut self, length: u16) {
-        self.hdma_finished = false;
-        self.hdma_hblank = true;
-        self.ppu.hdma_init = true;
-        self.hdma_length = length as isize;
-
-        self.curr_dma_source = self.vram_dma_source;
-        self.curr_dma_dest = self.vram_dma_destination;
-    }
-
     pub fn mem_write8(&mut self, address: u16, value: u8) {
         match address {
+            0xa000..=0xbfff if self.cartridge.is_infrared_mapped() => self.infrared.write_cartridge(value),
             0x0000..=0x7fff | 0xa000..=0xbfff => self.cartridge.mbc_write8(address, value),
             0x8000..=0x9fff => if self.ppu.cgb_mode {
-                if self.ppu.vram_enabled {
+                if self.ppu.vram_accessible() {
                     self.ppu.vram[self.ppu.vram_bank as usize][(address - 0x8000) as usize] = value;
                 }
             } else {
-                if self.ppu.vram_enabled {
+                if self.ppu.vram_accessible() {
                     self.ppu.vram[0][(address - 0x8000) as usize] = value;
                 }
             },
@@ -411,13 +611,26 @@ impl Bus {
             } else {
                 self.wram[1][(address - 0xd000) as usize] = value
             }
-            0xfe00..=0xfe9f => self.ppu.write_oam(address, value),
+            0xfe00..=0xfe9f => if self.ppu.oam_accessible() {
+                self.ppu.write_oam(address, value);
+            }
             0xfea0..=0xfeff => (), // ignore, this area is restricted but some games may still write to it
-            0xff00 => self.joypad.write(value),
-            0xff01..=0xff02 => (), // Serial ports, ignore!
-            0xff04 => self.timer.div = 0,
+            0xff00 => {
+                self.joypad.write(value, &mut self.IF);
+
+                if self.model == HardwareModel::Sgb {
+                    self.sgb.write_joypad(value, &mut self.joypad);
+                }
+            }
+            0xff01 => self.serial.write_data(value),
+            0xff02 => self.serial.write_control(value, self.ppu.cgb_mode),
+            0xff04 => {
+                self.timer.reset_div(self.double_speed);
+
+                self.clock_frame_sequencer();
+            }
             0xff05 => self.timer.write_tima(value),
-            0xff06 => self.timer.tma = value,
+            0xff06 => self.timer.write_tma(value),
             0xff07 => self.timer.update_tac(value),
             0xff0f => self.IF = InterruptRegister::from_bits_retain(value),
             0xff10 => if self.apu.nr52.audio_on { self.apu.channel1.write_sweep(value) },
@@ -469,32 +682,52 @@ impl Bus {
             0xff27..=0xff2f => (),
             0xff30..=0xff3f => self.apu.channel3.wave_ram[(address - 0xff30) as usize] = value,
             0xff40 => self.ppu.update_lcdc(value),
-            0xff41 => self.ppu.update_stat(value, &mut self.IF),
+            0xff41 => self.ppu.update_stat(value, self.model.is_cgb(), &mut self.IF),
             0xff42 => self.ppu.scy = value,
             0xff43 => self.ppu.scx = value,
             0xff44 => (),
             0xff45 => self.ppu.update_lyc(value, &mut self.IF),
-            0xff46 => self.handle_dma(value),
+            0xff46 => self.oam_dma.write(value),
             0xff47 => self.ppu.bgp.write(value),
             0xff48 => self.ppu.obp0.write(value),
             0xff49 => self.ppu.obp1.write(value),
             0xff4a => self.ppu.wy = value,
             0xff4b => self.ppu.wx = value,
-            0xff4d => if value & 0x1 == 1 {
-                self.double_speed = !self.double_speed
+            // KEY0 can only be written by the boot ROM, it gets locked once the boot ROM is unmapped
+            0xff4c => if self.ppu.cgb_mode && !self.key0_locked {
+                self.key0 = value;
+            }
+            0xff4d => if self.ppu.cgb_mode {
+                self.speed_switch_armed = value & 0x1 == 1;
             }
             0xff4f => self.ppu.set_vram_bank(value & 0x1),
-            0xff51 => self.vram_dma_source = (self.vram_dma_source & 0xff) | (value as u16) << 8,
-            0xff52 => self.vram_dma_source = (self.vram_dma_source & 0xff00) | value as u16,
-            0xff53 => self.vram_dma_destination = (self.vram_dma_destination & 0xff) | (value as u16) << 8,
-            0xff54 => self.vram_dma_destination = (self.vram_dma_destination & 0xff00) | value as u16,
-            0xff55 => self.start_hdma(value),
-            0xff56 => (), // Infrared comms port for GBC, TODO
+            0xff50 => self.unmap_boot_rom(),
+            0xff51 => self.vram_dma.write_source_high(value),
+            0xff52 => self.vram_dma.write_source_low(value),
+            0xff53 => self.vram_dma.write_destination_high(value),
+            0xff54 => self.vram_dma.write_destination_low(value),
+            0xff55 => if self.ppu.cgb_mode {
+                self.write_hdma_control(value);
+            }
+            0xff56 => self.infrared.write(value, self.ppu.cgb_mode),
             0xff68 => self.ppu.bgpi.write(value),
             0xff69 => self.ppu.update_bg_palette_color(value),
             0xff6a => self.ppu.obpi.write(value),
             0xff6b => self.ppu.update_obj_palette_color(value),
+            0xff6c => if self.ppu.cgb_mode {
+                self.ppu.coordinate_priority = value & 0x1 == 1;
+            }
             0xff70 => self.wram_bank = if value == 0 { 1 } else { (value & 0x7) as usize },
+            0xff72 | 0xff73 => if self.model.is_cgb() {
+                self.undocumented[(address - 0xff72) as usize] = value;
+            }
+            0xff74 => if self.ppu.cgb_mode {
+                self.undocumented[2] = value;
+            }
+            0xff75 => if self.model.is_cgb() {
+                self.undocumented[3] = value & 0x70;
+            }
+            0xff76 | 0xff77 => (),
             0xff7f => (), // ignore this one, tetris tries to write to here for some reason.
             0xff80..=0xfffe => self.hram[(address - 0xff80) as usize] = value,
             0xffff => self.ie = InterruptRegister::from_bits_retain(value),
@@ -105,7 +105,7 @@ impl<const IS_CHANNEL1: bool> PulseChannel<IS_CHANNEL1> {
         }
     }
 
-    pub fn generate_sample(&mut self) -> f32 {
+    pub fn generate_sample(&self) -> f32 {
         if self.enabled {
             let bit = DUTY_PATTERNS[self.nrx1.wave_duty as usize][self.duty_step];
 
@@ -20,7 +20,9 @@ pub mod channels;
 
 pub const TICKS_PER_SAMPLE: usize = CLOCK_SPEED / 44100;
 pub const NUM_SAMPLES: usize = 8192 * 2;
-pub const HZ_512: usize = CLOCK_SPEED / 512;
+
+// how loud the cartridge's speaker is next to the game boy's own channels
+const CARTRIDGE_VOLUME: f32 = 0.25;
 
 #[derive(Serialize, Deserialize)]
 pub struct APU {
@@ -38,8 +40,9 @@ pub struct APU {
     #[serde(skip_serializing)]
     #[serde(skip_deserializing)]
     pub waveform_producer: Option<Caching<Arc<SharedRb<Heap<f32>>>, true, false>>,
-    sequencer_cycles: usize,
     pub sequencer_step: usize,
+    // the cartridge speaker doesn't go through NR50, so it gets mixed in after the volume
+    pub cartridge_sample: f32,
     is_ios: bool,
     pub is_paused: bool
 }
@@ -59,8 +62,8 @@ impl APU {
             channel3: Channel3::new(),
             channel4: Channel4::new(),
             cycles: 0,
-            sequencer_cycles: 0,
             sequencer_step: 0,
+            cartridge_sample: 0.0,
             producer: Some(producer),
             waveform_producer: waveform_producer,
             is_ios,
@@ -87,8 +90,10 @@ impl APU {
         let left_volume = if self.nr50.left_volume == 0 { 1 } else { self.nr50.left_volume };
         let right_volume = if self.nr50.right_volume == 0 { 1 } else { self.nr50.right_volume };
 
-        let mut left_sample = sample * left_volume as f32 / 7.0;
-        let mut right_sample = sample * right_volume as f32 / 7.0;
+        let cartridge_sample = self.cartridge_sample * CARTRIDGE_VOLUME;
+
+        let mut left_sample = sample * left_volume as f32 / 7.0 + cartridge_sample;
+        let mut right_sample = sample * right_volume as f32 / 7.0 + cartridge_sample;
 
         left_sample = left_sample.clamp(-1.0, 1.0);
         right_sample = right_sample.clamp(-1.0, 1.0);
@@ -121,8 +126,11 @@ impl APU {
         let left_volume = if self.nr50.left_volume == 0 { 1 } else { self.nr50.left_volume };
         let right_volume = if self.nr50.right_volume == 0 { 1 } else { self.nr50.right_volume };
 
-        let mut left_sample = sample * left_volume as f32 / 7.0;
-        let mut right_sample = sample * right_volume as f32 / 7.0;
+        // same as the channels, the speaker only gets the positive half of its wave
+        let cartridge_sample = self.cartridge_sample.max(0.0) * CARTRIDGE_VOLUME;
+
+        let mut left_sample = sample * left_volume as f32 / 7.0 + cartridge_sample;
+        let mut right_sample = sample * right_volume as f32 / 7.0 + cartridge_sample;
 
         left_sample = left_sample.clamp(0.0, 1.0);
         right_sample = right_sample.clamp(0.0, 1.0);
@@ -155,7 +163,8 @@ impl APU {
     // 7      -           Clock       -
     // ---------------------------------------
     // Rate   256 Hz      64 Hz       128 Hz
-    fn update_frame_sequencer(&mut self) {
+    // clocked by the timer whenever DIV bit 4 falls, or bit 5 in double speed mode
+    pub fn clock_frame_sequencer(&mut self) {
         match self.sequencer_step {
             0 => self.clock_lengths(),
             1 => (),
@@ -177,6 +186,15 @@ impl APU {
         self.sequencer_step = (self.sequencer_step + 1) & 0x7;
     }
 
+    // PCM12 and PCM34 hold the current digital output of each channel, 4 bits apiece
+    pub fn read_pcm12(&self) -> u8 {
+        self.channel1.generate_sample() as u8 | (self.channel2.generate_sample() as u8) << 4
+    }
+
+    pub fn read_pcm34(&self) -> u8 {
+        self.channel3.generate_sample() as u8 | (self.channel4.generate_sample() as u8) << 4
+    }
+
     pub fn read_channel_status(&self) -> u8 {
         let audio_on = self.nr52.read();
 
@@ -248,19 +266,12 @@ impl APU {
 
     pub fn tick(&mut self, cycles: usize) {
         self.cycles += cycles;
-        self.sequencer_cycles += cycles;
 
         self.channel1.tick(cycles, self.sequencer_step);
         self.channel2.tick(cycles, self.sequencer_step);
         self.channel3.tick(cycles, self.sequencer_step);
         self.channel4.tick(cycles, self.sequencer_step);
 
-        if self.sequencer_cycles >= HZ_512 {
-            self.sequencer_cycles -= HZ_512;
-
-            self.update_frame_sequencer();
-        }
-
         if self.cycles >= TICKS_PER_SAMPLE {
             self.cycles -= TICKS_PER_SAMPLE;
             if self.is_ios { self.generate_ios_samples(); } else { self.generate_samples(); }
@@ -1,8 +1,29 @@
-use mbc::{mbc1::MBC1, mbc3::MBC3, mbc5::MBC5, MBC};
+use mbc::{huc1::HuC1, huc3::HuC3, m161::M161, mbc1::MBC1, mbc2::MBC2, mbc3::MBC3, mbc5::MBC5, mbc7::MBC7, mmm01::MMM01, pocket_camera::{image_source::ImageSource, PocketCamera}, sachen::{unscramble_address, Sachen}, wisdom_tree::WisdomTree, MBC};
 use serde::{Deserialize, Serialize};
+use unlicensed::UnlicensedMapper;
 
 pub mod backup_file;
 pub mod mbc;
+pub mod unlicensed;
+
+const TITLE_START: usize = 0x134;
+const TITLE_END: usize = 0x144;
+const CGB_FLAG_ADDR: usize = 0x143;
+const NEW_LICENSEE_ADDR: usize = 0x144;
+const SGB_FLAG_ADDR: usize = 0x146;
+const OLD_LICENSEE_ADDR: usize = 0x14b;
+const HEADER_CHECKSUM_ADDR: usize = 0x14d;
+const NINTENDO_LOGO_ADDR: usize = 0x104;
+const CARTRIDGE_TYPE_ADDR: usize = 0x147;
+
+// MBC1M multicarts have a game every 256KB, each with its own header
+const MULTICART_GAME_SIZE: usize = 0x40000;
+
+const NINTENDO_LOGO: [u8; 0x30] = [
+    0xce, 0xed, 0x66, 0x66, 0xcc, 0x0d, 0x00, 0x0b, 0x03, 0x73, 0x00, 0x83, 0x00, 0x0c, 0x00, 0x0d,
+    0x00, 0x08, 0x11, 0x1f, 0x88, 0x89, 0x00, 0x0e, 0xdc, 0xcc, 0x6e, 0xe6, 0xdd, 0xdd, 0xd9, 0x99,
+    0xbb, 0xbb, 0x67, 0x63, 0x6e, 0x0e, 0xec, 0xcc, 0xdd, 0xdc, 0x99, 0x9f, 0xbb, 0xb9, 0x33, 0x3e
+];
 
 #[derive(Serialize, Deserialize)]
 pub struct Cartridge {
@@ -29,12 +50,25 @@ impl Cartridge {
     }
 
     pub fn set_mbc1(&mut self, ram: bool, battery: bool) {
+        let multicart = self.is_mbc1_multicart();
+
         self.mbc = MBC::MBC1(
             MBC1::new(
                 ram,
                 battery,
                 self.rom_size,
                 self.ram_size,
+                multicart,
+                self.save_path.clone(),
+                self.is_desktop
+            )
+        );
+    }
+
+    pub fn set_mbc2(&mut self, battery: bool) {
+        self.mbc = MBC::MBC2(
+            MBC2::new(
+                battery,
                 self.save_path.clone(),
                 self.is_desktop
             )
@@ -70,14 +104,140 @@ impl Cartridge {
         );
     }
 
+    pub fn set_mbc7(&mut self) {
+        self.mbc = MBC::MBC7(
+            MBC7::new(
+                self.save_path.clone(),
+                self.is_desktop
+            )
+        );
+    }
+
+    pub fn set_mmm01(&mut self, battery: bool) {
+        self.mbc = MBC::MMM01(
+            MMM01::new(
+                self.ram_size,
+                battery,
+                self.save_path.clone(),
+                self.is_desktop
+            )
+        );
+    }
+
+    // unlicensed carts don't have RAM, and their ROM size comes from the file instead of the header
+    pub fn set_unlicensed(&mut self, mapper: UnlicensedMapper, locked: bool) {
+        self.rom_size = self.rom.len();
+        self.ram_size = 0;
+
+        match mapper {
+            UnlicensedMapper::WisdomTree => self.mbc = MBC::WisdomTree(WisdomTree::new()),
+            UnlicensedMapper::SachenMmc1 => self.mbc = MBC::Sachen(Sachen::new(false, locked)),
+            UnlicensedMapper::SachenMmc2 => self.mbc = MBC::Sachen(Sachen::new(true, locked)),
+            UnlicensedMapper::M161 => self.mbc = MBC::M161(M161::new())
+        }
+    }
+
+    pub fn set_huc1(&mut self) {
+        self.mbc = MBC::HuC1(
+            HuC1::new(
+                self.ram_size,
+                self.save_path.clone(),
+                self.is_desktop
+            )
+        );
+    }
+
+    pub fn set_huc3(&mut self, logged_in: bool) {
+        self.mbc = MBC::HuC3(
+            HuC3::new(
+                self.ram_size,
+                self.save_path.clone(),
+                self.is_desktop,
+                logged_in
+            )
+        );
+    }
+
+    pub fn set_pocket_camera(&mut self) {
+        self.mbc = MBC::PocketCamera(
+            PocketCamera::new(
+                self.save_path.clone(),
+                self.is_desktop
+            )
+        );
+    }
+
+    // the Pocket Camera times its captures, and MBC5 keeps track of how long the rumble motor is on
+    pub fn tick(&mut self, cycles: usize) {
+        match &mut self.mbc {
+            MBC::PocketCamera(camera) => camera.tick(cycles),
+            MBC::MBC5(mbc5) => mbc5.tick(cycles),
+            MBC::HuC3(huc3) => huc3.tick(cycles),
+            _ => ()
+        }
+    }
+
+    // HuC3 has its own speaker, which plays on top of the game boy's
+    pub fn speaker_sample(&self) -> f32 {
+        match &self.mbc {
+            MBC::HuC3(huc3) => huc3.speaker_sample(),
+            _ => 0.0
+        }
+    }
+
+    pub fn finish_frame(&mut self) {
+        if let MBC::MBC5(mbc5) = &mut self.mbc {
+            mbc5.finish_frame();
+        }
+    }
+
+    // how hard the rumble motor shook last frame, from 0 to 1
+    pub fn rumble_strength(&self) -> f32 {
+        match &self.mbc {
+            MBC::MBC5(mbc5) => mbc5.rumble_strength,
+            _ => 0.0
+        }
+    }
+
+    pub fn connect_image_source(&mut self, image_source: Box<dyn ImageSource>) {
+        if let MBC::PocketCamera(camera) = &mut self.mbc {
+            camera.connect(image_source);
+        }
+    }
+
+    pub fn disconnect_image_source(&mut self) -> Option<Box<dyn ImageSource>> {
+        match &mut self.mbc {
+            MBC::PocketCamera(camera) => Some(camera.disconnect()),
+            _ => None
+        }
+    }
+
+    // HuC1 and HuC3 can swap their infrared port in over RAM
+    pub fn is_infrared_mapped(&self) -> bool {
+        match &self.mbc {
+            MBC::HuC1(huc1) => huc1.is_infrared_mapped(),
+            MBC::HuC3(huc3) => huc3.is_infrared_mapped(),
+            _ => false
+        }
+    }
+
     pub fn mbc_write8(&mut self, address: u16, value: u8) {
         // if let Some(mbc) = &mut self.mbc {
         //     mbc.write(address, value)
         // }
         match &mut self.mbc {
             MBC::MBC1(mbc1) => mbc1.write(address, value),
+            MBC::MBC2(mbc2) => mbc2.write(address, value),
             MBC::MBC3(mbc3) => mbc3.write(address, value),
             MBC::MBC5(mbc5) => mbc5.write(address, value),
+            MBC::MBC7(mbc7) => mbc7.write(address, value),
+            MBC::MMM01(mmm01) => mmm01.write(address, value),
+            MBC::HuC1(huc1) => huc1.write(address, value),
+            MBC::HuC3(huc3) => huc3.write(address, value),
+            MBC::PocketCamera(camera) => camera.write(address, value),
+            MBC::WisdomTree(mbc) => mbc.write(address, value),
+            MBC::Sachen(mbc) => mbc.write(address, value),
+            MBC::M161(mbc) => mbc.write(address, value),
             _ => ()
         }
     }
@@ -85,8 +245,17 @@ impl Cartridge {
     pub fn mbc_read8(&mut self, address: u16) -> u8 {
         match &mut self.mbc {
             MBC::MBC1(mbc1) => mbc1.read(address, &self.rom),
+            MBC::MBC2(mbc2) => mbc2.read(address, &self.rom),
             MBC::MBC3(mbc3) => mbc3.read(address, &self.rom),
             MBC::MBC5(mbc5) => mbc5.read(address, &self.rom),
+            MBC::MBC7(mbc7) => mbc7.read(address, &self.rom),
+            MBC::MMM01(mmm01) => mmm01.read(address, &self.rom),
+            MBC::HuC1(huc1) => huc1.read(address, &self.rom),
+            MBC::HuC3(huc3) => huc3.read(address, &self.rom),
+            MBC::PocketCamera(camera) => camera.read(address, &self.rom),
+            MBC::WisdomTree(mbc) => mbc.read(address, &self.rom),
+            MBC::Sachen(mbc) => mbc.read(address, &self.rom),
+            MBC::M161(mbc) => mbc.read(address, &self.rom),
             _ => 0xff
         }
     }
@@ -94,8 +263,17 @@ impl Cartridge {
     pub fn mbc_read16(&mut self, address: u16) -> u16 {
         match &mut self.mbc {
             MBC::MBC1(mbc1) => mbc1.read16(address, &self.rom),
+            MBC::MBC2(mbc2) => mbc2.read16(address, &self.rom),
             MBC::MBC3(mbc3) => mbc3.read16(address, &self.rom),
             MBC::MBC5(mbc5) => mbc5.read16(address, &self.rom),
+            MBC::MBC7(mbc7) => mbc7.read16(address, &self.rom),
+            MBC::MMM01(mmm01) => mmm01.read16(address, &self.rom),
+            MBC::HuC1(huc1) => huc1.read16(address, &self.rom),
+            MBC::HuC3(huc3) => huc3.read16(address, &self.rom),
+            MBC::PocketCamera(camera) => camera.read16(address, &self.rom),
+            MBC::WisdomTree(mbc) => mbc.read16(address, &self.rom),
+            MBC::Sachen(mbc) => mbc.read16(address, &self.rom),
+            MBC::M161(mbc) => mbc.read16(address, &self.rom),
             _ => 0xff
         }
     }
@@ -106,8 +284,17 @@ impl Cartridge {
         // }
         match &mut self.mbc {
             MBC::MBC1(mbc1) => mbc1.write16(address, value),
+            MBC::MBC2(mbc2) => mbc2.write16(address, value),
             MBC::MBC3(mbc3) => mbc3.write16(address, value),
             MBC::MBC5(mbc5) => mbc5.write16(address, value),
+            MBC::MBC7(mbc7) => mbc7.write16(address, value),
+            MBC::MMM01(mmm01) => mmm01.write16(address, value),
+            MBC::HuC1(huc1) => huc1.write16(address, value),
+            MBC::HuC3(huc3) => huc3.write16(address, value),
+            MBC::PocketCamera(camera) => camera.write16(address, value),
+            MBC::WisdomTree(mbc) => mbc.write16(address, value),
+            MBC::Sachen(mbc) => mbc.write16(address, value),
+            MBC::M161(mbc) => mbc.write16(address, value),
             _ => ()
         }
     }
@@ -115,18 +302,96 @@ impl Cartridge {
     pub fn clear_save_file(&mut self) {
         match &mut self.mbc {
             MBC::MBC1(mbc) => mbc.backup_file.file = None,
+            MBC::MBC2(mbc) => mbc.backup_file.file = None,
             MBC::MBC3(mbc) => mbc.backup_file.file = None,
             MBC::MBC5(mbc) => mbc.backup_file.file = None,
-            MBC::None => ()
+            MBC::MBC7(mbc) => mbc.backup_file.file = None,
+            MBC::MMM01(mbc) => mbc.backup_file.file = None,
+            MBC::HuC1(mbc) => mbc.backup_file.file = None,
+            MBC::HuC3(mbc) => mbc.backup_file.file = None,
+            MBC::PocketCamera(mbc) => mbc.backup_file.file = None,
+            MBC::None | MBC::WisdomTree(_) | MBC::Sachen(_) | MBC::M161(_) => ()
         }
     }
 
     pub fn load_save(&mut self, bytes: &[u8]) {
         match &mut self.mbc {
             MBC::MBC1(mbc) => mbc.backup_file.load_save(bytes),
+            MBC::MBC2(mbc) => mbc.backup_file.load_save(bytes),
             MBC::MBC3(mbc) => mbc.backup_file.load_save(bytes),
             MBC::MBC5(mbc) => mbc.backup_file.load_save(bytes),
-            MBC::None => ()
+            MBC::MBC7(mbc) => mbc.backup_file.load_save(bytes),
+            MBC::MMM01(mbc) => mbc.backup_file.load_save(bytes),
+            MBC::HuC1(mbc) => mbc.backup_file.load_save(bytes),
+            MBC::HuC3(mbc) => mbc.backup_file.load_save(bytes),
+            MBC::PocketCamera(mbc) => mbc.backup_file.load_save(bytes),
+            MBC::None | MBC::WisdomTree(_) | MBC::Sachen(_) | MBC::M161(_) => ()
+        }
+    }
+
+    /*
+     * MMM01 multicarts boot into a menu at the end of the ROM, so that's where their real
+     * header is. the header at the start belongs to the first game.
+     */
+    pub fn header_offset(&self) -> usize {
+        let offset = self.rom.len().saturating_sub(0x8000);
+
+        match self.rom.get(offset + CARTRIDGE_TYPE_ADDR) {
+            Some(0x0b..=0x0d) if offset > 0 => offset,
+            _ => 0
+        }
+    }
+
+    fn has_nintendo_logo(&self, offset: usize) -> bool {
+        let address = offset + NINTENDO_LOGO_ADDR;
+
+        self.rom.get(address..address + NINTENDO_LOGO.len()) == Some(&NINTENDO_LOGO[..])
+    }
+
+    /*
+     * MBC1M multicarts don't say so in their header, but they're always 1MB and every game
+     * on them has its own header, so the logo shows up again past the menu.
+     */
+    fn is_mbc1_multicart(&self) -> bool {
+        self.rom_size == 0x100000 && (1..4).any(|game| self.has_nintendo_logo(game * MULTICART_GAME_SIZE))
+    }
+
+    pub fn title(&self) -> &[u8] {
+        &self.rom[TITLE_START..TITLE_END]
+    }
+
+    // the sum of the title bytes, which the CGB boot ROM uses to look up games
+    pub fn title_checksum(&self) -> u8 {
+        self.title().iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte))
+    }
+
+    // games published by Nintendo, either through the old licensee code or the new one
+    pub fn nintendo_licensee(&self) -> bool {
+        match self.rom[OLD_LICENSEE_ADDR] {
+            0x01 => true,
+            0x33 => &self.rom[NEW_LICENSEE_ADDR..NEW_LICENSEE_ADDR + 2] == b"01",
+            _ => false
+        }
+    }
+
+    // SGB features need both the SGB flag and the old licensee code set to 0x33
+    pub fn sgb_support(&self) -> bool {
+        self.rom[SGB_FLAG_ADDR] == 0x03 && self.rom[OLD_LICENSEE_ADDR] == 0x33
+    }
+
+    pub fn cgb_flag(&self) -> u8 {
+        self.header_byte(CGB_FLAG_ADDR)
+    }
+
+    pub fn header_checksum(&self) -> u8 {
+        self.header_byte(HEADER_CHECKSUM_ADDR)
+    }
+
+    // Sachen carts scramble their header, so it has to be read through the same address lines their mapper swaps
+    fn header_byte(&self, address: usize) -> u8 {
+        match unlicensed::detect(&self.rom) {
+            Some(UnlicensedMapper::SachenMmc1 | UnlicensedMapper::SachenMmc2) => self.rom[unscramble_address(address as u16) as usize],
+            _ => self.rom[address]
         }
     }
 }
\ No newline at end of file
@@ -0,0 +1,138 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::cpu::bus::cartridge::backup_file::BackupFile;
+
+/*
+ * Hudson's HuC1. banking works a lot like MBC1, but there's no RAM enable. instead, writing
+ * 0xe to 0x0000-0x1fff maps the infrared port over 0xa000-0xbfff, and anything else maps RAM back.
+ */
+#[derive(Serialize, Deserialize)]
+pub struct HuC1 {
+    rom_bank: u8,
+    ram_bank: u8,
+    ram_size: usize,
+    infrared_mapped: bool,
+    pub backup_file: BackupFile
+}
+
+impl HuC1 {
+    pub fn check_save(&mut self, is_cloud: bool) -> bool {
+        let min_diff = if is_cloud { 1500 } else { 500 };
+
+        let current_time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("an error occurred")
+            .as_millis();
+
+        let last_updated = self.backup_file.last_updated;
+
+        if self.backup_file.is_dirty &&
+            current_time > last_updated &&
+            last_updated != 0
+        {
+            let diff = current_time - last_updated;
+            if diff >= min_diff {
+                self.backup_file.last_updated = 0;
+                return true;
+            }
+        }
+
+        false
+    }
+
+    pub fn has_saved(&mut self) -> bool {
+        let return_val = self.backup_file.is_dirty;
+
+        self.backup_file.is_dirty = false;
+
+        return_val
+    }
+
+    // reads and writes to 0xa000-0xbfff go to the bus's infrared port while this is set
+    pub fn is_infrared_mapped(&self) -> bool {
+        self.infrared_mapped
+    }
+
+    pub fn read(&mut self, address: u16, rom: &[u8]) -> u8 {
+        match address {
+            0x0000..=0x3fff => rom[address as usize],
+            0x4000..=0x7fff => {
+                let actual_address = self.get_rom_address(address);
+
+                rom[actual_address % rom.len()]
+            }
+            0xa000..=0xbfff if self.ram_size > 0 => {
+                let actual_address = self.get_ram_address(address);
+
+                self.backup_file.read8(actual_address)
+            }
+            0xa000..=0xbfff => 0xff,
+            _ => panic!("invalid address given: 0x{:x}", address)
+        }
+    }
+
+    pub fn read16(&mut self, address: u16, rom: &[u8]) -> u16 {
+        match address {
+            0x0000..=0x3fff => {
+                unsafe { *(&rom[address as usize] as *const u8 as *const u16) }
+            }
+            0x4000..=0x7fff => {
+                let actual_address = self.get_rom_address(address);
+
+                unsafe { *(&rom[actual_address % rom.len()] as *const u8 as *const u16) }
+            }
+            0xa000..=0xbfff if self.ram_size > 0 => {
+                let actual_address = self.get_ram_address(address);
+
+                self.backup_file.read16(actual_address)
+            }
+            0xa000..=0xbfff => 0xffff,
+            _ => panic!("invalid address given: 0x{:x}", address)
+        }
+    }
+
+    pub fn write(&mut self, address: u16, value: u8) {
+        match address {
+            0x0000..=0x1fff => self.infrared_mapped = value & 0xf == 0xe,
+            0x2000..=0x3fff => self.rom_bank = if value & 0x3f == 0 { 1 } else { value & 0x3f },
+            0x4000..=0x5fff => self.ram_bank = value & 0x3,
+            0xa000..=0xbfff if self.ram_size > 0 => {
+                let actual_address = self.get_ram_address(address);
+
+                self.backup_file.write8(actual_address, value);
+            }
+            _ => ()
+        }
+    }
+
+    pub fn write16(&mut self, address: u16, value: u16) {
+        match address {
+            0xa000..=0xbfff if self.ram_size > 0 => {
+                let actual_address = self.get_ram_address(address);
+
+                self.backup_file.write16(actual_address, value);
+            }
+            _ => self.write(address, value as u8)
+        }
+    }
+
+    pub fn new(ram_size: usize, save_path: Option<String>, is_desktop: bool) -> Self {
+        Self {
+            rom_bank: 1,
+            ram_bank: 0,
+            ram_size,
+            infrared_mapped: false,
+            backup_file: BackupFile::new(save_path, ram_size, ram_size > 0, is_desktop)
+        }
+    }
+
+    fn get_ram_address(&self, address: u16) -> usize {
+        ((address & 0x1fff) as usize | (self.ram_bank as usize) << 13) % self.ram_size
+    }
+
+    fn get_rom_address(&self, address: u16) -> usize {
+        (address as usize) & 0x3fff | (self.rom_bank as usize) << 14
+    }
+}
@@ -0,0 +1,385 @@
+use std::{fs::File, io::{Seek, SeekFrom, Write}, time::{SystemTime, UNIX_EPOCH}};
+
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+
+use crate::cpu::{bus::cartridge::backup_file::BackupFile, CLOCK_SPEED};
+
+use super::mbc3::RtcFile;
+
+const MINUTES_PER_DAY: i64 = 1440;
+
+// the RTC has 256 nibbles of memory, the first 7 are the clock itself
+const RTC_MEMORY_SIZE: usize = 0x100;
+
+// selected by writing to 0x0000-0x1fff
+const MODE_RAM_READ_ONLY: u8 = 0x0;
+const MODE_RAM: u8 = 0xa;
+const MODE_RTC_COMMAND: u8 = 0xb;
+const MODE_RTC_RESPONSE: u8 = 0xc;
+const MODE_RTC_SEMAPHORE: u8 = 0xd;
+const MODE_INFRARED: u8 = 0xe;
+
+// RTC commands, the upper nibble of what gets written in MODE_RTC_COMMAND
+const READ_AND_INCREMENT: u8 = 0x1;
+const WRITE: u8 = 0x2;
+const WRITE_AND_INCREMENT: u8 = 0x3;
+const SET_ADDRESS_LOW: u8 = 0x4;
+const SET_ADDRESS_HIGH: u8 = 0x5;
+const EXTENDED: u8 = 0x6;
+
+// extended commands
+const STATUS: u8 = 0x2;
+const PLAY_TONE: u8 = 0xe;
+
+// the tone generator plays whichever tone was last written here
+const TONE_ADDRESS: usize = 0x26;
+
+/*
+ * the speaker's actual pitches aren't documented, so every tone is a half second square wave
+ * beep, each a semitone above the last starting from A5
+ */
+const TONE_CYCLES: usize = CLOCK_SPEED / 2;
+const TONE_BASE_FREQUENCY: f32 = 880.0;
+
+/*
+ * Hudson's HuC3. on top of ROM and RAM banking it has an RTC, a speaker and an infrared port,
+ * all of which are reached through 0xa000-0xbfff depending on the mode written to 0x0000-0x1fff.
+ * the RTC only counts minutes and days, and talks to the game a nibble at a time through commands.
+ */
+#[derive(Serialize, Deserialize)]
+pub struct HuC3 {
+    rom_bank: u8,
+    ram_bank: u8,
+    ram_size: usize,
+    mode: u8,
+    minutes: u16,
+    days: u16,
+    // unix time of the last minute the clock counted
+    last_tick: i64,
+    access_index: u8,
+    access_flags: u8,
+    rtc_response: u8,
+    rtc_memory: Box<[u8]>,
+    tone_cycles: usize,
+    tone_period: usize,
+    tone_phase: usize,
+    #[serde(skip_serializing)]
+    #[serde(skip_deserializing)]
+    pub rtc_file: Option<File>,
+    pub is_dirty: bool,
+    pub backup_file: BackupFile
+}
+
+impl HuC3 {
+    pub fn check_save(&mut self, is_cloud: bool) -> bool {
+        let min_diff = if is_cloud { 1500 } else { 500 };
+
+        let current_time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("an error occurred")
+            .as_millis();
+
+        let last_updated = self.backup_file.last_updated;
+
+        if self.backup_file.is_dirty &&
+            current_time > last_updated &&
+            last_updated != 0
+        {
+            let diff = current_time - last_updated;
+            if diff >= min_diff {
+                self.backup_file.last_updated = 0;
+                return true;
+            }
+        }
+
+        false
+    }
+
+    pub fn has_saved(&mut self) -> bool {
+        let return_val = self.backup_file.is_dirty;
+
+        self.backup_file.is_dirty = false;
+
+        return_val
+    }
+
+    // reads and writes to 0xa000-0xbfff go to the bus's infrared port while this is set
+    pub fn is_infrared_mapped(&self) -> bool {
+        self.mode == MODE_INFRARED
+    }
+
+    /*
+     * the clock gets stored the same way as MBC3's, as the time it would have started counting
+     * from. HuC3 has no halt or day carry, so those are always false.
+     */
+    fn rtc_json(&self) -> RtcFile {
+        let elapsed_minutes = self.days as i64 * MINUTES_PER_DAY + self.minutes as i64;
+
+        let mut rtc_json = RtcFile::new((self.last_tick - elapsed_minutes * 60) as usize, false, false, 0);
+
+        rtc_json.memory = self.rtc_memory.to_vec();
+
+        rtc_json
+    }
+
+    fn load_rtc_file(&mut self, rtc: RtcFile) {
+        self.set_start(rtc.timestamp as i64);
+
+        // older RTC files only have the clock
+        if rtc.memory.len() == RTC_MEMORY_SIZE {
+            self.rtc_memory.copy_from_slice(&rtc.memory);
+        }
+    }
+
+    pub fn save_rtc_web_mobile(&self) -> String {
+        let rtc_json = self.rtc_json();
+
+        serde_json::to_string::<RtcFile>(&rtc_json).unwrap_or("".to_string())
+    }
+
+    pub fn load_rtc(&mut self, json: String) {
+        if let Ok(result) = serde_json::from_str::<RtcFile>(&json) {
+            self.load_rtc_file(result);
+        }
+    }
+
+    pub fn save_rtc(&mut self) {
+        let rtc_json = self.rtc_json();
+
+        if let Some(file) = &mut self.rtc_file {
+            if let Ok(result) = serde_json::to_string::<RtcFile>(&rtc_json) {
+                file.set_len(0).unwrap();
+                file.seek(SeekFrom::Start(0)).unwrap();
+                file.write_all(result.as_bytes()).unwrap();
+            }
+        }
+    }
+
+    fn set_start(&mut self, start: i64) {
+        let elapsed_minutes = (Local::now().timestamp() - start).max(0) / 60;
+
+        self.minutes = (elapsed_minutes % MINUTES_PER_DAY) as u16;
+        self.days = (elapsed_minutes / MINUTES_PER_DAY) as u16;
+        self.last_tick = start + elapsed_minutes * 60;
+    }
+
+    // catches the clock up on however many minutes went by since it was last looked at
+    fn update_clock(&mut self) {
+        let elapsed_minutes = (Local::now().timestamp() - self.last_tick) / 60;
+
+        if elapsed_minutes > 0 {
+            let minutes = self.minutes as i64 + elapsed_minutes;
+
+            self.minutes = (minutes % MINUTES_PER_DAY) as u16;
+            self.days = self.days.wrapping_add((minutes / MINUTES_PER_DAY) as u16);
+            self.last_tick += elapsed_minutes * 60;
+        }
+    }
+
+    pub fn read(&mut self, address: u16, rom: &[u8]) -> u8 {
+        match address {
+            0x0000..=0x3fff => rom[address as usize],
+            0x4000..=0x7fff => {
+                let actual_address = self.get_rom_address(address);
+
+                rom[actual_address % rom.len()]
+            }
+            0xa000..=0xbfff => match self.mode {
+                MODE_RAM | MODE_RAM_READ_ONLY if self.ram_size > 0 => {
+                    let actual_address = self.get_ram_address(address);
+
+                    self.backup_file.read8(actual_address)
+                }
+                MODE_RAM | MODE_RAM_READ_ONLY => 0xff,
+                MODE_RTC_RESPONSE if self.access_flags == STATUS => 0x1,
+                MODE_RTC_RESPONSE => self.rtc_response,
+                // the RTC is always done with the last command by the time the game checks
+                MODE_RTC_SEMAPHORE => 0x1,
+                _ => 0x1
+            }
+            _ => panic!("invalid address given: 0x{:x}", address)
+        }
+    }
+
+    pub fn read16(&mut self, address: u16, rom: &[u8]) -> u16 {
+        match address {
+            0x0000..=0x3fff => {
+                unsafe { *(&rom[address as usize] as *const u8 as *const u16) }
+            }
+            0x4000..=0x7fff => {
+                let actual_address = self.get_rom_address(address);
+
+                unsafe { *(&rom[actual_address % rom.len()] as *const u8 as *const u16) }
+            }
+            0xa000..=0xbfff => self.read(address, rom) as u16 | (self.read(address + 1, rom) as u16) << 8,
+            _ => panic!("invalid address given: 0x{:x}", address)
+        }
+    }
+
+    pub fn write(&mut self, address: u16, value: u8) {
+        match address {
+            0x0000..=0x1fff => self.mode = value & 0xf,
+            0x2000..=0x3fff => self.rom_bank = if value & 0x7f == 0 { 1 } else { value & 0x7f },
+            0x4000..=0x5fff => self.ram_bank = value & 0xf,
+            0xa000..=0xbfff => match self.mode {
+                MODE_RAM if self.ram_size > 0 => {
+                    let actual_address = self.get_ram_address(address);
+
+                    self.backup_file.write8(actual_address, value);
+                }
+                MODE_RTC_COMMAND => self.rtc_command(value),
+                _ => ()
+            }
+            _ => ()
+        }
+    }
+
+    pub fn write16(&mut self, address: u16, value: u16) {
+        match address {
+            0xa000..=0xbfff => {
+                self.write(address, value as u8);
+                self.write(address + 1, (value >> 8) as u8);
+            }
+            _ => self.write(address, value as u8)
+        }
+    }
+
+    fn rtc_command(&mut self, value: u8) {
+        let argument = value & 0xf;
+
+        match (value >> 4) & 0x7 {
+            READ_AND_INCREMENT => {
+                self.rtc_response = self.read_rtc_memory(self.access_index);
+                self.access_index = self.access_index.wrapping_add(1);
+            }
+            WRITE => self.write_rtc_memory(self.access_index, argument),
+            WRITE_AND_INCREMENT => {
+                self.write_rtc_memory(self.access_index, argument);
+                self.access_index = self.access_index.wrapping_add(1);
+            }
+            SET_ADDRESS_LOW => self.access_index = (self.access_index & 0xf0) | argument,
+            SET_ADDRESS_HIGH => self.access_index = (self.access_index & 0xf) | argument << 4,
+            EXTENDED => {
+                self.access_flags = argument;
+
+                if argument == PLAY_TONE {
+                    self.play_tone(self.rtc_memory[TONE_ADDRESS]);
+                }
+            }
+            _ => ()
+        }
+    }
+
+    // addresses 0-2 are the minutes of the day and 3-6 are the day counter, both least significant nibble first
+    fn read_rtc_memory(&mut self, index: u8) -> u8 {
+        self.update_clock();
+
+        match index {
+            0..=2 => ((self.minutes >> (index * 4)) & 0xf) as u8,
+            3..=6 => ((self.days >> ((index - 3) * 4)) & 0xf) as u8,
+            _ => self.rtc_memory[index as usize]
+        }
+    }
+
+    fn write_rtc_memory(&mut self, index: u8, value: u8) {
+        self.update_clock();
+
+        match index {
+            0..=2 => {
+                let shift = index * 4;
+
+                self.minutes = (self.minutes & !(0xf << shift)) | (value as u16) << shift;
+                self.is_dirty = true;
+            }
+            3..=6 => {
+                let shift = (index - 3) * 4;
+
+                self.days = (self.days & !(0xf << shift)) | (value as u16) << shift;
+                self.is_dirty = true;
+            }
+            _ => {
+                self.rtc_memory[index as usize] = value;
+                self.is_dirty = true;
+            }
+        }
+    }
+
+    fn play_tone(&mut self, tone: u8) {
+        let frequency = TONE_BASE_FREQUENCY * 2f32.powf(tone as f32 / 12.0);
+
+        self.tone_period = (CLOCK_SPEED as f32 / frequency) as usize;
+        self.tone_phase = 0;
+        self.tone_cycles = TONE_CYCLES;
+    }
+
+    pub fn tick(&mut self, cycles: usize) {
+        if self.tone_cycles == 0 {
+            return;
+        }
+
+        self.tone_cycles = self.tone_cycles.saturating_sub(cycles);
+        self.tone_phase = (self.tone_phase + cycles) % self.tone_period;
+    }
+
+    // the speaker's output, silent unless a tone is playing
+    pub fn speaker_sample(&self) -> f32 {
+        if self.tone_cycles == 0 {
+            0.0
+        } else if self.tone_phase < self.tone_period / 2 {
+            1.0
+        } else {
+            -1.0
+        }
+    }
+
+    pub fn new(
+        ram_size: usize,
+        save_path: Option<String>,
+        is_desktop: bool,
+        logged_in: bool
+    ) -> Self {
+        let (rtc_file, rtc) = match &save_path {
+            Some(save_path) if !logged_in => {
+                let (rtc_file, rtc) = RtcFile::open(save_path);
+
+                (Some(rtc_file), rtc)
+            }
+            _ => (None, None)
+        };
+
+        let mut huc3 = Self {
+            rom_bank: 1,
+            ram_bank: 0,
+            ram_size,
+            mode: MODE_RAM_READ_ONLY,
+            minutes: 0,
+            days: 0,
+            last_tick: Local::now().timestamp(),
+            access_index: 0,
+            access_flags: 0,
+            rtc_response: 0,
+            rtc_memory: vec![0; RTC_MEMORY_SIZE].into_boxed_slice(),
+            tone_cycles: 0,
+            tone_period: 1,
+            tone_phase: 0,
+            rtc_file,
+            is_dirty: false,
+            backup_file: BackupFile::new(save_path, ram_size, ram_size > 0, is_desktop)
+        };
+
+        if let Some(rtc) = rtc {
+            huc3.load_rtc_file(rtc);
+        }
+
+        huc3
+    }
+
+    fn get_ram_address(&self, address: u16) -> usize {
+        ((address & 0x1fff) as usize | (self.ram_bank as usize) << 13) % self.ram_size
+    }
+
+    fn get_rom_address(&self, address: u16) -> usize {
+        (address as usize) & 0x3fff | (self.rom_bank as usize) << 14
+    }
+}
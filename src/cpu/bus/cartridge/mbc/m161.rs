@@ -0,0 +1,47 @@
+use serde::{Deserialize, Serialize};
+
+/*
+ * M161, used by the Mani 4 in 1 carts. 0x0000-0x7fff is switched 32KB at a time, and only
+ * the first write to 0x0000-0x7fff counts. after the menu picks a game, the bank is stuck
+ * until the next reset.
+ */
+#[derive(Serialize, Deserialize)]
+pub struct M161 {
+    rom_bank: u8,
+    locked: bool
+}
+
+impl M161 {
+    pub fn new() -> Self {
+        Self {
+            rom_bank: 0,
+            locked: false
+        }
+    }
+
+    pub fn read(&mut self, address: u16, rom: &[u8]) -> u8 {
+        match address {
+            0x0000..=0x7fff => rom[self.get_rom_address(address) % rom.len()],
+            _ => 0xff
+        }
+    }
+
+    pub fn read16(&mut self, address: u16, rom: &[u8]) -> u16 {
+        self.read(address, rom) as u16 | (self.read(address + 1, rom) as u16) << 8
+    }
+
+    pub fn write(&mut self, address: u16, value: u8) {
+        if address < 0x8000 && !self.locked {
+            self.rom_bank = value & 0x7;
+            self.locked = true;
+        }
+    }
+
+    pub fn write16(&mut self, address: u16, value: u16) {
+        self.write(address, value as u8);
+    }
+
+    fn get_rom_address(&self, address: u16) -> usize {
+        address as usize | (self.rom_bank as usize) << 15
+    }
+}
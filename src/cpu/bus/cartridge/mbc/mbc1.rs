@@ -19,6 +19,7 @@ pub struct MBC1 {
     rom_bank: u8,
     ram_bank: u8,
     banking_mode: BankingMode,
+    multicart: bool,
     pub backup_file: BackupFile
 }
 
@@ -137,6 +138,7 @@ impl MBC1 {
         has_battery: bool,
         rom_size: usize,
         ram_size: usize,
+        multicart: bool,
         save_path: Option<String>,
         is_desktop: bool
     ) -> Self {
@@ -148,6 +150,7 @@ impl MBC1 {
             banking_mode: BankingMode::Simple,
             rom_bank: 1,
             ram_bank: 0,
+            multicart,
             backup_file: BackupFile::new(save_path, ram_size, has_battery && has_ram, is_desktop)
         }
     }
@@ -160,22 +163,39 @@ impl MBC1 {
         }
     }
 
+    /*
+     * MBC1M multicarts leave bit 4 of the bank register unconnected and wire the upper 2 bits
+     * in one spot lower, so every game gets its own 256KB block of the ROM
+     */
+    fn get_rom_bank(&self) -> usize {
+        let upper = ((self.rom_bank as usize) >> 5) & 0x3;
+
+        if self.multicart {
+            upper << 4 | (self.rom_bank as usize) & 0xf
+        } else {
+            upper << 5 | (self.rom_bank as usize) & 0x1f
+        }
+    }
+
     fn get_rom_address_lower(&self, address: u16) -> usize {
         if self.banking_mode == BankingMode::Simple {
             (address as usize) & 0x3fff
         } else {
-            (address as usize) & 0x3fff | (((self.rom_bank as usize) >> 5) & 0x3) << 19
+            let shift = if self.multicart { 18 } else { 19 };
+
+            (address as usize) & 0x3fff | (((self.rom_bank as usize) >> 5) & 0x3) << shift
         }
     }
 
     fn get_rom_address_upper(&self, address: u16) -> usize {
-        (address as usize) & 0x3fff | (self.rom_bank as usize) << 14
+        (address as usize) & 0x3fff | self.get_rom_bank() << 14
     }
 
+    // the upper 2 bits come from 0x4000-0x5fff and are left alone
     fn update_rom_bank(&mut self, value: u8) {
-        let rom_bank = if value == 0 { 1 } else { value & 0x1f };
+        let rom_bank = if value & 0x1f == 0 { 1 } else { value & 0x1f };
 
-        self.rom_bank = rom_bank;
+        self.rom_bank = (self.rom_bank & 0x60) | rom_bank;
     }
 
     fn update_banking_mode(&mut self, value: u8) {
@@ -0,0 +1,132 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::cpu::bus::cartridge::backup_file::BackupFile;
+
+// MBC2 has 512 half bytes of RAM built in, so the header's RAM size is always 0
+pub const RAM_SIZE: usize = 0x200;
+
+#[derive(Serialize, Deserialize)]
+pub struct MBC2 {
+    rom_bank: u8,
+    ram_enable: bool,
+    pub backup_file: BackupFile
+}
+
+impl MBC2 {
+    pub fn check_save(&mut self, is_cloud: bool) -> bool {
+        let min_diff = if is_cloud { 1500 } else { 500 };
+
+        let current_time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("an error occurred")
+            .as_millis();
+
+        let last_updated = self.backup_file.last_updated;
+
+        if self.backup_file.is_dirty &&
+            current_time > last_updated &&
+            last_updated != 0
+        {
+            let diff = current_time - last_updated;
+            if diff >= min_diff {
+                self.backup_file.last_updated = 0;
+                return true;
+            }
+        }
+
+        false
+    }
+
+    pub fn has_saved(&mut self) -> bool {
+        let return_val = self.backup_file.is_dirty;
+
+        self.backup_file.is_dirty = false;
+
+        return_val
+    }
+
+    pub fn read(&mut self, address: u16, rom: &[u8]) -> u8 {
+        match address {
+            0x0000..=0x3fff => rom[address as usize],
+            0x4000..=0x7fff => {
+                let actual_address = self.get_rom_address(address);
+
+                rom[actual_address % rom.len()]
+            }
+            // only the lower 4 bits exist, the upper ones always read back as 1s
+            0xa000..=0xbfff if self.ram_enable => 0xf0 | self.backup_file.read8(Self::get_ram_address(address)),
+            0xa000..=0xbfff => 0xff,
+            _ => panic!("invalid address given: 0x{:x}", address)
+        }
+    }
+
+    pub fn read16(&mut self, address: u16, rom: &[u8]) -> u16 {
+        match address {
+            0x0000..=0x3fff => {
+                unsafe { *(&rom[address as usize] as *const u8 as *const u16) }
+            }
+            0x4000..=0x7fff => {
+                let actual_address = self.get_rom_address(address);
+
+                unsafe { *(&rom[actual_address % rom.len()] as *const u8 as *const u16) }
+            }
+            0xa000..=0xbfff if self.ram_enable => {
+                let lower = self.backup_file.read8(Self::get_ram_address(address));
+                let upper = self.backup_file.read8(Self::get_ram_address(address + 1));
+
+                0xf0f0 | lower as u16 | (upper as u16) << 8
+            }
+            0xa000..=0xbfff => 0xffff,
+            _ => panic!("invalid address given: 0x{:x}", address)
+        }
+    }
+
+    pub fn write(&mut self, address: u16, value: u8) {
+        match address {
+            0x0000..=0x3fff => self.write_register(address, value),
+            0xa000..=0xbfff if self.ram_enable => {
+                self.backup_file.write8(Self::get_ram_address(address), value & 0xf);
+            }
+            _ => ()
+        }
+    }
+
+    pub fn write16(&mut self, address: u16, value: u16) {
+        match address {
+            0x0000..=0x3fff => self.write_register(address, value as u8),
+            0xa000..=0xbfff if self.ram_enable => {
+                self.backup_file.write8(Self::get_ram_address(address), value as u8 & 0xf);
+                self.backup_file.write8(Self::get_ram_address(address + 1), (value >> 8) as u8 & 0xf);
+            }
+            _ => ()
+        }
+    }
+
+    pub fn new(has_battery: bool, save_path: Option<String>, is_desktop: bool) -> Self {
+        Self {
+            rom_bank: 1,
+            ram_enable: false,
+            backup_file: BackupFile::new(save_path, RAM_SIZE, has_battery, is_desktop)
+        }
+    }
+
+    // bit 8 of the address picks between RAM enable and the ROM bank
+    fn write_register(&mut self, address: u16, value: u8) {
+        if (address >> 8) & 0x1 == 0 {
+            self.ram_enable = value & 0xf == 0xa;
+        } else {
+            self.rom_bank = if value & 0xf == 0 { 1 } else { value & 0xf };
+        }
+    }
+
+    // the 512 half bytes are echoed all the way through 0xa000-0xbfff
+    fn get_ram_address(address: u16) -> usize {
+        (address & 0x1ff) as usize
+    }
+
+    fn get_rom_address(&self, address: u16) -> usize {
+        (address as usize) & 0x3fff | (self.rom_bank as usize) << 14
+    }
+}
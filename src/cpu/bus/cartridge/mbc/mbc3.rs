@@ -7,10 +7,14 @@ use crate::cpu::bus::cartridge::backup_file::BackupFile;
 
 #[derive(Serialize, Deserialize)]
 pub struct RtcFile {
-    timestamp: usize,
-    carry_bit: bool,
-    halted: bool,
-    num_wraps: usize
+    pub timestamp: usize,
+    pub carry_bit: bool,
+    pub halted: bool,
+    pub num_wraps: usize,
+    // HuC3 also keeps the alarm and whatever else the game puts in its RTC memory here
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub memory: Vec<u8>
 }
 
 impl RtcFile {
@@ -25,9 +29,35 @@ impl RtcFile {
             timestamp,
             carry_bit,
             halted,
-            num_wraps
+            num_wraps,
+            memory: Vec::new()
         }
     }
+
+    // the rtc file sits next to the save, with the extension swapped for .rtc
+    pub fn open(save_path: &str) -> (File, Option<RtcFile>) {
+        let mut split_str: Vec<&str> = save_path.split('.').collect();
+
+        split_str.pop();
+
+        split_str.push("rtc");
+
+        let rtc_path = split_str.join(".");
+
+        let mut rtc_file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .open(rtc_path)
+            .unwrap();
+
+        let mut str = "".to_string();
+
+        rtc_file.read_to_string( &mut str).unwrap();
+        rtc_file.seek(SeekFrom::Start(0)).unwrap();
+
+        (rtc_file, serde_json::from_str::<RtcFile>(&str).ok())
+    }
 }
 
 #[derive(Copy, Clone, Debug, Serialize, Deserialize)]
@@ -76,7 +106,8 @@ pub struct MBC3 {
     #[serde(skip_deserializing)]
     halted_elapsed: TimeDelta,
     pub num_wraps: usize,
-    pub is_dirty: bool
+    pub is_dirty: bool,
+    is_mbc30: bool
 }
 
 impl MBC3 {
@@ -354,34 +385,16 @@ impl MBC3 {
     ) -> Self {
         let (start, carry_bit, halted, halted_elapsed, rtc_file, num_wraps) = if let Some(save_path) = &save_path {
             if has_timer && !logged_in {
-                let mut split_str: Vec<&str> = save_path.split('.').collect();
-
-                split_str.pop();
-
-                split_str.push("rtc");
-
-                let rtc_path = split_str.join(".");
+                let (rtc_file, rtc) = RtcFile::open(save_path);
 
-                let mut rtc_file = OpenOptions::new()
-                    .read(true)
-                    .write(true)
-                    .create(true)
-                    .open(rtc_path)
-                    .unwrap();
-
-                let mut str = "".to_string();
-
-                rtc_file.read_to_string( &mut str).unwrap();
-                rtc_file.seek(SeekFrom::Start(0)).unwrap();
-
-                let (start, carry_bit, halted, halted_elapsed, num_wraps) = match serde_json::from_str::<RtcFile>(&str) {
-                    Ok(result) => {
+                let (start, carry_bit, halted, halted_elapsed, num_wraps) = match rtc {
+                    Some(result) => {
                         let start = Local.timestamp_opt(result.timestamp as i64, 0).unwrap();
                         let halted_elapsed = TimeDelta::new(0, 0).unwrap();
 
                         (start, result.carry_bit, result.halted, halted_elapsed, result.num_wraps)
                     }
-                    Err(_) => (Local::now(), false, false, Duration::seconds(0), 0)
+                    None => (Local::now(), false, false, Duration::seconds(0), 0)
                 };
 
                 (start, carry_bit, halted, halted_elapsed, Some(rtc_file), num_wraps)
@@ -410,22 +423,32 @@ impl MBC3 {
             halted,
             halted_elapsed,
             num_wraps,
-            is_dirty: false
+            is_dirty: false,
+            // MBC30 is only told apart by having more ROM or RAM than MBC3 can address
+            is_mbc30: rom_size > 0x200000 || ram_size > 0x8000
         }
     }
 
+    // MBC30 has 8 bits for the ROM bank instead of 7, for up to 4MB of ROM
     fn update_bank(&mut self, value: u8) {
-        if value == 0 {
+        let mask = if self.is_mbc30 { 0xff } else { 0x7f };
+
+        if value & mask == 0 {
             self.rom_bank = 1;
         } else {
-            self.rom_bank = value & 0x7f;
+            self.rom_bank = value & mask;
         }
     }
 
+    // 0x8-0xc select the RTC registers. MBC3 has 4 RAM banks and MBC30 has 8
     fn update_timer_ram_bank(&mut self, value: u8) {
         let id = value & 0xf;
 
-        self.ram_bank = id;
+        self.ram_bank = match id {
+            0x0..=0x7 if self.is_mbc30 => id & 0x7,
+            0x0..=0x7 => id & 0x3,
+            _ => id
+        };
     }
 
     fn latch_clock_value(&mut self, value: u8) {
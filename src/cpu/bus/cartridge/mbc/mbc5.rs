@@ -4,12 +4,20 @@ use serde::{Deserialize, Serialize};
 
 use crate::cpu::bus::cartridge::backup_file::BackupFile;
 
+// with the LCD off there are no frames to go by, so the rumble gets measured over a frame's worth of cycles instead
+const RUMBLE_WINDOW_CYCLES: usize = 70224;
+
 #[derive(Serialize, Deserialize)]
 pub struct MBC5 {
     rom_bank: u16,
     ram_bank: u8,
     ram_enable: bool,
-    _has_rumble: bool,
+    has_rumble: bool,
+    motor_on: bool,
+    // cycles the motor has been on for so far this frame, out of frame_cycles
+    motor_cycles: usize,
+    frame_cycles: usize,
+    pub rumble_strength: f32,
     ram_size: usize,
     has_ram: bool,
     pub backup_file: BackupFile
@@ -107,7 +115,7 @@ impl MBC5 {
             }
             0x2000..=0x2fff => self.rom_bank = (self.rom_bank & 0x100) | value as u16,
             0x3000..=0x3fff => self.rom_bank = (self.rom_bank & 0xff) | (value as u16 & 0x1) << 8,
-            0x4000..=0x5fff => self.ram_bank = value & 0xf,
+            0x4000..=0x5fff => self.update_ram_bank(value),
             0xa000..=0xbfff => if self.has_ram && self.ram_enable {
                 let actual_address = self.get_ram_address(address) % self.ram_size;
                 self.backup_file.write8(actual_address, value);
@@ -125,7 +133,7 @@ impl MBC5 {
             }
             0x2000..=0x2fff => self.rom_bank = (self.rom_bank & 0x100) | value,
             0x3000..=0x3fff => self.rom_bank = (self.rom_bank & 0xff) | (value & 0x1) << 8,
-            0x4000..=0x5fff => self.ram_bank = (value as u8) & 0xf,
+            0x4000..=0x5fff => self.update_ram_bank(value as u8),
             0xa000..=0xbfff => if self.has_ram && self.ram_enable {
                 let actual_address = self.get_ram_address(address);
                 self.backup_file.write16(actual_address % self.ram_size, value);
@@ -137,7 +145,7 @@ impl MBC5 {
     pub fn new(
         has_ram: bool,
         has_battery: bool,
-        _has_rumble: bool,
+        has_rumble: bool,
         _rom_size: usize,
         ram_size: usize,
         save_path: Option<String>,
@@ -149,10 +157,52 @@ impl MBC5 {
             ram_enable: false,
             has_ram,
             ram_size,
-            _has_rumble,
+            has_rumble,
+            motor_on: false,
+            motor_cycles: 0,
+            frame_cycles: 0,
+            rumble_strength: 0.0,
             backup_file: BackupFile::new(save_path, ram_size, has_battery, is_desktop)
         }
     }
+
+    // on rumble carts, bit 3 of the RAM bank drives the motor instead
+    fn update_ram_bank(&mut self, value: u8) {
+        if self.has_rumble {
+            self.ram_bank = value & 0x7;
+            self.motor_on = (value >> 3) & 0x1 == 1;
+        } else {
+            self.ram_bank = value & 0xf;
+        }
+    }
+
+    pub fn tick(&mut self, cycles: usize) {
+        if self.has_rumble {
+            self.frame_cycles += cycles;
+
+            if self.motor_on {
+                self.motor_cycles += cycles;
+            }
+
+            if self.frame_cycles >= RUMBLE_WINDOW_CYCLES {
+                self.finish_frame();
+            }
+        }
+    }
+
+    /*
+     * games control how hard the motor shakes by switching it on and off several times a
+     * frame, so the strength is how much of the frame it was on for
+     */
+    pub fn finish_frame(&mut self) {
+        if self.frame_cycles > 0 {
+            self.rumble_strength = self.motor_cycles as f32 / self.frame_cycles as f32;
+        }
+
+        self.motor_cycles = 0;
+        self.frame_cycles = 0;
+    }
+
     fn get_ram_address(&self, address: u16) -> usize {
         (address & 0x1fff) as usize | (self.ram_bank as usize) << 13
     }
@@ -0,0 +1,347 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::cpu::bus::cartridge::backup_file::BackupFile;
+
+// the 93LC56 holds 128 16-bit words
+pub const EEPROM_SIZE: usize = 0x100;
+
+const NUM_WORDS: usize = EEPROM_SIZE / 2;
+
+// the accelerometer reads 0x81d0 when flat, and moves by about 0x70 for every g of tilt
+const ACCELEROMETER_CENTER: f32 = 0x81d0 as f32;
+const ACCELEROMETER_SCALE: f32 = 0x70 as f32;
+
+// start bit aside, every command is a 2 bit opcode followed by 8 address bits
+const COMMAND_BITS: u8 = 10;
+
+#[derive(Copy, Clone, PartialEq, Serialize, Deserialize)]
+enum EepromState {
+    Idle,
+    Command { command: u16, bits: u8 },
+    Read { address: usize, bit: u8 },
+    Write { address: Option<usize>, value: u16, bits: u8 }
+}
+
+/*
+ * 93LC56 serial EEPROM, bit banged through 0xa080. every bit the game sends is latched on the
+ * rising edge of CLK while CS is high, and DO holds either the data being read or, in between
+ * commands, whether the EEPROM is ready.
+ */
+#[derive(Serialize, Deserialize)]
+struct Eeprom {
+    chip_select: bool,
+    clock: bool,
+    data_in: bool,
+    data_out: bool,
+    write_enable: bool,
+    state: EepromState
+}
+
+impl Eeprom {
+    fn new() -> Self {
+        Self {
+            chip_select: false,
+            clock: false,
+            data_in: false,
+            data_out: true,
+            write_enable: false,
+            state: EepromState::Idle
+        }
+    }
+
+    fn read(&self) -> u8 {
+        (self.chip_select as u8) << 7 | (self.clock as u8) << 6 | (self.data_in as u8) << 1 | self.data_out as u8
+    }
+
+    fn write(&mut self, value: u8, backup_file: &mut BackupFile) {
+        let chip_select = (value >> 7) & 0x1 == 1;
+        let clock = (value >> 6) & 0x1 == 1;
+
+        self.data_in = (value >> 1) & 0x1 == 1;
+
+        // dropping CS cancels whatever command was in progress
+        if !chip_select {
+            self.state = EepromState::Idle;
+            self.data_out = true;
+        } else if clock && !self.clock {
+            self.clock_in(backup_file);
+        }
+
+        self.chip_select = chip_select;
+        self.clock = clock;
+    }
+
+    fn clock_in(&mut self, backup_file: &mut BackupFile) {
+        let bit = self.data_in as u16;
+
+        self.state = match self.state {
+            // leading zeros are ignored until the start bit comes in
+            EepromState::Idle => if bit == 1 {
+                EepromState::Command { command: 0, bits: 0 }
+            } else {
+                EepromState::Idle
+            }
+            EepromState::Command { command, bits } => {
+                let command = command << 1 | bit;
+
+                if bits + 1 == COMMAND_BITS {
+                    self.execute(command, backup_file)
+                } else {
+                    EepromState::Command { command, bits: bits + 1 }
+                }
+            }
+            // reads keep going through the following words for as long as the game keeps clocking
+            EepromState::Read { address, bit } => {
+                let bit = bit - 1;
+
+                self.data_out = (Self::read_word(backup_file, address) >> bit) & 0x1 == 1;
+
+                if bit == 0 {
+                    EepromState::Read { address: (address + 1) % NUM_WORDS, bit: 16 }
+                } else {
+                    EepromState::Read { address, bit }
+                }
+            }
+            EepromState::Write { address, value, bits } => {
+                let value = value << 1 | bit;
+
+                if bits + 1 == 16 {
+                    self.write_words(backup_file, address, value);
+
+                    EepromState::Idle
+                } else {
+                    EepromState::Write { address, value, bits: bits + 1 }
+                }
+            }
+        }
+    }
+
+    fn execute(&mut self, command: u16, backup_file: &mut BackupFile) -> EepromState {
+        let address = command as usize % NUM_WORDS;
+
+        match (command >> 8) & 0x3 {
+            // READ, a dummy 0 comes out first
+            0b10 => {
+                self.data_out = false;
+
+                EepromState::Read { address, bit: 16 }
+            }
+            // WRITE
+            0b01 => EepromState::Write { address: Some(address), value: 0, bits: 0 },
+            // ERASE
+            0b11 => {
+                self.write_words(backup_file, Some(address), 0xffff);
+
+                EepromState::Idle
+            }
+            _ => match (command >> 6) & 0x3 {
+                // EWDS
+                0b00 => {
+                    self.write_enable = false;
+
+                    EepromState::Idle
+                }
+                // WRAL
+                0b01 => EepromState::Write { address: None, value: 0, bits: 0 },
+                // ERAL
+                0b10 => {
+                    self.write_words(backup_file, None, 0xffff);
+
+                    EepromState::Idle
+                }
+                // EWEN
+                0b11 => {
+                    self.write_enable = true;
+
+                    EepromState::Idle
+                }
+                _ => unreachable!()
+            }
+        }
+    }
+
+    fn read_word(backup_file: &mut BackupFile, address: usize) -> u16 {
+        u16::from_le_bytes([backup_file.read8(address * 2), backup_file.read8(address * 2 + 1)])
+    }
+
+    // an address of None writes every word, for WRAL and ERAL
+    fn write_words(&mut self, backup_file: &mut BackupFile, address: Option<usize>, value: u16) {
+        if !self.write_enable {
+            return;
+        }
+
+        let addresses = match address {
+            Some(address) => address..address + 1,
+            None => 0..NUM_WORDS
+        };
+
+        let [lower, upper] = value.to_le_bytes();
+
+        for address in addresses {
+            backup_file.write8(address * 2, lower);
+            backup_file.write8(address * 2 + 1, upper);
+        }
+
+        // writes finish instantly, so the EEPROM is always ready afterwards
+        self.data_out = true;
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct MBC7 {
+    rom_bank: u8,
+    ram_enable1: bool,
+    ram_enable2: bool,
+    tilt_x: f32,
+    tilt_y: f32,
+    latch_erased: bool,
+    x_latch: u16,
+    y_latch: u16,
+    eeprom: Eeprom,
+    pub backup_file: BackupFile
+}
+
+impl MBC7 {
+    pub fn check_save(&mut self, is_cloud: bool) -> bool {
+        let min_diff = if is_cloud { 1500 } else { 500 };
+
+        let current_time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("an error occurred")
+            .as_millis();
+
+        let last_updated = self.backup_file.last_updated;
+
+        if self.backup_file.is_dirty &&
+            current_time > last_updated &&
+            last_updated != 0
+        {
+            let diff = current_time - last_updated;
+            if diff >= min_diff {
+                self.backup_file.last_updated = 0;
+                return true;
+            }
+        }
+
+        false
+    }
+
+    pub fn has_saved(&mut self) -> bool {
+        let return_val = self.backup_file.is_dirty;
+
+        self.backup_file.is_dirty = false;
+
+        return_val
+    }
+
+    // tilt is measured in g, positive x is tilting right and positive y is tilting towards the player
+    pub fn set_tilt(&mut self, x: f32, y: f32) {
+        self.tilt_x = x;
+        self.tilt_y = y;
+    }
+
+    pub fn read(&mut self, address: u16, rom: &[u8]) -> u8 {
+        match address {
+            0x0000..=0x3fff => rom[address as usize],
+            0x4000..=0x7fff => {
+                let actual_address = self.get_rom_address(address);
+
+                rom[actual_address % rom.len()]
+            }
+            0xa000..=0xafff if self.ram_enable1 && self.ram_enable2 => self.read_register(address),
+            0xa000..=0xbfff => 0xff,
+            _ => panic!("invalid address given: 0x{:x}", address)
+        }
+    }
+
+    pub fn read16(&mut self, address: u16, rom: &[u8]) -> u16 {
+        match address {
+            0x0000..=0x3fff => {
+                unsafe { *(&rom[address as usize] as *const u8 as *const u16) }
+            }
+            0x4000..=0x7fff => {
+                let actual_address = self.get_rom_address(address);
+
+                unsafe { *(&rom[actual_address % rom.len()] as *const u8 as *const u16) }
+            }
+            0xa000..=0xbfff => self.read(address, rom) as u16 | (self.read(address + 1, rom) as u16) << 8,
+            _ => panic!("invalid address given: 0x{:x}", address)
+        }
+    }
+
+    pub fn write(&mut self, address: u16, value: u8) {
+        match address {
+            0x0000..=0x1fff => self.ram_enable1 = value == 0xa,
+            0x2000..=0x3fff => self.rom_bank = value,
+            0x4000..=0x5fff => self.ram_enable2 = value == 0x40,
+            0xa000..=0xafff if self.ram_enable1 && self.ram_enable2 => self.write_register(address, value),
+            _ => ()
+        }
+    }
+
+    pub fn write16(&mut self, address: u16, value: u16) {
+        match address {
+            0xa000..=0xbfff => {
+                self.write(address, value as u8);
+                self.write(address + 1, (value >> 8) as u8);
+            }
+            _ => self.write(address, value as u8)
+        }
+    }
+
+    pub fn new(save_path: Option<String>, is_desktop: bool) -> Self {
+        Self {
+            rom_bank: 1,
+            ram_enable1: false,
+            ram_enable2: false,
+            tilt_x: 0.0,
+            tilt_y: 0.0,
+            latch_erased: false,
+            x_latch: 0x8000,
+            y_latch: 0x8000,
+            eeprom: Eeprom::new(),
+            backup_file: BackupFile::new(save_path, EEPROM_SIZE, true, is_desktop)
+        }
+    }
+
+    // registers are mirrored every 0x100 bytes, with bits 4-7 of the address picking one
+    fn read_register(&mut self, address: u16) -> u8 {
+        match (address >> 4) & 0xf {
+            0x2 => self.x_latch as u8,
+            0x3 => (self.x_latch >> 8) as u8,
+            0x4 => self.y_latch as u8,
+            0x5 => (self.y_latch >> 8) as u8,
+            0x6 => 0,
+            0x8 => self.eeprom.read(),
+            _ => 0xff
+        }
+    }
+
+    fn write_register(&mut self, address: u16, value: u8) {
+        match (address >> 4) & 0xf {
+            // the latch has to be erased with 0x55 before 0xaa can latch a new reading
+            0x0 if value == 0x55 => {
+                self.latch_erased = true;
+                self.x_latch = 0x8000;
+                self.y_latch = 0x8000;
+            }
+            0x1 if value == 0xaa && self.latch_erased => {
+                self.latch_erased = false;
+                self.x_latch = Self::accelerometer_value(self.tilt_x);
+                self.y_latch = Self::accelerometer_value(self.tilt_y);
+            }
+            0x8 => self.eeprom.write(value, &mut self.backup_file),
+            _ => ()
+        }
+    }
+
+    fn accelerometer_value(tilt: f32) -> u16 {
+        (ACCELEROMETER_CENTER + tilt * ACCELEROMETER_SCALE) as u16
+    }
+
+    fn get_rom_address(&self, address: u16) -> usize {
+        (address as usize) & 0x3fff | (self.rom_bank as usize) << 14
+    }
+}
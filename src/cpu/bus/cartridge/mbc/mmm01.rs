@@ -0,0 +1,230 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::cpu::bus::cartridge::backup_file::BackupFile;
+
+/*
+ * MMM01, used by multicarts. it starts out unmapped, with the last 32KB of the ROM (the menu)
+ * at 0x0000-0x7fff. while it's unmapped, the menu can write the outer bank bits and masks
+ * for the game it's about to start, and then locks them in by setting bit 6 of 0x0000-0x1fff.
+ * once locked, the game only sees something that acts like an MBC1 of its own size, and
+ * only a reset gets back to the menu.
+ *
+ * 0x0000-0x1fff: bits 0-3 RAM enable. unlocked only: bits 4-5 RAM bank mask, bit 6 lock
+ * 0x2000-0x3fff: bits 0-4 ROM bank low. unlocked only: bits 5-6 ROM bank mid
+ * 0x4000-0x5fff: bits 0-1 RAM bank low. unlocked only: bits 2-3 RAM bank high, bits 4-5 ROM bank high,
+ *                bit 6 disables MBC1 mode writes
+ * 0x6000-0x7fff: bit 0 MBC1 mode. unlocked only: bits 2-5 ROM bank mask, bit 6 multiplex
+ *
+ * bits set in the masks keep the game from changing the matching bank bits.
+ */
+#[derive(Serialize, Deserialize)]
+pub struct MMM01 {
+    ram_size: usize,
+    ram_enable: bool,
+    locked: bool,
+    rom_bank_low: u8,
+    rom_bank_mid: u8,
+    rom_bank_high: u8,
+    rom_bank_mask: u8,
+    ram_bank_low: u8,
+    ram_bank_high: u8,
+    ram_bank_mask: u8,
+    mbc1_mode: bool,
+    mbc1_mode_disabled: bool,
+    multiplex: bool,
+    pub backup_file: BackupFile
+}
+
+impl MMM01 {
+    pub fn check_save(&mut self, is_cloud: bool) -> bool {
+        let min_diff = if is_cloud { 1500 } else { 500 };
+
+        let current_time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("an error occurred")
+            .as_millis();
+
+        let last_updated = self.backup_file.last_updated;
+
+        if self.backup_file.is_dirty &&
+            current_time > last_updated &&
+            last_updated != 0
+        {
+            let diff = current_time - last_updated;
+            if diff >= min_diff {
+                self.backup_file.last_updated = 0;
+                return true;
+            }
+        }
+
+        false
+    }
+
+    pub fn has_saved(&mut self) -> bool {
+        let return_val = self.backup_file.is_dirty;
+
+        self.backup_file.is_dirty = false;
+
+        return_val
+    }
+
+    pub fn read(&mut self, address: u16, rom: &[u8]) -> u8 {
+        match address {
+            0x0000..=0x3fff => {
+                let actual_address = self.get_rom_address(address, self.get_lower_rom_bank(), rom);
+
+                rom[actual_address]
+            }
+            0x4000..=0x7fff => {
+                let actual_address = self.get_rom_address(address, self.get_upper_rom_bank(), rom);
+
+                rom[actual_address]
+            }
+            0xa000..=0xbfff if self.ram_enable && self.ram_size > 0 => {
+                let actual_address = self.get_ram_address(address);
+
+                self.backup_file.read8(actual_address)
+            }
+            0xa000..=0xbfff => 0xff,
+            _ => panic!("invalid address given: 0x{:x}", address)
+        }
+    }
+
+    pub fn read16(&mut self, address: u16, rom: &[u8]) -> u16 {
+        self.read(address, rom) as u16 | (self.read(address + 1, rom) as u16) << 8
+    }
+
+    pub fn write(&mut self, address: u16, value: u8) {
+        match address {
+            0x0000..=0x1fff => {
+                self.ram_enable = value & 0xf == 0xa;
+
+                if !self.locked {
+                    self.ram_bank_mask = (value >> 4) & 0x3;
+                    self.locked = (value >> 6) & 0x1 == 1;
+                }
+            }
+            0x2000..=0x3fff => {
+                if !self.locked {
+                    self.rom_bank_mid = (value >> 5) & 0x3;
+                }
+
+                let mask = self.rom_bank_mask << 1;
+
+                self.rom_bank_low = (self.rom_bank_low & mask) | (value & !mask & 0x1f);
+            }
+            0x4000..=0x5fff => {
+                self.ram_bank_low = (self.ram_bank_low & self.ram_bank_mask) | (value & !self.ram_bank_mask & 0x3);
+
+                if !self.locked {
+                    self.ram_bank_high = (value >> 2) & 0x3;
+                    self.rom_bank_high = (value >> 4) & 0x3;
+                    self.mbc1_mode_disabled = (value >> 6) & 0x1 == 1;
+                }
+            }
+            0x6000..=0x7fff => {
+                if !self.mbc1_mode_disabled {
+                    self.mbc1_mode = value & 0x1 == 1;
+                }
+
+                if !self.locked {
+                    self.rom_bank_mask = (value >> 2) & 0xf;
+                    self.multiplex = (value >> 6) & 0x1 == 1;
+                }
+            }
+            0xa000..=0xbfff if self.ram_enable && self.ram_size > 0 => {
+                let actual_address = self.get_ram_address(address);
+
+                self.backup_file.write8(actual_address, value);
+            }
+            _ => ()
+        }
+    }
+
+    pub fn write16(&mut self, address: u16, value: u16) {
+        match address {
+            0xa000..=0xbfff => {
+                self.write(address, value as u8);
+                self.write(address + 1, (value >> 8) as u8);
+            }
+            _ => self.write(address, value as u8)
+        }
+    }
+
+    pub fn new(ram_size: usize, has_battery: bool, save_path: Option<String>, is_desktop: bool) -> Self {
+        Self {
+            ram_size,
+            ram_enable: false,
+            locked: false,
+            rom_bank_low: 0,
+            rom_bank_mid: 0,
+            rom_bank_high: 0,
+            rom_bank_mask: 0,
+            ram_bank_low: 0,
+            ram_bank_high: 0,
+            ram_bank_mask: 0,
+            mbc1_mode: false,
+            mbc1_mode_disabled: false,
+            multiplex: false,
+            backup_file: BackupFile::new(save_path, ram_size, has_battery && ram_size > 0, is_desktop)
+        }
+    }
+
+    // in multiplex mode, the mid ROM bits and the low RAM bits trade places
+    fn rom_bank_bits_5_6(&self) -> usize {
+        if self.multiplex { self.ram_bank_low as usize } else { self.rom_bank_mid as usize }
+    }
+
+    // bits of the low bank that the menu masked off stay fixed, the rest act as bank 0
+    fn get_lower_rom_bank(&self) -> Option<usize> {
+        if !self.locked {
+            return None;
+        }
+
+        let low = (self.rom_bank_low & (self.rom_bank_mask << 1)) as usize;
+
+        let mid = if self.multiplex && !self.mbc1_mode { 0 } else { self.rom_bank_bits_5_6() };
+
+        Some(low | mid << 5 | (self.rom_bank_high as usize) << 7)
+    }
+
+    fn get_upper_rom_bank(&self) -> Option<usize> {
+        if !self.locked {
+            return None;
+        }
+
+        // like MBC1, bank 0 can't be mapped here
+        let low = if self.rom_bank_low & 0x1f == 0 { 1 } else { self.rom_bank_low as usize };
+
+        Some(low | self.rom_bank_bits_5_6() << 5 | (self.rom_bank_high as usize) << 7)
+    }
+
+    // until the menu locks in a game, the last 32KB of the ROM is mapped
+    fn get_rom_address(&self, address: u16, bank: Option<usize>, rom: &[u8]) -> usize {
+        let num_banks = rom.len() / 0x4000;
+
+        let bank = match bank {
+            Some(bank) => bank,
+            None if address < 0x4000 => num_banks - 2,
+            None => num_banks - 1
+        };
+
+        ((address as usize) & 0x3fff | (bank % num_banks) << 14) % rom.len()
+    }
+
+    fn get_ram_address(&self, address: u16) -> usize {
+        let low = if self.multiplex {
+            self.rom_bank_mid
+        } else if self.mbc1_mode {
+            self.ram_bank_low
+        } else {
+            0
+        };
+
+        let bank = (low | self.ram_bank_high << 2) as usize;
+
+        ((address & 0x1fff) as usize | bank << 13) % self.ram_size
+    }
+}
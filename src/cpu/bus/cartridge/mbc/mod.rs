@@ -1,16 +1,43 @@
+use huc1::HuC1;
+use huc3::HuC3;
+use m161::M161;
 use mbc1::MBC1;
+use mbc2::MBC2;
 use mbc3::MBC3;
 use mbc5::MBC5;
+use mbc7::MBC7;
+use mmm01::MMM01;
+use pocket_camera::PocketCamera;
+use sachen::Sachen;
 use serde::{Deserialize, Serialize};
+use wisdom_tree::WisdomTree;
 
+pub mod huc1;
+pub mod huc3;
+pub mod m161;
 pub mod mbc1;
+pub mod mbc2;
 pub mod mbc3;
 pub mod mbc5;
+pub mod mbc7;
+pub mod mmm01;
+pub mod pocket_camera;
+pub mod sachen;
+pub mod wisdom_tree;
 
 #[derive(Serialize, Deserialize)]
 pub enum MBC {
     None,
     MBC1(MBC1),
+    MBC2(MBC2),
     MBC3(MBC3),
-    MBC5(MBC5)
+    MBC5(MBC5),
+    MBC7(MBC7),
+    MMM01(MMM01),
+    HuC1(HuC1),
+    HuC3(HuC3),
+    PocketCamera(PocketCamera),
+    WisdomTree(WisdomTree),
+    Sachen(Sachen),
+    M161(M161)
 }
\ No newline at end of file
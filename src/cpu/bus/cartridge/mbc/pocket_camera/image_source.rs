@@ -0,0 +1,141 @@
+use std::sync::{Arc, Mutex};
+
+// the size of a captured photo
+pub const CAMERA_WIDTH: usize = 128;
+pub const CAMERA_HEIGHT: usize = 112;
+
+/*
+ * An image source is whatever the camera's sensor is looking at. every capture asks it for a
+ * 128x112 frame of 8 bit grayscale pixels, where 0 is black and 0xff is white.
+ */
+pub trait ImageSource: Send {
+    fn capture(&mut self, frame: &mut [u8]);
+}
+
+// diagonal bands going from white to black, so there's always something to take a picture of
+pub struct TestPatternSource;
+
+impl ImageSource for TestPatternSource {
+    fn capture(&mut self, frame: &mut [u8]) {
+        for y in 0..CAMERA_HEIGHT {
+            for x in 0..CAMERA_WIDTH {
+                frame[x + y * CAMERA_WIDTH] = 0xff - ((x + y) * 0xff / (CAMERA_WIDTH + CAMERA_HEIGHT - 2)) as u8;
+            }
+        }
+    }
+}
+
+// the same picture every time, stretched to fit the sensor
+pub struct StillImageSource {
+    pixels: Vec<u8>
+}
+
+impl StillImageSource {
+    // pixels are 8 bit grayscale, width * height of them
+    pub fn new(pixels: &[u8], width: usize, height: usize) -> Self {
+        Self {
+            pixels: scale(pixels, width, height)
+        }
+    }
+
+    // decodes a PNG of any color type, returning None if it isn't a valid PNG
+    pub fn from_png(bytes: &[u8]) -> Option<Self> {
+        let mut decoder = png::Decoder::new(bytes);
+
+        decoder.set_transformations(png::Transformations::normalize_to_color8());
+
+        let mut reader = decoder.read_info().ok()?;
+
+        let mut buffer = vec![0; reader.output_buffer_size()];
+
+        let info = reader.next_frame(&mut buffer).ok()?;
+
+        let channels = info.color_type.samples();
+
+        let width = info.width as usize;
+        let height = info.height as usize;
+
+        let mut pixels = Vec::with_capacity(width * height);
+
+        for y in 0..height {
+            let line = &buffer[y * info.line_size..];
+
+            for x in 0..width {
+                let pixel = &line[x * channels..x * channels + channels];
+
+                pixels.push(match channels {
+                    1 | 2 => pixel[0],
+                    _ => luminance(pixel[0], pixel[1], pixel[2])
+                });
+            }
+        }
+
+        Some(Self::new(&pixels, width, height))
+    }
+}
+
+impl ImageSource for StillImageSource {
+    fn capture(&mut self, frame: &mut [u8]) {
+        frame.copy_from_slice(&self.pixels);
+    }
+}
+
+/*
+ * frames come from the frontend, e.g. a webcam. the frontend keeps a handle from `frame` and
+ * writes a new 128x112 grayscale frame into it whenever it has one, and every capture takes
+ * whichever frame is there at the time.
+ */
+pub struct BufferImageSource {
+    frame: Arc<Mutex<Vec<u8>>>
+}
+
+impl BufferImageSource {
+    pub fn new() -> Self {
+        Self {
+            frame: Arc::new(Mutex::new(vec![0xff; CAMERA_WIDTH * CAMERA_HEIGHT]))
+        }
+    }
+
+    pub fn frame(&self) -> Arc<Mutex<Vec<u8>>> {
+        self.frame.clone()
+    }
+
+    // for frontends that don't have a 128x112 frame on hand
+    pub fn update(&self, pixels: &[u8], width: usize, height: usize) {
+        *self.frame.lock().unwrap() = scale(pixels, width, height);
+    }
+}
+
+impl ImageSource for BufferImageSource {
+    fn capture(&mut self, frame: &mut [u8]) {
+        let buffer = self.frame.lock().unwrap();
+
+        if buffer.len() == frame.len() {
+            frame.copy_from_slice(&buffer);
+        }
+    }
+}
+
+fn luminance(r: u8, g: u8, b: u8) -> u8 {
+    ((r as u32 * 299 + g as u32 * 587 + b as u32 * 114) / 1000) as u8
+}
+
+// nearest neighbor, good enough for a 128x112 sensor
+fn scale(pixels: &[u8], width: usize, height: usize) -> Vec<u8> {
+    let mut scaled = vec![0xff; CAMERA_WIDTH * CAMERA_HEIGHT];
+
+    if width == 0 || height == 0 || pixels.len() < width * height {
+        return scaled;
+    }
+
+    for y in 0..CAMERA_HEIGHT {
+        for x in 0..CAMERA_WIDTH {
+            let source_x = x * width / CAMERA_WIDTH;
+            let source_y = y * height / CAMERA_HEIGHT;
+
+            scaled[x + y * CAMERA_WIDTH] = pixels[source_x + source_y * width];
+        }
+    }
+
+    scaled
+}
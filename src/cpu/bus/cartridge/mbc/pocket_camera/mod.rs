@@ -0,0 +1,332 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use image_source::{ImageSource, TestPatternSource, CAMERA_HEIGHT, CAMERA_WIDTH};
+use serde::{Deserialize, Serialize};
+
+use crate::cpu::bus::cartridge::backup_file::BackupFile;
+
+pub mod image_source;
+pub mod photos;
+
+// the camera always has 128KB of RAM, split into 16 banks
+pub const RAM_SIZE: usize = 0x20000;
+
+// setting bit 4 of the RAM bank maps the camera registers over 0xa000-0xbfff instead
+const REGISTERS_SELECTED: u8 = 1 << 4;
+
+const NUM_REGISTERS: usize = 0x36;
+
+const CONTROL: usize = 0x0;
+const GAIN_AND_EDGE: usize = 0x1;
+const EXPOSURE_HIGH: usize = 0x2;
+const EXPOSURE_LOW: usize = 0x3;
+const EDGE_RATIO_AND_INVERT: usize = 0x4;
+const DITHER_MATRIX: usize = 0x6;
+
+const CAPTURE_BUSY: u8 = 1;
+
+// captured photos get written to the start of the first RAM bank as 16x14 tiles
+const CAPTURE_ADDRESS: usize = 0x100;
+
+const TILES_PER_ROW: usize = CAMERA_WIDTH / 8;
+
+// how long a capture takes in CPU cycles, on top of the exposure time
+const CAPTURE_CYCLES: usize = 32446 * 4;
+const EXPOSURE_CYCLES: usize = 16 * 4;
+const NO_N_CYCLES: usize = 512 * 4;
+
+// edge enhancement strength in percent, picked with bits 4-6 of 0xa004
+const EDGE_RATIOS: [i32; 8] = [50, 75, 100, 125, 200, 300, 400, 500];
+
+/*
+ * Pocket Camera. besides its 128KB of RAM, it has a sensor controlled through a bank of
+ * registers at 0xa000-0xa035, mirrored every 0x80 bytes:
+ *
+ * 0xa000: bit 0 starts a capture and stays set while the camera is busy. the only one that can be read
+ * 0xa001: bit 7 is N, bits 5-6 pick the edge enhancement direction, bits 0-4 are the gain
+ * 0xa002-0xa003: exposure time, most significant byte first
+ * 0xa004: bits 4-6 are the edge enhancement ratio, bit 3 inverts the image, bits 0-2 are the output voltage
+ * 0xa006-0xa035: a 4x4 matrix of 3 thresholds each, used to dither the picture down to 4 shades
+ *
+ * the picture itself comes from an image source, which the frontend can swap out.
+ */
+#[derive(Serialize, Deserialize)]
+pub struct PocketCamera {
+    rom_bank: u8,
+    ram_bank: u8,
+    ram_enable: bool,
+    registers: Box<[u8]>,
+    capture_cycles_left: usize,
+    #[serde(skip_serializing)]
+    #[serde(skip_deserializing)]
+    #[serde(default = "PocketCamera::test_pattern_source")]
+    pub image_source: Box<dyn ImageSource>,
+    pub backup_file: BackupFile
+}
+
+impl PocketCamera {
+    pub fn check_save(&mut self, is_cloud: bool) -> bool {
+        let min_diff = if is_cloud { 1500 } else { 500 };
+
+        let current_time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("an error occurred")
+            .as_millis();
+
+        let last_updated = self.backup_file.last_updated;
+
+        if self.backup_file.is_dirty &&
+            current_time > last_updated &&
+            last_updated != 0
+        {
+            let diff = current_time - last_updated;
+            if diff >= min_diff {
+                self.backup_file.last_updated = 0;
+                return true;
+            }
+        }
+
+        false
+    }
+
+    pub fn has_saved(&mut self) -> bool {
+        let return_val = self.backup_file.is_dirty;
+
+        self.backup_file.is_dirty = false;
+
+        return_val
+    }
+
+    fn test_pattern_source() -> Box<dyn ImageSource> {
+        Box::new(TestPatternSource)
+    }
+
+    pub fn connect(&mut self, image_source: Box<dyn ImageSource>) {
+        self.image_source = image_source;
+    }
+
+    pub fn disconnect(&mut self) -> Box<dyn ImageSource> {
+        std::mem::replace(&mut self.image_source, Self::test_pattern_source())
+    }
+
+    fn is_busy(&self) -> bool {
+        self.capture_cycles_left > 0
+    }
+
+    pub fn tick(&mut self, cycles: usize) {
+        if self.is_busy() {
+            self.capture_cycles_left = self.capture_cycles_left.saturating_sub(cycles);
+
+            if !self.is_busy() {
+                self.capture();
+            }
+        }
+    }
+
+    pub fn read(&mut self, address: u16, rom: &[u8]) -> u8 {
+        match address {
+            0x0000..=0x3fff => rom[address as usize],
+            0x4000..=0x7fff => {
+                let actual_address = self.get_rom_address(address);
+
+                rom[actual_address % rom.len()]
+            }
+            0xa000..=0xbfff if self.ram_bank & REGISTERS_SELECTED != 0 => self.read_register(address),
+            // RAM can always be read, except while the camera is busy writing to it
+            0xa000..=0xbfff if self.is_busy() => 0,
+            0xa000..=0xbfff => {
+                let actual_address = self.get_ram_address(address);
+
+                self.backup_file.read8(actual_address)
+            }
+            _ => panic!("invalid address given: 0x{:x}", address)
+        }
+    }
+
+    pub fn read16(&mut self, address: u16, rom: &[u8]) -> u16 {
+        match address {
+            0x0000..=0x3fff => {
+                unsafe { *(&rom[address as usize] as *const u8 as *const u16) }
+            }
+            0x4000..=0x7fff => {
+                let actual_address = self.get_rom_address(address);
+
+                unsafe { *(&rom[actual_address % rom.len()] as *const u8 as *const u16) }
+            }
+            0xa000..=0xbfff => self.read(address, rom) as u16 | (self.read(address + 1, rom) as u16) << 8,
+            _ => panic!("invalid address given: 0x{:x}", address)
+        }
+    }
+
+    pub fn write(&mut self, address: u16, value: u8) {
+        match address {
+            0x0000..=0x1fff => self.ram_enable = value & 0xf == 0xa,
+            0x2000..=0x3fff => self.rom_bank = value & 0x3f,
+            0x4000..=0x5fff => self.ram_bank = value & 0x1f,
+            0xa000..=0xbfff if self.ram_bank & REGISTERS_SELECTED != 0 => self.write_register(address, value),
+            0xa000..=0xbfff if self.ram_enable => {
+                let actual_address = self.get_ram_address(address);
+
+                self.backup_file.write8(actual_address, value);
+            }
+            _ => ()
+        }
+    }
+
+    pub fn write16(&mut self, address: u16, value: u16) {
+        match address {
+            0xa000..=0xbfff => {
+                self.write(address, value as u8);
+                self.write(address + 1, (value >> 8) as u8);
+            }
+            _ => self.write(address, value as u8)
+        }
+    }
+
+    pub fn new(save_path: Option<String>, is_desktop: bool) -> Self {
+        Self {
+            rom_bank: 1,
+            ram_bank: 0,
+            ram_enable: false,
+            registers: vec![0; NUM_REGISTERS].into_boxed_slice(),
+            capture_cycles_left: 0,
+            image_source: Self::test_pattern_source(),
+            backup_file: BackupFile::new(save_path, RAM_SIZE, true, is_desktop)
+        }
+    }
+
+    fn read_register(&self, address: u16) -> u8 {
+        match (address & 0x7f) as usize {
+            CONTROL => (self.registers[CONTROL] & !CAPTURE_BUSY) | self.is_busy() as u8,
+            _ => 0
+        }
+    }
+
+    fn write_register(&mut self, address: u16, value: u8) {
+        let index = (address & 0x7f) as usize;
+
+        if index == CONTROL {
+            self.registers[CONTROL] = value & 0x7;
+
+            if value & CAPTURE_BUSY != 0 && !self.is_busy() {
+                self.capture_cycles_left = self.capture_time();
+            }
+        } else if index < NUM_REGISTERS {
+            self.registers[index] = value;
+        }
+    }
+
+    fn exposure(&self) -> usize {
+        (self.registers[EXPOSURE_HIGH] as usize) << 8 | self.registers[EXPOSURE_LOW] as usize
+    }
+
+    fn capture_time(&self) -> usize {
+        let n = (self.registers[GAIN_AND_EDGE] >> 7) & 0x1 == 1;
+
+        CAPTURE_CYCLES + self.exposure() * EXPOSURE_CYCLES + if n { 0 } else { NO_N_CYCLES }
+    }
+
+    // takes the picture once the camera is done exposing, and writes it out to RAM as tiles
+    fn capture(&mut self) {
+        let mut frame = vec![0xff; CAMERA_WIDTH * CAMERA_HEIGHT];
+
+        self.image_source.capture(&mut frame);
+
+        let exposed = self.apply_exposure(&frame);
+        let enhanced = self.enhance_edges(&exposed);
+
+        let invert = (self.registers[EDGE_RATIO_AND_INVERT] >> 3) & 0x1 == 1;
+
+        for y in 0..CAMERA_HEIGHT {
+            for x in 0..CAMERA_WIDTH {
+                let mut value = enhanced[x + y * CAMERA_WIDTH].clamp(0, 0xff) as u8;
+
+                if invert {
+                    value = 0xff - value;
+                }
+
+                let shade = self.dither(value, x, y);
+
+                let tile = x / 8 + (y / 8) * TILES_PER_ROW;
+                let address = CAPTURE_ADDRESS + tile * 16 + (y % 8) * 2;
+
+                let bit = 7 - (x % 8);
+
+                let lower = self.backup_file.read8(address) & !(1 << bit);
+                let upper = self.backup_file.read8(address + 1) & !(1 << bit);
+
+                self.backup_file.write8(address, lower | (shade & 0x1) << bit);
+                self.backup_file.write8(address + 1, upper | ((shade >> 1) & 0x1) << bit);
+            }
+        }
+    }
+
+    // an exposure of 0x1000 leaves the picture as is, anything below that darkens it
+    fn apply_exposure(&self, frame: &[u8]) -> Vec<i32> {
+        let exposure = self.exposure() as i32;
+
+        frame.iter().map(|pixel| (*pixel as i32 * exposure / 0x1000).min(0xff)).collect()
+    }
+
+    /*
+     * sharpens the picture by adding on the difference between every pixel and its neighbors,
+     * either horizontally, vertically or both depending on bits 5-6 of 0xa001.
+     */
+    fn enhance_edges(&self, frame: &[i32]) -> Vec<i32> {
+        let direction = (self.registers[GAIN_AND_EDGE] >> 5) & 0x3;
+
+        if direction == 0 {
+            return frame.to_vec();
+        }
+
+        let ratio = EDGE_RATIOS[((self.registers[EDGE_RATIO_AND_INVERT] >> 4) & 0x7) as usize];
+
+        let pixel = |x: usize, y: usize| frame[x.min(CAMERA_WIDTH - 1) + y.min(CAMERA_HEIGHT - 1) * CAMERA_WIDTH];
+
+        let mut enhanced = vec![0; frame.len()];
+
+        for y in 0..CAMERA_HEIGHT {
+            for x in 0..CAMERA_WIDTH {
+                let center = pixel(x, y);
+
+                let horizontal = 2 * center - pixel(x.saturating_sub(1), y) - pixel(x + 1, y);
+                let vertical = 2 * center - pixel(x, y.saturating_sub(1)) - pixel(x, y + 1);
+
+                let edge = match direction {
+                    1 => horizontal,
+                    2 => vertical,
+                    _ => (horizontal + vertical) / 2
+                };
+
+                enhanced[x + y * CAMERA_WIDTH] = center + edge * ratio / 100;
+            }
+        }
+
+        enhanced
+    }
+
+    // every spot in the 4x4 matrix has 3 thresholds, anything below the first one is black
+    fn dither(&self, value: u8, x: usize, y: usize) -> u8 {
+        let index = DITHER_MATRIX + ((x % 4) + (y % 4) * 4) * 3;
+
+        let thresholds = &self.registers[index..index + 3];
+
+        if value < thresholds[0] {
+            3
+        } else if value < thresholds[1] {
+            2
+        } else if value < thresholds[2] {
+            1
+        } else {
+            0
+        }
+    }
+
+    fn get_ram_address(&self, address: u16) -> usize {
+        (address & 0x1fff) as usize | ((self.ram_bank & 0xf) as usize) << 13
+    }
+
+    fn get_rom_address(&self, address: u16) -> usize {
+        (address as usize) & 0x3fff | (self.rom_bank as usize) << 14
+    }
+}
@@ -0,0 +1,89 @@
+use crate::cpu::bus::ppu::GRAYSCALE;
+
+use super::image_source::{CAMERA_HEIGHT, CAMERA_WIDTH};
+
+pub const NUM_PHOTOS: usize = 30;
+
+// slots start at the second RAM bank, each one taking up 0x1000 bytes with the picture first
+const PHOTOS_START: usize = 0x2000;
+const PHOTO_SIZE: usize = 0x1000;
+
+// the game keeps the album order here, one byte per slot with 0xff meaning the slot is empty
+const ALBUM_START: usize = 0x11b2;
+
+const TILES_PER_ROW: usize = CAMERA_WIDTH / 8;
+
+pub struct Photo {
+    pub slot: usize,
+    // where the photo shows up in the game's album, None for deleted or empty slots
+    pub album_number: Option<usize>,
+    pub width: usize,
+    pub height: usize,
+    pub data: Vec<u8>
+}
+
+impl Photo {
+    fn new(slot: usize, save: &[u8]) -> Self {
+        let album_number = match save[ALBUM_START + slot] {
+            0xff => None,
+            number => Some(number as usize)
+        };
+
+        let tiles = &save[PHOTOS_START + slot * PHOTO_SIZE..];
+
+        let mut data = Vec::with_capacity(CAMERA_WIDTH * CAMERA_HEIGHT * 4);
+
+        for y in 0..CAMERA_HEIGHT {
+            for x in 0..CAMERA_WIDTH {
+                let tile = x / 8 + (y / 8) * TILES_PER_ROW;
+                let address = tile * 16 + (y % 8) * 2;
+
+                let bit = 7 - (x % 8);
+
+                let lower = (tiles[address] >> bit) & 0x1;
+                let upper = (tiles[address + 1] >> bit) & 0x1;
+
+                let color = GRAYSCALE[(lower | upper << 1) as usize];
+
+                data.push(color.r);
+                data.push(color.g);
+                data.push(color.b);
+                data.push(0xff);
+            }
+        }
+
+        Self {
+            slot,
+            album_number,
+            width: CAMERA_WIDTH,
+            height: CAMERA_HEIGHT,
+            data
+        }
+    }
+
+    pub fn to_png(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+
+        {
+            let mut encoder = png::Encoder::new(&mut bytes, self.width as u32, self.height as u32);
+
+            encoder.set_color(png::ColorType::Rgba);
+            encoder.set_depth(png::BitDepth::Eight);
+
+            let mut writer = encoder.write_header().unwrap();
+
+            writer.write_image_data(&self.data).unwrap();
+        }
+
+        bytes
+    }
+}
+
+// pulls all 30 photo slots out of a Pocket Camera save, empty or not
+pub fn extract_photos(save: &[u8]) -> Vec<Photo> {
+    if save.len() < PHOTOS_START + NUM_PHOTOS * PHOTO_SIZE {
+        return Vec::new();
+    }
+
+    (0..NUM_PHOTOS).map(|slot| Photo::new(slot, save)).collect()
+}
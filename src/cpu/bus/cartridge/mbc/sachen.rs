@@ -0,0 +1,128 @@
+use serde::{Deserialize, Serialize};
+
+// how many reads of 0x0100-0x01ff it takes to get through a lock stage
+const LOCK_READS: u8 = 0x31;
+
+#[derive(Copy, Clone, PartialEq, Serialize, Deserialize)]
+enum LockState {
+    LockedDmg,
+    LockedCgb,
+    Unlocked
+}
+
+/*
+ * Sachen's MMC1 and MMC2. they swap address lines A0/A6 and A1/A4 on every read of
+ * 0x0100-0x01ff, so the header in the ROM file is scrambled.
+ *
+ * to get past the boot ROM with their own logo, they start out locked and force A7 high
+ * on header reads, so the logo that gets drawn comes from 0x0184 instead of 0x0104. once the
+ * boot ROM is done drawing they unlock, and the logo check sees Nintendo's. MMC2 has an
+ * extra lock stage after that for the CGB boot ROM, which leaves A7 alone.
+ *
+ * 0x0000-0x1fff: base ROM bank
+ * 0x2000-0x3fff: ROM bank
+ * 0x4000-0x5fff: ROM bank mask, the bits set in it come from the base bank instead
+ *
+ * the base bank and mask can only be written while bits 4-5 of the base bank are both set,
+ * which lets multicart menus lock the game in.
+ */
+#[derive(Serialize, Deserialize)]
+pub struct Sachen {
+    base_rom_bank: u8,
+    rom_bank: u8,
+    rom_bank_mask: u8,
+    lock_state: LockState,
+    lock_reads: u8,
+    is_mmc2: bool
+}
+
+impl Sachen {
+    // without a boot ROM there's nothing to get past, so it starts out unlocked
+    pub fn new(is_mmc2: bool, locked: bool) -> Self {
+        Self {
+            base_rom_bank: 0xff,
+            rom_bank: 1,
+            rom_bank_mask: 0,
+            lock_state: if locked { LockState::LockedDmg } else { LockState::Unlocked },
+            lock_reads: 0,
+            is_mmc2
+        }
+    }
+
+    pub fn read(&mut self, address: u16, rom: &[u8]) -> u8 {
+        match address {
+            0x0000..=0x3fff => {
+                let address = self.header_address(address);
+
+                let bank = (self.base_rom_bank & self.rom_bank_mask) as usize;
+
+                rom[(address as usize | bank << 14) % rom.len()]
+            }
+            0x4000..=0x7fff => {
+                let bank = ((self.base_rom_bank & self.rom_bank_mask) | (self.rom_bank & !self.rom_bank_mask)) as usize;
+
+                rom[((address as usize) & 0x3fff | bank << 14) % rom.len()]
+            }
+            _ => 0xff
+        }
+    }
+
+    pub fn read16(&mut self, address: u16, rom: &[u8]) -> u16 {
+        self.read(address, rom) as u16 | (self.read(address + 1, rom) as u16) << 8
+    }
+
+    pub fn write(&mut self, address: u16, value: u8) {
+        let writable = self.base_rom_bank & 0x30 == 0x30;
+
+        match address {
+            0x0000..=0x1fff if writable => self.base_rom_bank = value,
+            0x2000..=0x3fff => self.rom_bank = if value == 0 { 1 } else { value },
+            0x4000..=0x5fff if writable => self.rom_bank_mask = value,
+            _ => ()
+        }
+    }
+
+    pub fn write16(&mut self, address: u16, value: u16) {
+        self.write(address, value as u8);
+    }
+
+    fn header_address(&mut self, address: u16) -> u16 {
+        if address & 0xff00 != 0x0100 {
+            return address;
+        }
+
+        let mut address = address;
+
+        match self.lock_state {
+            LockState::LockedDmg => {
+                self.lock_reads += 1;
+
+                if self.lock_reads == LOCK_READS {
+                    self.lock_reads = 0;
+                    self.lock_state = if self.is_mmc2 { LockState::LockedCgb } else { LockState::Unlocked };
+                } else {
+                    address |= 0x80;
+                }
+            }
+            LockState::LockedCgb => {
+                self.lock_reads += 1;
+
+                if self.lock_reads == LOCK_READS {
+                    self.lock_state = LockState::Unlocked;
+                }
+            }
+            LockState::Unlocked => ()
+        }
+
+        unscramble_address(address)
+    }
+}
+
+// swaps A0 with A6 and A1 with A4
+pub fn unscramble_address(address: u16) -> u16 {
+    (address & 0xffac) |
+        (address & 0x40) >> 6 |
+        (address & 0x10) >> 3 |
+        (address & 0x02) << 3 |
+        (address & 0x01) << 6
+}
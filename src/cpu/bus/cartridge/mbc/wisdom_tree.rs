@@ -0,0 +1,43 @@
+use serde::{Deserialize, Serialize};
+
+/*
+ * Wisdom Tree's mapper. the whole 0x0000-0x7fff area is switched 32KB at a time, and the
+ * bank comes from the low byte of the address written to in 0x0000-0x3fff, not the value.
+ */
+#[derive(Serialize, Deserialize)]
+pub struct WisdomTree {
+    rom_bank: u8
+}
+
+impl WisdomTree {
+    pub fn new() -> Self {
+        Self {
+            rom_bank: 0
+        }
+    }
+
+    pub fn read(&mut self, address: u16, rom: &[u8]) -> u8 {
+        match address {
+            0x0000..=0x7fff => rom[self.get_rom_address(address) % rom.len()],
+            _ => 0xff
+        }
+    }
+
+    pub fn read16(&mut self, address: u16, rom: &[u8]) -> u16 {
+        self.read(address, rom) as u16 | (self.read(address + 1, rom) as u16) << 8
+    }
+
+    pub fn write(&mut self, address: u16, _value: u8) {
+        if let 0x0000..=0x3fff = address {
+            self.rom_bank = address as u8;
+        }
+    }
+
+    pub fn write16(&mut self, address: u16, value: u16) {
+        self.write(address, value as u8);
+    }
+
+    fn get_rom_address(&self, address: u16) -> usize {
+        address as usize | (self.rom_bank as usize) << 15
+    }
+}
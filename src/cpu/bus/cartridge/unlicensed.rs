@@ -0,0 +1,71 @@
+use super::{mbc::sachen::unscramble_address, CARTRIDGE_TYPE_ADDR, NINTENDO_LOGO, NINTENDO_LOGO_ADDR};
+
+const TITLE_ADDR: usize = 0x134;
+const CGB_ADDR: usize = 0x143;
+
+// a cartridge without an MBC can only hold 32KB
+const MAX_PLAIN_ROM_SIZE: usize = 0x8000;
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum UnlicensedMapper {
+    WisdomTree,
+    SachenMmc1,
+    SachenMmc2,
+    M161
+}
+
+/*
+ * unlicensed carts don't go through Nintendo, so their headers can't be trusted. these
+ * fingerprint the ROM instead, and anything that doesn't match falls through to the header.
+ */
+pub fn detect(rom: &[u8]) -> Option<UnlicensedMapper> {
+    if rom.len() < 0x150 {
+        return None;
+    }
+
+    if is_sachen(rom) {
+        // MMC2 was made for CGB games
+        return Some(if read_sachen(rom, CGB_ADDR) & 0x80 != 0 {
+            UnlicensedMapper::SachenMmc2
+        } else {
+            UnlicensedMapper::SachenMmc1
+        });
+    }
+
+    // the Mani 4 in 1 carts say they're MBC3, but only the menu's title gives them away
+    if rom[CARTRIDGE_TYPE_ADDR] == 0x10 && rom.len() == 0x40000 && rom[TITLE_ADDR..].starts_with(b"TETRIS SET") {
+        return Some(UnlicensedMapper::M161);
+    }
+
+    let is_plain = matches!(rom[CARTRIDGE_TYPE_ADDR], 0x00 | 0xc0);
+
+    /*
+     * Rocket Games' boards also claim to have no MBC, but there's nothing in their ROMs to tell
+     * them apart from homebrew that's just padded past 32KB, so they're left to the header
+     */
+    if is_plain && rom.len() > MAX_PLAIN_ROM_SIZE && is_wisdom_tree(rom) {
+        return Some(UnlicensedMapper::WisdomTree);
+    }
+
+    None
+}
+
+// Wisdom Tree put their name in every game, spelled with either a space or a null in the middle
+fn is_wisdom_tree(rom: &[u8]) -> bool {
+    let bank0 = &rom[..rom.len().min(0x4000)];
+
+    [&b"WISDOM TREE"[..], &b"WISDOM\x00TREE"[..]]
+        .iter()
+        .any(|name| bank0.windows(name.len()).any(|window| window == *name))
+}
+
+fn read_sachen(rom: &[u8], address: usize) -> u8 {
+    rom[unscramble_address(address as u16) as usize]
+}
+
+// the logo is only where it should be once the address lines get unscrambled
+fn is_sachen(rom: &[u8]) -> bool {
+    let logo_range = NINTENDO_LOGO_ADDR..NINTENDO_LOGO_ADDR + NINTENDO_LOGO.len();
+
+    rom[logo_range.clone()] != NINTENDO_LOGO && logo_range.zip(NINTENDO_LOGO).all(|(address, byte)| read_sachen(rom, address) == byte)
+}
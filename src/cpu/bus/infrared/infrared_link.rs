@@ -0,0 +1,19 @@
+/*
+ * An infrared link carries the light from this instance's LED over to whatever is
+ * pointed at it, and reports whether any light is coming back.
+ */
+pub trait InfraredLink: Send {
+    fn set_led(&mut self, on: bool);
+    fn is_receiving_light(&mut self) -> bool;
+}
+
+// nothing in front of the sensor, so there's never any light to detect
+pub struct NullInfraredLink;
+
+impl InfraredLink for NullInfraredLink {
+    fn set_led(&mut self, _on: bool) {}
+
+    fn is_receiving_light(&mut self) -> bool {
+        false
+    }
+}
@@ -0,0 +1,96 @@
+use infrared_link::{InfraredLink, NullInfraredLink};
+use serde::{Deserialize, Serialize};
+
+pub mod infrared_link;
+pub mod local_infrared_link;
+pub mod tcp_infrared_link;
+
+const LED_ON: u8 = 1;
+const LIGHT_NOT_DETECTED: u8 = 1 << 1;
+const READ_ENABLE: u8 = 0b11 << 6;
+
+/*
+ * RP register (0xff56), the CGB's infrared port.
+ *
+ * bit 0: LED on (read/write)
+ * bit 1: 0 = receiving light, 1 = normal (read only)
+ * bits 6-7: both set to enable reading the sensor (read/write)
+ *
+ * HuC1 and HuC3 cartridges have their own LED and sensor, mapped to 0xa000 instead. they
+ * share the same link, so the light coming from either LED gets sent.
+ */
+#[derive(Serialize, Deserialize)]
+pub struct Infrared {
+    rp: u8,
+    cartridge_led: bool,
+    #[serde(skip_serializing)]
+    #[serde(skip_deserializing)]
+    #[serde(default = "Infrared::null_infrared_link")]
+    pub link: Box<dyn InfraredLink>
+}
+
+impl Infrared {
+    pub fn new() -> Self {
+        Self {
+            rp: 0,
+            cartridge_led: false,
+            link: Self::null_infrared_link()
+        }
+    }
+
+    fn null_infrared_link() -> Box<dyn InfraredLink> {
+        Box::new(NullInfraredLink)
+    }
+
+    pub fn connect(&mut self, link: Box<dyn InfraredLink>) {
+        self.link = link;
+
+        self.update_led();
+    }
+
+    fn update_led(&mut self) {
+        self.link.set_led(self.rp & LED_ON != 0 || self.cartridge_led);
+    }
+
+    pub fn disconnect(&mut self) -> Box<dyn InfraredLink> {
+        self.link.set_led(false);
+
+        std::mem::replace(&mut self.link, Self::null_infrared_link())
+    }
+
+    pub fn read(&mut self, cgb_mode: bool) -> u8 {
+        if !cgb_mode {
+            return 0xff;
+        }
+
+        let mut value = self.rp | 0x3c | LIGHT_NOT_DETECTED;
+
+        if self.rp & READ_ENABLE == READ_ENABLE && self.link.is_receiving_light() {
+            value &= !LIGHT_NOT_DETECTED;
+        }
+
+        value
+    }
+
+    pub fn write(&mut self, value: u8, cgb_mode: bool) {
+        if !cgb_mode {
+            return;
+        }
+
+        self.rp = value & (READ_ENABLE | LED_ON);
+
+        self.update_led();
+    }
+
+    // bit 0 is set while the cartridge's sensor is picking up light
+    pub fn read_cartridge(&mut self) -> u8 {
+        0xc0 | self.link.is_receiving_light() as u8
+    }
+
+    // bit 0 turns the cartridge's LED on
+    pub fn write_cartridge(&mut self, value: u8) {
+        self.cartridge_led = value & 0x1 == 1;
+
+        self.update_led();
+    }
+}
@@ -0,0 +1,113 @@
+use std::{
+    io::{self, Read, Write},
+    net::{TcpListener, TcpStream},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+        Mutex
+    },
+    thread
+};
+
+use super::infrared_link::InfraredLink;
+
+/*
+ * Infrared link between two emulator instances over TCP. Every message is a single
+ * byte with the new state of the sender's LED, which only gets sent when it changes.
+ * Since the other side only sees the LED once per message, games that time the
+ * length of the pulses won't see exactly what real hardware would.
+ */
+pub struct TcpInfraredLink {
+    writer: Arc<Mutex<Option<TcpStream>>>,
+    remote_led: Arc<AtomicBool>,
+    led: bool
+}
+
+impl TcpInfraredLink {
+    pub fn host(port: u16) -> io::Result<Self> {
+        let listener = TcpListener::bind(("127.0.0.1", port))?;
+
+        let link = Self::new();
+
+        let writer = link.writer.clone();
+        let remote_led = link.remote_led.clone();
+
+        thread::spawn(move || {
+            if let Ok((stream, _)) = listener.accept() {
+                Self::start_connection(stream, writer, remote_led);
+            }
+        });
+
+        Ok(link)
+    }
+
+    pub fn connect(address: &str) -> io::Result<Self> {
+        let stream = TcpStream::connect(address)?;
+
+        let link = Self::new();
+
+        let writer = link.writer.clone();
+        let remote_led = link.remote_led.clone();
+
+        thread::spawn(move || Self::start_connection(stream, writer, remote_led));
+
+        Ok(link)
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.writer.lock().unwrap().is_some()
+    }
+
+    fn new() -> Self {
+        Self {
+            writer: Arc::new(Mutex::new(None)),
+            remote_led: Arc::new(AtomicBool::new(false)),
+            led: false
+        }
+    }
+
+    fn start_connection(mut stream: TcpStream, writer: Arc<Mutex<Option<TcpStream>>>, remote_led: Arc<AtomicBool>) {
+        stream.set_nodelay(true).unwrap_or(());
+
+        match stream.try_clone() {
+            Ok(stream_writer) => *writer.lock().unwrap() = Some(stream_writer),
+            Err(_) => return
+        }
+
+        let mut message = [0; 1];
+
+        while stream.read_exact(&mut message).is_ok() {
+            remote_led.store(message[0] != 0, Ordering::Relaxed);
+        }
+
+        remote_led.store(false, Ordering::Relaxed);
+
+        *writer.lock().unwrap() = None;
+    }
+}
+
+impl InfraredLink for TcpInfraredLink {
+    fn set_led(&mut self, on: bool) {
+        if on == self.led {
+            return;
+        }
+
+        self.led = on;
+
+        let mut writer = self.writer.lock().unwrap();
+
+        let mut disconnected = false;
+
+        if let Some(stream) = &mut *writer {
+            disconnected = stream.write_all(&[on as u8]).is_err();
+        }
+
+        if disconnected {
+            *writer = None;
+        }
+    }
+
+    fn is_receiving_light(&mut self) -> bool {
+        self.remote_led.load(Ordering::Relaxed)
+    }
+}
@@ -3,6 +3,8 @@ use std::collections::HashMap;
 use bitflags::bitflags;
 use serde::{Deserialize, Serialize};
 
+use super::interrupt_register::InterruptRegister;
+
 bitflags! {
     #[derive(Copy, Clone, Serialize, Deserialize)]
     pub struct JoypadRegister: u8 {
@@ -35,12 +37,17 @@ pub enum JoypadButtons {
     None
 }
 
+// the SGB can poll up to four controllers through MLT_REQ
+pub const MAX_PLAYERS: usize = 4;
+
 #[derive(Serialize, Deserialize)]
 pub struct Joypad {
     pub select_buttons: bool,
     pub select_dpad: bool,
     pub joypad_register: JoypadRegister,
-    pressed_buttons: HashMap<JoypadButtons, bool>
+    pressed_buttons: [HashMap<JoypadButtons, bool>; MAX_PLAYERS],
+    num_players: usize,
+    current_player: usize
 }
 
 impl Joypad {
@@ -48,37 +55,106 @@ impl Joypad {
         Self {
             select_buttons: false,
             select_dpad: false,
-            pressed_buttons: HashMap::new(),
+            pressed_buttons: std::array::from_fn(|_| HashMap::new()),
+            num_players: 1,
+            current_player: 0,
             joypad_register: JoypadRegister::from_bits_retain(0xf)
         }
     }
 
-    pub fn write(&mut self, value: u8) {
+    pub fn write(&mut self, value: u8, interrupt_register: &mut InterruptRegister) {
+        let previous_lines = self.lines();
+
         self.select_buttons = (value >> 5) & 0b1 == 0;
         self.select_dpad = (value >> 4) & 0b1 == 0;
+
+        // selecting a group with a button already held pulls its line low as well
+        self.check_interrupt(previous_lines, interrupt_register);
     }
 
-    pub fn read(&mut self) -> u8 {
+    pub fn is_pressed(&self, button: JoypadButtons) -> bool {
+        self.is_player_pressed(0, button)
+    }
+
+    fn is_player_pressed(&self, player: usize, button: JoypadButtons) -> bool {
+        *self.pressed_buttons[player].get(&button).unwrap_or(&false)
+    }
+
+    pub fn num_players(&self) -> usize {
+        self.num_players
+    }
+
+    // MLT_REQ, the SGB goes back to player 1 whenever the number of players changes
+    pub fn set_num_players(&mut self, num_players: usize) {
+        self.num_players = num_players;
+        self.current_player = 0;
+    }
+
+    pub fn next_player(&mut self) {
+        self.current_player = (self.current_player + 1) % self.num_players;
+    }
+
+    // state of the four input lines, a line goes low when a pressed button in any of the selected groups is wired to it
+    pub fn lines(&self) -> JoypadRegister {
+        let mut lines = JoypadRegister::all();
+
+        let is_pressed = |button| self.is_player_pressed(self.current_player, button);
+
         if self.select_buttons {
-            self.joypad_register.set(JoypadRegister::A_RIGHT, !*self.pressed_buttons.get(&JoypadButtons::A).unwrap_or(&false));
-            self.joypad_register.set(JoypadRegister::B_LEFT, !*self.pressed_buttons.get(&JoypadButtons::B).unwrap_or(&false));
-            self.joypad_register.set(JoypadRegister::START_DOWN, !*self.pressed_buttons.get(&JoypadButtons::Start).unwrap_or(&false));
-            self.joypad_register.set(JoypadRegister::SELECT_UP, !*self.pressed_buttons.get(&JoypadButtons::Select).unwrap_or(&false));
-        } else {
-            self.joypad_register.set(JoypadRegister::A_RIGHT, !*self.pressed_buttons.get(&JoypadButtons::Right).unwrap_or(&false));
-            self.joypad_register.set(JoypadRegister::B_LEFT, !*self.pressed_buttons.get(&JoypadButtons::Left).unwrap_or(&false));
-            self.joypad_register.set(JoypadRegister::START_DOWN, !*self.pressed_buttons.get(&JoypadButtons::Down).unwrap_or(&false));
-            self.joypad_register.set(JoypadRegister::SELECT_UP, !*self.pressed_buttons.get(&JoypadButtons::Up).unwrap_or(&false));
+            lines.set(JoypadRegister::A_RIGHT, lines.contains(JoypadRegister::A_RIGHT) && !is_pressed(JoypadButtons::A));
+            lines.set(JoypadRegister::B_LEFT, lines.contains(JoypadRegister::B_LEFT) && !is_pressed(JoypadButtons::B));
+            lines.set(JoypadRegister::START_DOWN, lines.contains(JoypadRegister::START_DOWN) && !is_pressed(JoypadButtons::Start));
+            lines.set(JoypadRegister::SELECT_UP, lines.contains(JoypadRegister::SELECT_UP) && !is_pressed(JoypadButtons::Select));
         }
+        if self.select_dpad {
+            lines.set(JoypadRegister::A_RIGHT, lines.contains(JoypadRegister::A_RIGHT) && !is_pressed(JoypadButtons::Right));
+            lines.set(JoypadRegister::B_LEFT, lines.contains(JoypadRegister::B_LEFT) && !is_pressed(JoypadButtons::Left));
+            lines.set(JoypadRegister::START_DOWN, lines.contains(JoypadRegister::START_DOWN) && !is_pressed(JoypadButtons::Down));
+            lines.set(JoypadRegister::SELECT_UP, lines.contains(JoypadRegister::SELECT_UP) && !is_pressed(JoypadButtons::Up));
+        }
+
+        lines
+    }
+
+    pub fn is_any_line_low(&self) -> bool {
+        !self.lines().is_all()
+    }
+
+    pub fn read(&mut self) -> u8 {
+        self.joypad_register = if !self.select_buttons && !self.select_dpad {
+            // with neither group selected, the SGB puts the current player's ID on the lines (0xf for player 1, 0xe for player 2, etc)
+            JoypadRegister::from_bits_retain(0xf - self.current_player as u8)
+        } else {
+            self.lines()
+        };
+
+        0xc0 | self.joypad_register.bits() | (!self.select_dpad as u8) << 4 | (!self.select_buttons as u8) << 5
+    }
 
-        self.joypad_register.bits() | (self.select_dpad as u8) << 4 | (self.select_buttons as u8) << 5
+    // the joypad interrupt is requested whenever one of the lines goes from high to low
+    fn check_interrupt(&self, previous_lines: JoypadRegister, interrupt_register: &mut InterruptRegister) {
+        if !(previous_lines - self.lines()).is_empty() {
+            interrupt_register.set(InterruptRegister::JOYPAD, true);
+        }
     }
 
-    pub fn press_button(&mut self, button: JoypadButtons) {
-        self.pressed_buttons.insert(button, true);
+    pub fn press_button(&mut self, button: JoypadButtons, interrupt_register: &mut InterruptRegister) {
+        self.press_player_button(0, button, interrupt_register);
     }
 
     pub fn release_button(&mut self, button: JoypadButtons) {
-        self.pressed_buttons.remove(&button);
+        self.release_player_button(0, button);
     }
-}
\ No newline at end of file
+
+    pub fn press_player_button(&mut self, player: usize, button: JoypadButtons, interrupt_register: &mut InterruptRegister) {
+        let previous_lines = self.lines();
+
+        self.pressed_buttons[player].insert(button, true);
+
+        self.check_interrupt(previous_lines, interrupt_register);
+    }
+
+    pub fn release_player_button(&mut self, player: usize, button: JoypadButtons) {
+        self.pressed_buttons[player].remove(&button);
+    }
+}
@@ -0,0 +1,108 @@
+use serde::{Deserialize, Serialize};
+
+pub const OAM_SIZE: u16 = 0xa0;
+
+// writing to DMA takes an m-cycle to set up before the first byte gets copied
+const STARTUP_CYCLES: u8 = 1;
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum MemoryBus {
+    External,
+    Wram,
+    Video,
+    Oam
+}
+
+impl MemoryBus {
+    // on DMG, WRAM sits on the same bus as the cartridge. CGB gives it its own bus
+    pub fn from_address(address: u16, cgb_mode: bool) -> Option<Self> {
+        match address {
+            0x0000..=0x7fff | 0xa000..=0xbfff => Some(MemoryBus::External),
+            0x8000..=0x9fff => Some(MemoryBus::Video),
+            0xc000..=0xfdff => if cgb_mode { Some(MemoryBus::Wram) } else { Some(MemoryBus::External) },
+            0xfe00..=0xfeff => Some(MemoryBus::Oam),
+            _ => None
+        }
+    }
+}
+
+/*
+ * OAM DMA copies 160 bytes in to OAM, one byte per m-cycle. while it's running the DMA
+ * owns OAM along with whichever bus it's reading from, so the CPU only has full access to
+ * HRAM and the IO registers. writing DMA again restarts the transfer from the beginning
+ * once the new transfer finishes setting up, the old one keeps going until then.
+ */
+#[derive(Serialize, Deserialize)]
+pub struct OamDma {
+    register: u8,
+    source: u16,
+    index: u16,
+    active: bool,
+    pending: Option<(u16, u8)>,
+    pub last_value: u8
+}
+
+impl OamDma {
+    pub fn new() -> Self {
+        Self {
+            register: 0xff,
+            source: 0,
+            index: 0,
+            active: false,
+            pending: None,
+            last_value: 0xff
+        }
+    }
+
+    pub fn read(&self) -> u8 {
+        self.register
+    }
+
+    pub fn write(&mut self, value: u8) {
+        self.register = value;
+
+        self.pending = Some(((value as u16) << 8, STARTUP_CYCLES));
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    pub fn source_bus(&self, cgb_mode: bool) -> Option<MemoryBus> {
+        let address = self.source + self.index.min(OAM_SIZE - 1);
+
+        // sources past WRAM are mirrors of it
+        let address = if address >= 0xe000 { address - 0x2000 } else { address };
+
+        MemoryBus::from_address(address, cgb_mode)
+    }
+
+    // returns the source address and OAM offset of the byte to copy this m-cycle, if there is one
+    pub fn tick(&mut self) -> Option<(u16, u16)> {
+        if let Some((source, cycles)) = self.pending {
+            if cycles == 0 {
+                self.pending = None;
+
+                self.active = true;
+                self.source = source;
+                self.index = 0;
+            } else {
+                self.pending = Some((source, cycles - 1));
+            }
+        }
+
+        if !self.active {
+            return None;
+        }
+
+        let transfer = (self.source + self.index, self.index);
+
+        self.index += 1;
+
+        if self.index == OAM_SIZE {
+            self.active = false;
+        }
+
+        Some(transfer)
+    }
+}
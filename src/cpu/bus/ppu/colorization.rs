@@ -0,0 +1,349 @@
+use serde::{Deserialize, Serialize};
+
+use crate::cpu::bus::{cartridge::Cartridge, joypad::{Joypad, JoypadButtons}};
+
+use super::picture::Color;
+
+// the 4th letter of the title, used when more than one game shares the same checksum
+const DISAMBIGUATION_INDEX: usize = 3;
+
+// checksums from here on are shared by more than one game, so they also need the right 4th letter
+const FIRST_DUPLICATE_CHECKSUM: usize = 0x41;
+const NUM_DUPLICATE_CHECKSUMS: usize = 14;
+
+// every title checksum the boot ROM knows about, in the order it searches them
+const TITLE_CHECKSUMS: [u8; 79] = [
+    0x00, 0x88, 0x16, 0x36, 0xd1, 0xdb, 0xf2, 0x3c, 0x8c, 0x92, 0x3d, 0x5c, 0x58, 0xc9, 0x3e, 0x70,
+    0x1d, 0x59, 0x69, 0x19, 0x35, 0xa8, 0x14, 0xaa, 0x75, 0x95, 0x99, 0x34, 0x6f, 0x15, 0xff, 0x97,
+    0x4b, 0x90, 0x17, 0x10, 0x39, 0xf7, 0xf6, 0xa2, 0x49, 0x4e, 0x43, 0x68, 0xe0, 0x8b, 0xf0, 0xce,
+    0x0c, 0x29, 0xe8, 0xb7, 0x86, 0x9a, 0x52, 0x01, 0x9d, 0x71, 0x9c, 0xbd, 0x5d, 0x6d, 0x67, 0x3f,
+    0x6b, 0xb3, 0x46, 0x28, 0xa5, 0xc6, 0xd3, 0x27, 0x61, 0x18, 0x66, 0x6a, 0xbf, 0x0d, 0xf4
+];
+
+/*
+ * the letters checked against the 4th letter of the title for the duplicate checksums. the boot ROM
+ * goes through these 14 at a time, so a duplicate checksum at index i can stand for the titles at
+ * i, i + 14 and i + 28, whichever one's letter matches.
+ */
+const DISAMBIGUATION_LETTERS: &[u8; 29] = b"BEFAARBEKEK R-URAR INAILICE R";
+
+// the palette combination picked for every title, indexed by where the title lookup ended up
+const TITLE_COMBINATIONS: [usize; 94] = [
+    0,  // blank title
+    4,  // ALLEY WAY
+    5,  // YAKUMAN
+    35, // BASEBALL
+    34, // TENNIS
+    3,  // TETRIS
+    31, // QIX
+    15, // DR.MARIO
+    10, // RADARMISSION
+    5,  // F1RACE
+    19, // YOSSY NO TAMAGO
+    36,
+    7,  // X
+    37, // MARIOLAND2
+    30, // YOSSY NO COOKIE
+    44, // ZELDA
+    21,
+    32,
+    31, // TETRIS FLASH
+    20, // DONKEY KONG
+    5,  // MARIO'S PICROSS
+    33,
+    13, // POKEMON RED
+    14, // POKEMON GREEN
+    5,  // PICROSS 2
+    29, // YOSSY NO PANEPON
+    5,  // KIRAKIRA KIDS
+    18, // GAMEBOY GALLERY
+    9,  // POCKETCAMERA
+    3,
+    2,  // BALLOON KID
+    26, // KINGOFTHEZOO
+    25, // DMG FOOTBALL
+    25, // WORLD CUP
+    41, // OTHELLO
+    42, // SUPER RC PRO-AM
+    26, // DYNABLASTER
+    45, // BOY AND BLOB GB2
+    42, // MEGAMAN
+    45, // STAR WARS-NOA
+    36,
+    38, // WAVERACE
+    26,
+    42, // LOLO2
+    30, // YOSHI'S COOKIE
+    41, // MYSTIC QUEST
+    34,
+    34, // TOPRANKINGTENNIS
+    5,  // MANSELL
+    42, // MEGAMAN3
+    6,  // SPACE INVADERS
+    5,  // GAME&WATCH
+    33, // DONKEYKONGLAND95
+    25, // ASTEROIDS/MISCMD
+    42, // STREET FIGHTER 2
+    42, // DEFENDER/JOUST
+    40, // KILLERINSTINCT95
+    2,  // TETRIS BLAST
+    16, // PINOCCHIO
+    25,
+    42, // BA.TOSHINDEN
+    42, // NETTOU KOF 95
+    5,
+    0,  // TETRIS PLUS
+    39, // DONKEYKONGLAND 3
+    36,
+    22, // SUPER MARIOLAND
+    25, // GOLF
+    6,  // SOLARSTRIKER
+    32, // GBWARS
+    12, // KAERUNOTAMENI
+    36,
+    11, // POKEMON BLUE
+    39, // DONKEYKONGLAND
+    18, // GAMEBOY GALLERY2
+    39, // DONKEYKONGLAND 2
+    24, // KID ICARUS
+    31, // TETRIS2
+    50,
+    17, // MOGURANYA
+    46,
+    6,  // GALAGA&GALAXIAN
+    27, // BT2RAGNAROKWORLD
+    0,  // KEN GRIFFEY JR
+    47,
+    41, // MAGNETIC SOCCER
+    41, // VEGAS STAKES
+    0,
+    0,  // MILLI/CENTI/PEDE
+    19, // MARIO & YOSHI
+    34, // SOCCER
+    23, // POKEBOM
+    18, // G&W GALLERY
+    29  // TETRIS ATTACK
+];
+
+// the boot ROM's 30 palettes of 4 colors, in the same BGR555 format as palette RAM
+const PALETTES: [u16; 120] = [
+    0x7fff, 0x32bf, 0x00d0, 0x0000,
+    0x639f, 0x4279, 0x15b0, 0x04cb,
+    0x7fff, 0x6e31, 0x454a, 0x0000,
+    0x7fff, 0x1bef, 0x0200, 0x0000,
+    0x7fff, 0x421f, 0x1cf2, 0x0000,
+    0x7fff, 0x5294, 0x294a, 0x0000,
+    0x7fff, 0x03ff, 0x012f, 0x0000,
+    0x7fff, 0x03ef, 0x01d6, 0x0000,
+    0x7fff, 0x42b5, 0x3dc8, 0x0000,
+    0x7e74, 0x03ff, 0x0180, 0x0000,
+    0x67ff, 0x77ac, 0x1a13, 0x2d6b,
+    0x7ed6, 0x4bff, 0x2175, 0x0000,
+    0x53ff, 0x4a5f, 0x7e52, 0x0000,
+    0x4fff, 0x7ed2, 0x3a4c, 0x1ce0,
+    0x03ed, 0x7fff, 0x255f, 0x0000,
+    0x036a, 0x021f, 0x03ff, 0x7fff,
+    0x7fff, 0x01df, 0x0112, 0x0000,
+    0x231f, 0x035f, 0x00f2, 0x0009,
+    0x7fff, 0x03ea, 0x011f, 0x0000,
+    0x299f, 0x001a, 0x000c, 0x0000,
+    0x7fff, 0x027f, 0x001f, 0x0000,
+    0x7fff, 0x03e0, 0x0206, 0x0120,
+    0x7fff, 0x7eeb, 0x001f, 0x7c00,
+    0x7fff, 0x3fff, 0x7e00, 0x001f,
+    0x7fff, 0x03ff, 0x001f, 0x0000,
+    0x03ff, 0x001f, 0x000c, 0x0000,
+    0x7fff, 0x033f, 0x0193, 0x0000,
+    0x0000, 0x4200, 0x037f, 0x7fff,
+    0x7fff, 0x7e8c, 0x7c00, 0x0000,
+    0x7fff, 0x1bef, 0x6180, 0x0000
+];
+
+/*
+ * OBJ0, OBJ1 and BG for each combination, as offsets in to the colors above. most of them start at
+ * a palette, but a few start in the middle of one and run over in to the next.
+ */
+const COMBINATIONS: [[usize; 3]; 51] = [
+    combination(4, 4, 29),     // right + A, also used for unknown titles
+    combination(18, 18, 18),   // right
+    combination(20, 20, 20),
+    combination(24, 24, 24),   // down + A
+    combination(9, 9, 9),
+    combination(0, 0, 0),      // up
+    combination(27, 27, 27),   // right + B
+    combination(5, 5, 5),      // left + B
+    combination(12, 12, 12),   // down
+    combination(26, 26, 26),
+    combination(16, 8, 8),
+    combination(4, 28, 28),
+    combination(4, 2, 2),
+    combination(3, 4, 4),
+    combination(4, 29, 29),
+    combination(28, 4, 28),
+    combination(2, 17, 2),
+    combination(16, 16, 8),
+    combination(4, 4, 7),
+    combination(4, 4, 18),
+    combination(4, 4, 20),
+    combination(19, 19, 9),
+    [15, 15, 44],
+    combination(17, 17, 2),
+    combination(4, 4, 2),
+    combination(4, 4, 3),
+    combination(28, 28, 0),
+    combination(3, 3, 0),
+    combination(0, 0, 1),      // up + B
+    combination(18, 22, 18),
+    combination(20, 22, 20),
+    combination(24, 22, 24),
+    combination(16, 22, 8),
+    combination(17, 4, 13),
+    [111, 0, 56],
+    [111, 16, 60],
+    combination(19, 22, 9),
+    combination(16, 28, 10),
+    combination(4, 23, 28),
+    combination(17, 22, 2),
+    combination(4, 0, 2),      // left + A
+    combination(4, 28, 3),
+    combination(28, 3, 0),
+    combination(3, 28, 4),     // up + A
+    combination(21, 28, 4),
+    combination(3, 28, 0),
+    combination(25, 3, 28),
+    combination(0, 28, 8),
+    combination(4, 3, 28),     // left
+    combination(28, 3, 6),     // down + B
+    combination(4, 28, 29)
+];
+
+// combinations held at boot, in the same order as the D-pad directions and then none, A and B
+const BUTTON_COMBINATIONS: [[usize; 3]; 4] = [
+    [5, 43, 28],
+    [48, 40, 7],
+    [8, 3, 49],
+    [1, 0, 6]
+];
+
+// a combination made up of whole palettes
+const fn combination(obj0: usize, obj1: usize, bg: usize) -> [usize; 3] {
+    [obj0 * 4, obj1 * 4, bg * 4]
+}
+
+// the same conversion the PPU uses for palette RAM
+const fn convert_color(color: u16) -> Color {
+    let r = (color & 0x1f) as u8;
+    let g = ((color >> 5) & 0x1f) as u8;
+    let b = ((color >> 10) & 0x1f) as u8;
+
+    Color {
+        r: r << 3 | r >> 2,
+        g: g << 3 | g >> 2,
+        b: b << 3 | b >> 2
+    }
+}
+
+const fn palette(offset: usize) -> [Color; 4] {
+    [
+        convert_color(PALETTES[offset]),
+        convert_color(PALETTES[offset + 1]),
+        convert_color(PALETTES[offset + 2]),
+        convert_color(PALETTES[offset + 3])
+    ]
+}
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum DmgPalette {
+    Bg,
+    Obj0,
+    Obj1
+}
+
+impl DmgPalette {
+    pub fn from_obj_palette(dmg_palette: u8) -> Self {
+        if dmg_palette == 0 { DmgPalette::Obj0 } else { DmgPalette::Obj1 }
+    }
+}
+
+/*
+ * the palettes the CGB boot ROM picks for DMG games. BGP, OBP0 and OBP1 each get their own set of
+ * 4 colors, which the registers then index in to like they would with the DMG shades.
+ */
+#[derive(Copy, Clone, Serialize, Deserialize)]
+pub struct CompatibilityPalettes {
+    pub bg: [Color; 4],
+    pub obj0: [Color; 4],
+    pub obj1: [Color; 4]
+}
+
+// the palettes used for titles the boot ROM doesn't know about, same as holding right + A
+const DEFAULT_PALETTES: CompatibilityPalettes = CompatibilityPalettes::from_combination(0);
+
+impl CompatibilityPalettes {
+    pub const fn new(bg: [Color; 4], obj0: [Color; 4], obj1: [Color; 4]) -> Self {
+        Self { bg, obj0, obj1 }
+    }
+
+    const fn from_combination(index: usize) -> Self {
+        let [obj0, obj1, bg] = COMBINATIONS[index];
+
+        Self::new(palette(bg), palette(obj0), palette(obj1))
+    }
+
+    pub fn default_palettes() -> Self {
+        DEFAULT_PALETTES
+    }
+
+    pub fn get(&self, palette: DmgPalette) -> &[Color; 4] {
+        match palette {
+            DmgPalette::Bg => &self.bg,
+            DmgPalette::Obj0 => &self.obj0,
+            DmgPalette::Obj1 => &self.obj1
+        }
+    }
+
+    // a button combo held while booting overrides whatever the title lookup picks
+    pub fn from_cartridge(cartridge: &Cartridge, joypad: &Joypad) -> Self {
+        Self::from_buttons(joypad).unwrap_or_else(|| Self::from_title(cartridge))
+    }
+
+    fn from_buttons(joypad: &Joypad) -> Option<Self> {
+        let directions = [JoypadButtons::Up, JoypadButtons::Left, JoypadButtons::Down, JoypadButtons::Right];
+
+        let direction = directions.iter().position(|button| joypad.is_pressed(*button))?;
+
+        // A wins if both are held
+        let button = if joypad.is_pressed(JoypadButtons::A) {
+            1
+        } else if joypad.is_pressed(JoypadButtons::B) {
+            2
+        } else {
+            0
+        };
+
+        Some(Self::from_combination(BUTTON_COMBINATIONS[direction][button]))
+    }
+
+    fn from_title(cartridge: &Cartridge) -> Self {
+        // only Nintendo published games get looked up, everything else uses the default
+        if !cartridge.nintendo_licensee() {
+            return DEFAULT_PALETTES;
+        }
+
+        let index = Self::title_index(cartridge.title_checksum(), cartridge.title()[DISAMBIGUATION_INDEX]);
+
+        index.map(|index| Self::from_combination(TITLE_COMBINATIONS[index])).unwrap_or(DEFAULT_PALETTES)
+    }
+
+    fn title_index(checksum: u8, letter: u8) -> Option<usize> {
+        let index = TITLE_CHECKSUMS.iter().position(|value| *value == checksum)?;
+
+        if index < FIRST_DUPLICATE_CHECKSUM {
+            return Some(index);
+        }
+
+        (index..TITLE_COMBINATIONS.len())
+            .step_by(NUM_DUPLICATE_CHECKSUMS)
+            .find(|index| DISAMBIGUATION_LETTERS[index - FIRST_DUPLICATE_CHECKSUM] == letter)
+    }
+}
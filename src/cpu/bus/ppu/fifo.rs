@@ -0,0 +1,526 @@
+use std::collections::VecDeque;
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    colorization::DmgPalette,
+    lcd_control_register::LCDControlRegister,
+    oam_entry::OAMEntry,
+    OamPriority,
+    MODE2_CYCLES,
+    MODE1_CYCLES,
+    PPU,
+    SCREEN_WIDTH
+};
+
+// the first tile fetch of every line gets thrown away, which delays the first pixel by 6 dots
+const INITIAL_FETCH_DELAY: usize = 6;
+const SPRITE_FETCH_CYCLES: usize = 6;
+const MAX_SPRITES_PER_LINE: usize = 10;
+
+#[derive(Copy, Clone, PartialEq, Serialize, Deserialize)]
+enum FetcherStep {
+    Tile,
+    DataLow,
+    DataHigh,
+    Push
+}
+
+#[derive(Copy, Clone, Serialize, Deserialize)]
+struct BgPixel {
+    color: u8,
+    palette: u8,
+    priority: bool
+}
+
+#[derive(Copy, Clone, Serialize, Deserialize)]
+struct ObjPixel {
+    color: u8,
+    palette: u8,
+    priority: OamPriority,
+    oam_index: usize
+}
+
+impl ObjPixel {
+    fn transparent() -> Self {
+        Self {
+            color: 0,
+            palette: 0,
+            priority: OamPriority::None,
+            oam_index: usize::MAX
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Fetcher {
+    step: FetcherStep,
+    cycles: usize,
+    tile_x: u8,
+    tile_index: u8,
+    attributes: u8,
+    lower: u8,
+    upper: u8
+}
+
+impl Fetcher {
+    fn new() -> Self {
+        Self {
+            step: FetcherStep::Tile,
+            cycles: 0,
+            tile_x: 0,
+            tile_index: 0,
+            attributes: 0,
+            lower: 0,
+            upper: 0
+        }
+    }
+
+    // every step besides pushing takes 2 dots
+    fn step_ready(&mut self) -> bool {
+        self.cycles += 1;
+
+        if self.cycles == 2 {
+            self.cycles = 0;
+
+            return true;
+        }
+
+        false
+    }
+}
+
+/*
+ * State for the pixel FIFO renderer. The background fetcher fills the background FIFO 8 pixels
+ * at a time while the LCD shifts out one pixel per dot. Sprites are fetched as the LCD reaches
+ * them, pausing the background fetcher and getting mixed in to the sprite FIFO. Since registers
+ * are sampled as pixels are fetched and shifted out, mid-scanline writes show up on screen and
+ * mode 3 ends up with a variable length.
+ */
+#[derive(Serialize, Deserialize)]
+pub struct PixelFifo {
+    drawing: bool,
+    bg_fifo: VecDeque<BgPixel>,
+    obj_fifo: VecDeque<ObjPixel>,
+    fetcher: Fetcher,
+    sprites: Vec<OAMEntry>,
+    sprite_fetch: Option<OAMEntry>,
+    sprite_fetch_cycles: usize,
+    lcd_x: usize,
+    discard_pixels: usize,
+    delay: usize,
+    mode3_cycles: usize,
+    window_active: bool,
+    window_drawn: bool,
+    window_y_triggered: bool,
+    window_line: u8
+}
+
+impl PixelFifo {
+    pub fn new() -> Self {
+        Self {
+            drawing: false,
+            bg_fifo: VecDeque::with_capacity(16),
+            obj_fifo: VecDeque::with_capacity(16),
+            fetcher: Fetcher::new(),
+            sprites: Vec::with_capacity(MAX_SPRITES_PER_LINE),
+            sprite_fetch: None,
+            sprite_fetch_cycles: 0,
+            lcd_x: 0,
+            discard_pixels: 0,
+            delay: 0,
+            mode3_cycles: 0,
+            window_active: false,
+            window_drawn: false,
+            window_y_triggered: false,
+            window_line: 0
+        }
+    }
+
+    pub fn reset_line(&mut self) {
+        self.drawing = false;
+    }
+}
+
+impl PPU {
+    // runs mode 3 a dot at a time, returns true once the line is finished
+    pub(super) fn fifo_hdraw(&mut self) -> bool {
+        if !self.fifo.drawing {
+            self.start_fifo_line();
+        }
+
+        while self.cycles > 0 {
+            self.cycles -= 1;
+            self.fifo.mode3_cycles += 1;
+
+            self.fifo_dot();
+
+            if self.fifo.lcd_x == SCREEN_WIDTH {
+                self.finish_fifo_line();
+
+                return true;
+            }
+        }
+
+        false
+    }
+
+    // mode 0 gets whatever's left of the line after mode 2 and mode 3
+    pub(super) fn fifo_hblank_cycles(&self) -> usize {
+        MODE1_CYCLES - MODE2_CYCLES - self.fifo.mode3_cycles
+    }
+
+    fn start_fifo_line(&mut self) {
+        let fifo = &mut self.fifo;
+
+        if self.line_y == 0 {
+            fifo.window_y_triggered = false;
+            fifo.window_line = 0;
+        }
+
+        if self.line_y == self.wy {
+            fifo.window_y_triggered = true;
+        }
+
+        fifo.drawing = true;
+        fifo.bg_fifo.clear();
+        fifo.obj_fifo.clear();
+        fifo.fetcher = Fetcher::new();
+        fifo.sprite_fetch = None;
+        fifo.sprite_fetch_cycles = 0;
+        fifo.lcd_x = 0;
+        fifo.discard_pixels = (self.scx & 0x7) as usize;
+        fifo.delay = INITIAL_FETCH_DELAY;
+        fifo.mode3_cycles = 0;
+        fifo.window_active = false;
+        fifo.window_drawn = false;
+
+        self.scan_oam();
+    }
+
+    fn finish_fifo_line(&mut self) {
+        self.fifo.drawing = false;
+
+        if self.fifo.window_drawn {
+            self.fifo.window_line = self.fifo.window_line.wrapping_add(1);
+        }
+    }
+
+    // selects the first 10 sprites in OAM that overlap the current line
+    fn scan_oam(&mut self) {
+        let sprite_height = if self.lcdc.contains(LCDControlRegister::OBJ_SIZE) { 16 } else { 8 };
+
+        self.fifo.sprites.clear();
+
+        for i in 0..self.oam.len() {
+            let y_diff = self.line_y as i16 - (self.oam[i].y_position as i16 - 16);
+
+            if y_diff >= 0 && y_diff < sprite_height {
+                let mut entry = self.oam[i];
+
+                entry.address = i;
+
+                self.fifo.sprites.push(entry);
+
+                if self.fifo.sprites.len() == MAX_SPRITES_PER_LINE {
+                    break;
+                }
+            }
+        }
+    }
+
+    fn fifo_dot(&mut self) {
+        if self.fifo.delay > 0 {
+            self.fifo.delay -= 1;
+
+            return;
+        }
+
+        if self.fifo.sprite_fetch.is_none() {
+            self.check_window_trigger();
+            self.check_sprite_trigger();
+        }
+
+        if self.fifo.sprite_fetch.is_some() {
+            self.tick_sprite_fetch();
+
+            return;
+        }
+
+        self.tick_bg_fetcher();
+
+        if let Some(bg_pixel) = self.fifo.bg_fifo.pop_front() {
+            let obj_pixel = self.fifo.obj_fifo.pop_front();
+
+            if self.fifo.discard_pixels > 0 {
+                self.fifo.discard_pixels -= 1;
+
+                return;
+            }
+
+            self.output_pixel(bg_pixel, obj_pixel);
+
+            self.fifo.lcd_x += 1;
+        }
+    }
+
+    fn window_enabled(&self) -> bool {
+        // on DMG, clearing LCDC bit 0 hides the window along with the background
+        self.lcdc.contains(LCDControlRegister::WINDOW_ENABLE) &&
+            (self.cgb_mode || self.lcdc.contains(LCDControlRegister::BG_WINDOW_ENABLE_PRIORITY))
+    }
+
+    fn check_window_trigger(&mut self) {
+        if self.fifo.window_active || !self.fifo.window_y_triggered || !self.window_enabled() || self.fifo.discard_pixels > 0 {
+            return;
+        }
+
+        let lcd_x = self.fifo.lcd_x;
+
+        if lcd_x + 7 == self.wx as usize || (self.wx < 7 && lcd_x == 0) {
+            let fifo = &mut self.fifo;
+
+            fifo.window_active = true;
+            fifo.window_drawn = true;
+            fifo.bg_fifo.clear();
+            fifo.fetcher = Fetcher::new();
+
+            // with WX below 7, the pixels left of the screen get shifted out instead
+            if self.wx < 7 {
+                fifo.discard_pixels = 7 - self.wx as usize;
+            }
+        }
+    }
+
+    fn check_sprite_trigger(&mut self) {
+        if !self.lcdc.contains(LCDControlRegister::OBJ_ENABLE) || self.fifo.discard_pixels > 0 {
+            return;
+        }
+
+        let lcd_x = self.fifo.lcd_x;
+
+        let triggered = self.fifo.sprites
+            .iter()
+            .enumerate()
+            .filter(|(_, sprite)| sprite.x_position as usize <= lcd_x + 8);
+
+        // sprites are fetched in OAM order, unless X coordinate priority is in use and the sprite with the lowest X has to go first so it wins
+        let next = if self.cgb_mode && !self.coordinate_priority {
+            triggered.min_by_key(|(index, _)| *index)
+        } else {
+            triggered.min_by_key(|(index, sprite)| (sprite.x_position, *index))
+        };
+
+        if let Some((index, _)) = next {
+            let sprite = self.fifo.sprites.remove(index);
+
+            self.fifo.sprite_fetch = Some(sprite);
+            self.fifo.sprite_fetch_cycles = 0;
+        }
+    }
+
+    /*
+     * the background fetcher has to finish fetching its current tile before the sprite fetch
+     * can start, and then the sprite fetch itself takes another 6 dots. this is where the
+     * 6-11 dot penalty for every sprite comes from.
+     */
+    fn tick_sprite_fetch(&mut self) {
+        if self.fifo.fetcher.step != FetcherStep::Push || self.fifo.bg_fifo.is_empty() {
+            self.tick_bg_fetcher();
+
+            return;
+        }
+
+        self.fifo.sprite_fetch_cycles += 1;
+
+        if self.fifo.sprite_fetch_cycles == SPRITE_FETCH_CYCLES {
+            if let Some(sprite) = self.fifo.sprite_fetch.take() {
+                self.fetch_sprite(sprite);
+            }
+        }
+    }
+
+    fn fetch_sprite(&mut self, sprite: OAMEntry) {
+        let is8by16 = self.lcdc.contains(LCDControlRegister::OBJ_SIZE);
+
+        let sprite_height = if is8by16 { 16 } else { 8 };
+
+        let mut y_in_tile = self.line_y.wrapping_sub(sprite.y_position.wrapping_sub(16)) & (sprite_height - 1);
+
+        if sprite.attributes.y_flip {
+            y_in_tile = sprite_height - 1 - y_in_tile;
+        }
+
+        let tile_index = if is8by16 { sprite.tile_index & 0xfe } else { sprite.tile_index };
+
+        let tile_address = 0x8000 + tile_index as usize * 16 + y_in_tile as usize * 2;
+
+        let bank = if self.cgb_mode { sprite.attributes.bank as usize } else { 0 };
+
+        let lower = self.vram_read8(tile_address, bank);
+        let upper = self.vram_read8(tile_address + 1, bank);
+
+        let palette = if self.cgb_mode { sprite.attributes.gbc_palette } else { sprite.attributes.dmg_palette };
+
+        // sprites hanging off the left side of the screen lose their leftmost pixels
+        let skip = 8 - (sprite.x_position as usize).min(8);
+
+        while self.fifo.obj_fifo.len() < 8 {
+            self.fifo.obj_fifo.push_back(ObjPixel::transparent());
+        }
+
+        for i in skip..8 {
+            let shift = if sprite.attributes.x_flip { i } else { 7 - i };
+
+            let color = ((upper >> shift) & 0x1) << 1 | (lower >> shift) & 0x1;
+
+            let existing = &mut self.fifo.obj_fifo[i - skip];
+
+            // with X coordinate priority the sprite fetched first always wins, otherwise it goes by OAM index
+            let replace = existing.color == 0 ||
+                (self.cgb_mode && !self.coordinate_priority && color != 0 && sprite.address < existing.oam_index);
+
+            if replace {
+                *existing = ObjPixel {
+                    color,
+                    palette,
+                    priority: sprite.attributes.priority,
+                    oam_index: sprite.address
+                };
+            }
+        }
+    }
+
+    fn tick_bg_fetcher(&mut self) {
+        match self.fifo.fetcher.step {
+            FetcherStep::Tile => if self.fifo.fetcher.step_ready() {
+                self.fetch_tile_index();
+
+                self.fifo.fetcher.step = FetcherStep::DataLow;
+            }
+            FetcherStep::DataLow => if self.fifo.fetcher.step_ready() {
+                self.fifo.fetcher.lower = self.fetch_tile_data(0);
+
+                self.fifo.fetcher.step = FetcherStep::DataHigh;
+            }
+            FetcherStep::DataHigh => if self.fifo.fetcher.step_ready() {
+                self.fifo.fetcher.upper = self.fetch_tile_data(1);
+
+                self.fifo.fetcher.step = FetcherStep::Push;
+            }
+            FetcherStep::Push => if self.fifo.bg_fifo.is_empty() {
+                self.push_bg_pixels();
+
+                self.fifo.fetcher.tile_x = self.fifo.fetcher.tile_x.wrapping_add(1);
+                self.fifo.fetcher.step = FetcherStep::Tile;
+            }
+        }
+    }
+
+    fn fetch_tile_index(&mut self) {
+        let (tilemap_address, tile_x, tile_y) = if self.fifo.window_active {
+            let tilemap_address = if self.lcdc.contains(LCDControlRegister::WINDOW_TILEMAP) { 0x9c00 } else { 0x9800 };
+
+            (tilemap_address, self.fifo.fetcher.tile_x as usize, self.fifo.window_line as usize / 8)
+        } else {
+            let tilemap_address = if self.lcdc.contains(LCDControlRegister::BG_TILEMAP) { 0x9c00 } else { 0x9800 };
+
+            let tile_x = ((self.scx as usize / 8) + self.fifo.fetcher.tile_x as usize) & 0x1f;
+            let tile_y = ((self.scy as usize + self.line_y as usize) & 0xff) / 8;
+
+            (tilemap_address, tile_x, tile_y)
+        };
+
+        let address = tilemap_address + (tile_y * 32) + (tile_x & 0x1f);
+
+        self.fifo.fetcher.tile_index = self.vram_read8(address, 0);
+        self.fifo.fetcher.attributes = if self.cgb_mode { self.vram_read8(address, 1) } else { 0 };
+    }
+
+    fn fetch_tile_data(&self, offset: usize) -> u8 {
+        let fetcher = &self.fifo.fetcher;
+
+        let y_flip = (fetcher.attributes >> 6) & 0x1 == 1;
+        let bank = ((fetcher.attributes >> 3) & 0x1) as usize;
+
+        let mut y_in_tile = if self.fifo.window_active {
+            self.fifo.window_line as usize & 0x7
+        } else {
+            (self.scy as usize + self.line_y as usize) & 0x7
+        };
+
+        if y_flip {
+            y_in_tile = 7 - y_in_tile;
+        }
+
+        let tile_address = if self.lcdc.contains(LCDControlRegister::BG_AND_WINDOW_TILES) {
+            0x8000 + fetcher.tile_index as usize * 16
+        } else {
+            (0x9000 + (fetcher.tile_index as i8 as i32) * 16) as usize
+        };
+
+        self.vram_read8(tile_address + y_in_tile * 2 + offset, bank)
+    }
+
+    fn push_bg_pixels(&mut self) {
+        let fetcher = &self.fifo.fetcher;
+
+        let x_flip = (fetcher.attributes >> 5) & 0x1 == 1;
+        let palette = fetcher.attributes & 0x7;
+        let priority = (fetcher.attributes >> 7) & 0x1 == 1;
+
+        for i in 0..8 {
+            let shift = if x_flip { i } else { 7 - i };
+
+            let color = ((fetcher.upper >> shift) & 0x1) << 1 | (fetcher.lower >> shift) & 0x1;
+
+            self.fifo.bg_fifo.push_back(BgPixel {
+                color,
+                palette,
+                priority
+            });
+        }
+    }
+
+    // palettes and the LCDC priority bits are sampled when the pixel is shifted out
+    fn output_pixel(&mut self, bg_pixel: BgPixel, obj_pixel: Option<ObjPixel>) {
+        let bg_enabled = self.lcdc.contains(LCDControlRegister::BG_WINDOW_ENABLE_PRIORITY);
+
+        let bg_color = if !self.cgb_mode && !bg_enabled { 0 } else { bg_pixel.color };
+
+        let obj_pixel = obj_pixel.filter(|obj| obj.color != 0 && self.lcdc.contains(LCDControlRegister::OBJ_ENABLE));
+
+        let obj_pixel = obj_pixel.filter(|obj| {
+            if self.cgb_mode && !bg_enabled {
+                // on CGB, LCDC bit 0 being clear means sprites always go on top
+                true
+            } else {
+                bg_color == 0 || (obj.priority == OamPriority::None && !bg_pixel.priority)
+            }
+        });
+
+        if self.cgb_mode {
+            let color = if let Some(obj) = obj_pixel {
+                self.obj_pal_read16((obj.palette as usize * 4 + obj.color as usize) * 2)
+            } else {
+                self.bg_pal_read16((bg_pixel.palette as usize * 4 + bg_color as usize) * 2)
+            };
+
+            self.picture.set_pixel(self.fifo.lcd_x, self.line_y as usize, Self::convert_pixel(color));
+        } else {
+            let (color, palette) = if let Some(obj) = obj_pixel {
+                let color = if obj.palette == 0 {
+                    self.obp0.indexes[obj.color as usize]
+                } else {
+                    self.obp1.indexes[obj.color as usize]
+                };
+
+                (color, DmgPalette::from_obj_palette(obj.palette))
+            } else {
+                (self.bgp.indexes[bg_color as usize], DmgPalette::Bg)
+            };
+
+            self.set_dmg_pixel(self.fifo.lcd_x, self.line_y as usize, color, palette);
+        }
+    }
+}
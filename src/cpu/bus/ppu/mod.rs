@@ -2,6 +2,8 @@ use std::{thread::sleep, time::{Duration, SystemTime, UNIX_EPOCH}};
 
 use bg_palette_register::{BGColor, BGPaletteRegister};
 use bg_palette_index_register::BgPaletteIndexRegister;
+use colorization::{CompatibilityPalettes, DmgPalette};
+use fifo::PixelFifo;
 use lcd_control_register::LCDControlRegister;
 use lcd_status_register::LCDStatusRegister;
 use oam_entry::OAMEntry;
@@ -20,6 +22,8 @@ pub mod oam_entry;
 pub mod picture;
 pub mod bg_palette_index_register;
 pub mod obj_palette_index_register;
+pub mod fifo;
+pub mod colorization;
 
 const MODE2_CYCLES: usize = 80;
 const MODE3_CYCLES: usize = 172;
@@ -62,6 +66,15 @@ impl OamAttributes {
             bank: (attributes >> 3) & 0x1
         }
     }
+
+    pub fn bits(&self) -> u8 {
+        (self.priority as u8) << 7 |
+            (self.y_flip as u8) << 6 |
+            (self.x_flip as u8) << 5 |
+            self.dmg_palette << 4 |
+            self.bank << 3 |
+            self.gbc_palette
+    }
 }
 
 pub const CLASSIC_GREEN: [Color; 4] = [
@@ -143,6 +156,13 @@ pub enum LCDMode {
     HDraw = 3
 }
 
+// the scanline renderer draws a whole line at the end of mode 3, the pixel FIFO renderer draws a pixel at a time
+#[derive(Copy, Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub enum Renderer {
+    Scanline,
+    PixelFifo
+}
+
 #[derive(Copy, Clone, Debug, Serialize, Deserialize)]
 struct BgAttributes {
     palette_index: usize,
@@ -176,6 +196,7 @@ pub struct PPU {
     pub oam: Box<[OAMEntry]>,
     pub frame_finished: bool,
     pub picture: Picture,
+    pub shades: Box<[u8]>,
     previous_time: u128,
     prev_background_pixels: Box<[BgAttributes]>,
     prev_window_pixels: Box<[BgAttributes]>,
@@ -184,6 +205,8 @@ pub struct PPU {
     pub lyc: u8,
     pub palette_colors: [[Color; 4]; 10],
     pub current_palette: usize,
+    pub colorization: bool,
+    pub colorization_palettes: CompatibilityPalettes,
     pub vram_bank: u8,
     pub bgpi: BgPaletteIndexRegister,
     pub palette_ram: Box<[u8]>,
@@ -191,11 +214,16 @@ pub struct PPU {
     pub obpi: ObjPaletteIndexRegister,
     pub cgb_mode: bool,
     pub in_hblank: bool,
-    pub vram_enabled: bool,
+    stat_line: bool,
+    // only set for the tick where mode 2 starts, see update_stat_line
+    #[serde(skip_serializing)]
+    #[serde(skip_deserializing)]
+    mode2_started: bool,
     pub debug_on: bool,
-    pub hdma_init: bool,
-    pub bgpd_byte: u8,
-    pub obpd_byte: u8
+    pub coordinate_priority: bool,
+    pub renderer: Renderer,
+    fifo: PixelFifo,
+    hblank_cycles: usize
 }
 
 impl PPU {
@@ -216,7 +244,8 @@ impl PPU {
             obp1: ObjPaletteRegister::new(),
             oam: vec![OAMEntry::new(); 0xa0].into_boxed_slice(),
             frame_finished: false,
-            picture: Picture::new(),
+            picture: Picture::new(SCREEN_WIDTH, SCREEN_HEIGHT),
+            shades: vec![0; SCREEN_WIDTH * SCREEN_HEIGHT].into_boxed_slice(),
             previous_time: 0,
             prev_background_pixels: vec![BgAttributes::new(); SCREEN_WIDTH].into_boxed_slice(),
             prev_window_pixels: vec![BgAttributes::new(); SCREEN_WIDTH].into_boxed_slice(),
@@ -236,6 +265,8 @@ impl PPU {
                 VOID_DREAM
             ],
             current_palette: 1,
+            colorization: false,
+            colorization_palettes: CompatibilityPalettes::default_palettes(),
             vram_bank: 0,
             bgpi: BgPaletteIndexRegister::new(),
             obpi: ObjPaletteIndexRegister::new(),
@@ -243,11 +274,13 @@ impl PPU {
             obj_palette_ram: vec![0; 64].into_boxed_slice(),
             cgb_mode: false,
             in_hblank: false,
-            vram_enabled: true,
+            stat_line: false,
+            mode2_started: false,
             debug_on: false,
-            hdma_init: false,
-            bgpd_byte: 0,
-            obpd_byte: 0
+            coordinate_priority: false,
+            renderer: Renderer::Scanline,
+            fifo: PixelFifo::new(),
+            hblank_cycles: MODE0_CYCLES
         }
     }
 
@@ -255,29 +288,100 @@ impl PPU {
         if self.lcdc.contains(LCDControlRegister::LCD_AND_PPU_ENABLE) {
             self.cycles += cycles;
 
+            let previous_mode = self.mode;
+
             match self.mode {
                 LCDMode::HBlank => self.handle_hblank(interrupt_register),
-                LCDMode::VBlank => self.handle_vblank(interrupt_register),
-                LCDMode::OAMScan => self.handle_oam_scan(interrupt_register),
+                LCDMode::VBlank => self.handle_vblank(),
+                LCDMode::OAMScan => self.handle_oam_scan(),
                 LCDMode::HDraw => self.handle_hdraw(),
             }
+
+            // the mode 2 source also fires when entering vblank
+            self.mode2_started = self.mode != previous_mode && matches!(self.mode, LCDMode::OAMScan | LCDMode::VBlank);
+
+            self.update_stat_line(interrupt_register);
+
+            self.mode2_started = false;
+        }
+    }
+
+    /*
+     * all of the STAT interrupt sources are ORed together in to a single line, and the LCD
+     * interrupt is only requested when that line goes from low to high. so if one source is
+     * already holding the line high, the others can't trigger an interrupt until it drops.
+     *
+     * the mode 2 source is the exception, it only pulses the line as mode 2 starts instead of
+     * holding it for the whole OAM scan.
+     */
+    fn update_stat_line(&mut self, interrupt_register: &mut InterruptRegister) {
+        let previous_line = self.stat_line;
+
+        self.stat_line = self.lcd_enabled() && self.any_stat_source();
+
+        if !previous_line && self.stat_line {
+            interrupt_register.set(InterruptRegister::LCD, true);
         }
     }
 
+    fn any_stat_source(&self) -> bool {
+        let lyc_source = self.stat.contains(LCDStatusRegister::LYC_INT) && self.line_y == self.lyc;
+
+        let mode_source = match self.mode {
+            LCDMode::HBlank => self.stat.contains(LCDStatusRegister::MODE0),
+            LCDMode::VBlank => self.stat.contains(LCDStatusRegister::MODE1) ||
+                (self.mode2_started && self.stat.contains(LCDStatusRegister::MODE2)),
+            LCDMode::OAMScan => self.mode2_started && self.stat.contains(LCDStatusRegister::MODE2),
+            LCDMode::HDraw => false
+        };
+
+        lyc_source || mode_source
+    }
+
+    fn lcd_enabled(&self) -> bool {
+        self.lcdc.contains(LCDControlRegister::LCD_AND_PPU_ENABLE)
+    }
+
+    // VRAM can't be accessed by the CPU while the PPU is drawing
+    pub fn vram_accessible(&self) -> bool {
+        !self.lcd_enabled() || self.mode != LCDMode::HDraw
+    }
+
+    // OAM is in use during both OAM scan and drawing
+    pub fn oam_accessible(&self) -> bool {
+        !self.lcd_enabled() || matches!(self.mode, LCDMode::HBlank | LCDMode::VBlank)
+    }
+
+    // CGB palette RAM is read while drawing, so it gets locked during mode 3 just like VRAM
+    pub fn palette_ram_accessible(&self) -> bool {
+        !self.lcd_enabled() || self.mode != LCDMode::HDraw
+    }
+
+    pub fn read_bg_palette_color(&self) -> u8 {
+        if self.palette_ram_accessible() { self.palette_ram[self.bgpi.address as usize] } else { 0xff }
+    }
+
+    pub fn read_obj_palette_color(&self) -> u8 {
+        if self.palette_ram_accessible() { self.obj_palette_ram[self.obpi.address as usize] } else { 0xff }
+    }
+
+    // writes during mode 3 are dropped, but the index still gets incremented
     pub fn update_bg_palette_color(&mut self, value: u8) {
-        self.palette_ram[self.bgpi.address as usize % self.palette_ram.len()] = value;
+        if self.palette_ram_accessible() {
+            self.palette_ram[self.bgpi.address as usize % self.palette_ram.len()] = value;
+        }
         if self.bgpi.auto_increment {
-            self.bgpi.address += 1;
+            self.bgpi.address = (self.bgpi.address + 1) & 0x3f;
         }
-        self.bgpd_byte = value;
     }
 
     pub fn update_obj_palette_color(&mut self, value: u8) {
-        self.obj_palette_ram[self.obpi.address as usize] = value;
+        if self.palette_ram_accessible() {
+            self.obj_palette_ram[self.obpi.address as usize] = value;
+        }
         if self.obpi.auto_increment {
-            self.obpi.address += 1;
+            self.obpi.address = (self.obpi.address + 1) & 0x3f;
         }
-        self.obpd_byte = value;
     }
 
     pub fn set_vram_bank(&mut self, value: u8) {
@@ -287,33 +391,15 @@ impl PPU {
     pub fn update_lyc(&mut self, value: u8, interrupt_register: &mut InterruptRegister) {
         self.lyc = value;
 
-        if self.stat.contains(LCDStatusRegister::LYC_INT) && self.line_y == self.lyc {
-            interrupt_register.set(InterruptRegister::LCD, true);
-        }
-    }
-
-    pub fn entering_hblank(&self, cycles: usize) -> bool {
-        self.mode == LCDMode::HBlank && self.cycles + cycles >= MODE0_CYCLES && self.hdma_init
+        self.update_stat_line(interrupt_register);
     }
 
     fn handle_hblank(&mut self, interrupt_register: &mut InterruptRegister) {
-        self.vram_enabled = true;
-
-        if self.cycles >= MODE0_CYCLES {
-            self.cycles -= MODE0_CYCLES;
+        if self.cycles >= self.hblank_cycles {
+            self.cycles -= self.hblank_cycles;
 
             self.line_y += 1;
 
-            self.hdma_init = false;
-
-            if self.stat.contains(LCDStatusRegister::LYC_INT) && self.line_y == self.lyc {
-                interrupt_register.set(InterruptRegister::LCD, true);
-            }
-
-            if self.stat.contains(LCDStatusRegister::MODE0) {
-                interrupt_register.set(InterruptRegister::LCD, true);
-            }
-
             self.mode = if self.line_y == 144 {
                 interrupt_register.set(InterruptRegister::VBLANK, true);
                 self.in_hblank = false;
@@ -324,12 +410,48 @@ impl PPU {
         }
     }
 
+    pub fn set_renderer(&mut self, renderer: Renderer) {
+        self.renderer = renderer;
+
+        // start over from a fresh line in case the switch happens during mode 3
+        self.fifo.reset_line();
+    }
+
     pub fn set_dmg_palette(&mut self, palette_id: usize) {
         self.current_palette = palette_id;
     }
 
-    fn get_pixel(&self, bg_color: BGColor) -> Color {
-        self.palette_colors[self.current_palette][bg_color as usize]
+    pub fn set_colorization(&mut self, enabled: bool) {
+        self.colorization = enabled;
+    }
+
+    // a CGB boot ROM leaves the palettes for DMG games in BG palette 0 and OBJ palettes 0 and 1
+    pub fn load_compatibility_palettes(&mut self) {
+        let bg_palette = |ppu: &PPU, index: usize| Self::convert_pixel(ppu.bg_pal_read16(index * 2));
+        let obj_palette = |ppu: &PPU, index: usize| Self::convert_pixel(ppu.obj_pal_read16(index * 2));
+
+        self.colorization_palettes = CompatibilityPalettes::new(
+            [0, 1, 2, 3].map(|i| bg_palette(self, i)),
+            [0, 1, 2, 3].map(|i| obj_palette(self, i)),
+            [4, 5, 6, 7].map(|i| obj_palette(self, i))
+        );
+    }
+
+    // the SGB colors the screen based on the final shade of every pixel, so keep track of those as well
+    fn set_dmg_pixel(&mut self, x: usize, y: usize, bg_color: BGColor, palette: DmgPalette) {
+        self.shades[x + y * SCREEN_WIDTH] = bg_color as u8;
+
+        let pixel = self.get_pixel(bg_color, palette);
+
+        self.picture.set_pixel(x, y, pixel);
+    }
+
+    fn get_pixel(&self, bg_color: BGColor, palette: DmgPalette) -> Color {
+        if self.colorization {
+            self.colorization_palettes.get(palette)[bg_color as usize]
+        } else {
+            self.palette_colors[self.current_palette][bg_color as usize]
+        }
     }
 
     pub fn cap_fps(&mut self) {
@@ -524,9 +646,7 @@ impl PPU {
 
                 let color = self.bgp.indexes[palette_index as usize];
 
-                let pixel = self.get_pixel(color);
-
-                self.picture.set_pixel(x + i, self.line_y as usize, pixel);
+                self.set_dmg_pixel(x + i, self.line_y as usize, color, DmgPalette::Bg);
             }
         }
 
@@ -535,7 +655,7 @@ impl PPU {
 
     pub fn read_stat(&self) -> u8 {
         let lcd_status = if !self.lcdc.contains(LCDControlRegister::LCD_AND_PPU_ENABLE) { 0 } else { self.mode as u8 };
-        self.stat.read() | lcd_status | ((self.line_y == self.lyc) as u8) << 2
+        0x80 | self.stat.read() | lcd_status | ((self.line_y == self.lyc) as u8) << 2
     }
 
     fn draw_gbc_background(&mut self) {
@@ -610,7 +730,7 @@ impl PPU {
         }
     }
 
-    fn convert_pixel(color: u16) -> Color {
+    pub fn convert_pixel(color: u16) -> Color {
         let mut r = color & 0x1f;
         let mut g = (color >> 5) & 0x1f;
         let mut b = (color >> 10) & 0x1f;
@@ -675,9 +795,7 @@ impl PPU {
 
                 let color = self.bgp.indexes[palette_index as usize];
 
-                let pixel = self.get_pixel(color);
-
-                self.picture.set_pixel(x, y as usize, pixel);
+                self.set_dmg_pixel(x, y as usize, color, DmgPalette::Bg);
 
                 self.prev_background_pixels[x as usize] = BgAttributes {
                     palette_index: palette_index as usize,
@@ -686,9 +804,7 @@ impl PPU {
             } else {
                 let color = self.bgp.indexes[0];
 
-                let pixel = self.get_pixel(color);
-
-                self.picture.set_pixel(x, y as usize, pixel);
+                self.set_dmg_pixel(x, y as usize, color, DmgPalette::Bg);
 
                 self.prev_background_pixels[x as usize] = BgAttributes::new();
             }
@@ -793,9 +909,7 @@ impl PPU {
                     (self.prev_background_pixels[x_pos as usize].palette_index == 0 && self.prev_window_pixels[x_pos as usize].palette_index == 0)
                 {
                     // draw the pixel!
-                    let pixel = self.get_pixel(color);
-
-                    self.picture.set_pixel(x_pos as usize, self.line_y as usize, pixel);
+                    self.set_dmg_pixel(x_pos as usize, self.line_y as usize, color, DmgPalette::from_obj_palette(sprite.attributes.dmg_palette));
 
                     self.previous_objs[x_pos as usize] = Some(sprite);
                 }
@@ -884,7 +998,14 @@ impl PPU {
                 let color = self.obj_pal_read16(obj_palette_address as usize);
 
                 if let Some(prev_obj) = self.previous_objs[x_pos as usize] {
-                    if sprite.address > prev_obj.address {
+                    // OPRI picks between CGB style OAM order priority and DMG style X coordinate priority
+                    let lower_priority = if self.coordinate_priority {
+                        (sprite.x_position, sprite.address) > (prev_obj.x_position, prev_obj.address)
+                    } else {
+                        sprite.address > prev_obj.address
+                    };
+
+                    if lower_priority {
                         continue;
                     }
                 }
@@ -919,72 +1040,64 @@ impl PPU {
         true
     }
 
-    fn handle_vblank(&mut self, interrupt_register: &mut InterruptRegister) {
+    fn handle_vblank(&mut self) {
         self.in_hblank = false;
-        self.vram_enabled = true;
         if self.cycles >= MODE1_CYCLES {
             self.cycles -= MODE1_CYCLES;
 
-            if self.stat.contains(LCDStatusRegister::MODE1) {
-                interrupt_register.set(InterruptRegister::LCD, true);
-            }
-
             self.line_y += 1;
 
-            if self.stat.contains(LCDStatusRegister::LYC_INT) && self.line_y == self.lyc {
-                interrupt_register.set(InterruptRegister::LCD, true);
-            }
-
             if self.line_y == 154 {
-                if self.stat.contains(LCDStatusRegister::MODE2) {
-                    interrupt_register.set(InterruptRegister::LCD, true);
-                }
                 self.frame_finished = true;
 
                 self.mode = LCDMode::OAMScan;
                 self.line_y = 0;
 
-                if self.stat.contains(LCDStatusRegister::LYC_INT) && self.line_y == self.lyc {
-                    interrupt_register.set(InterruptRegister::LCD, true);
-                }
-
                 self.current_window_line = 0;
             }
         }
     }
 
-    fn handle_oam_scan(&mut self, interrupt_register: &mut InterruptRegister) {
+    fn handle_oam_scan(&mut self) {
         self.in_hblank = false;
-        self.vram_enabled = true;
         if self.cycles >= MODE2_CYCLES {
             self.cycles -= MODE2_CYCLES;
 
-            if self.stat.contains(LCDStatusRegister::MODE2) {
-                interrupt_register.set(InterruptRegister::LCD, true);
-            }
-
             self.mode = LCDMode::HDraw
         }
     }
 
     fn handle_hdraw(&mut self) {
         self.in_hblank = false;
-        self.vram_enabled = false;
-        if self.cycles >= MODE3_CYCLES {
-            self.cycles -= MODE3_CYCLES;
 
-            self.draw_line();
+        match self.renderer {
+            Renderer::Scanline => if self.cycles >= MODE3_CYCLES {
+                self.cycles -= MODE3_CYCLES;
 
-            self.mode = LCDMode::HBlank;
+                self.draw_line();
+
+                self.hblank_cycles = MODE0_CYCLES;
+                self.mode = LCDMode::HBlank;
+            }
+            Renderer::PixelFifo => if self.fifo_hdraw() {
+                self.hblank_cycles = self.fifo_hblank_cycles();
+                self.mode = LCDMode::HBlank;
+            }
         }
     }
 
-    pub fn update_stat(&mut self, value: u8, interrupt_register: &mut InterruptRegister) {
-        self.stat = LCDStatusRegister::from_bits_truncate(value);
+    // the bug is in the DMG hardware, so a CGB running a DMG game doesn't have it
+    pub fn update_stat(&mut self, value: u8, is_cgb: bool, interrupt_register: &mut InterruptRegister) {
+        // DMG bug: for a cycle, writing STAT acts like every source is enabled
+        if !is_cgb {
+            self.stat = LCDStatusRegister::all();
 
-        if self.stat.contains(LCDStatusRegister::LYC_INT) && self.line_y == self.lyc {
-            interrupt_register.set(InterruptRegister::LCD, true);
+            self.update_stat_line(interrupt_register);
         }
+
+        self.stat = LCDStatusRegister::from_bits_truncate(value);
+
+        self.update_stat_line(interrupt_register);
     }
 
     pub fn update_lcdc(&mut self, value: u8) {
@@ -995,12 +1108,32 @@ impl PPU {
             self.line_y = 0;
             // set to mode 0 on disabling the ppu
             self.mode = LCDMode::HBlank;
+
+            self.fifo.reset_line();
+
+            self.stat_line = false;
         } else if !previous_enable && self.lcdc.contains(LCDControlRegister::LCD_AND_PPU_ENABLE) {
             // set to mode 2
             self.mode = LCDMode::OAMScan;
         }
     }
 
+    pub fn read_oam(&self, address: u16) -> u8 {
+        let oam_index = (address - 0xfe00) / 4;
+
+        let offset = (address - 0xfe00) & 0x3;
+
+        let oam = &self.oam[oam_index as usize];
+
+        match offset {
+            0 => oam.y_position,
+            1 => oam.x_position,
+            2 => oam.tile_index,
+            3 => oam.attributes.bits(),
+            _ => unreachable!()
+        }
+    }
+
     pub fn write_oam(&mut self, address: u16, value: u8) {
         let oam_index = (address - 0xfe00) / 4;
 
@@ -1,7 +1,5 @@
 use serde::{Deserialize, Serialize};
 
-use super::{SCREEN_HEIGHT, SCREEN_WIDTH};
-
 #[derive(Debug, Copy, Clone, Serialize, Deserialize)]
 pub struct Color {
     pub r: u8,
@@ -17,18 +15,22 @@ impl Color {
 
 #[derive(Serialize, Deserialize)]
 pub struct Picture {
-    pub data: Vec<u8>
+    pub data: Vec<u8>,
+    pub width: usize,
+    pub height: usize
 }
 
 impl Picture {
-    pub fn new() -> Self {
+    pub fn new(width: usize, height: usize) -> Self {
         Self {
-            data: vec![0; 4 * SCREEN_WIDTH * SCREEN_HEIGHT]
+            data: vec![0; 4 * width * height],
+            width,
+            height
         }
     }
 
     pub fn set_pixel(&mut self, x: usize, y: usize, pixel: Color) {
-        let i = (x + y * SCREEN_WIDTH) * 4;
+        let i = (x + y * self.width) * 4;
         self.data[i] = pixel.r;
         self.data[i + 1] = pixel.g;
         self.data[i + 2] = pixel.b;
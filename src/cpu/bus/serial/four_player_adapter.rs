@@ -0,0 +1,272 @@
+use std::sync::{Arc, Mutex};
+
+use super::link_cable::LinkCable;
+
+pub const MAX_PLAYERS: usize = 4;
+
+const PING_HEADER: u8 = 0xfe;
+const ACK: u8 = 0x88;
+const START_TRANSMISSION: u8 = 0xaa;
+const TRANSMISSION_HEADER: u8 = 0xcc;
+const RESTART_PING: u8 = 0xff;
+
+const PING_PACKET_SIZE: usize = 4;
+
+// the delay between bytes is ((RATE & 0xf) * 12) + 0x28 units, approximated here as 64 cpu cycles per unit
+const RATE_CYCLES: usize = 64;
+
+#[derive(Copy, Clone, PartialEq)]
+enum AdapterPhase {
+    Ping,
+    Transition,
+    Transmission
+}
+
+#[derive(Default)]
+struct AdapterPort {
+    external: Option<u8>,
+    received: Option<u8>,
+    connected: bool
+}
+
+struct AdapterState {
+    ports: [AdapterPort; MAX_PLAYERS],
+    phase: AdapterPhase,
+    cycles: usize,
+    byte_index: usize,
+    rate: u8,
+    size: u8,
+    ping_replies: [[u8; PING_PACKET_SIZE]; MAX_PLAYERS],
+    outgoing: Vec<u8>,
+    incoming: [Vec<u8>; MAX_PLAYERS]
+}
+
+impl AdapterState {
+    fn new() -> Self {
+        Self {
+            ports: Default::default(),
+            phase: AdapterPhase::Ping,
+            cycles: 0,
+            byte_index: 0,
+            rate: 0,
+            size: 1,
+            ping_replies: [[0; PING_PACKET_SIZE]; MAX_PLAYERS],
+            outgoing: Vec::new(),
+            incoming: Default::default()
+        }
+    }
+
+    fn packet_size(&self) -> usize {
+        (self.size as usize).max(1)
+    }
+
+    fn byte_cycles(&self) -> usize {
+        ((self.rate & 0xf) as usize * 12 + 0x28) * RATE_CYCLES
+    }
+
+    fn tick(&mut self, cycles: usize) {
+        self.cycles += cycles;
+
+        while self.cycles >= self.byte_cycles() {
+            self.cycles -= self.byte_cycles();
+
+            self.transfer_byte();
+        }
+    }
+
+    // the adapter drives the clock for every player at once. a game boy that isn't waiting on an external transfer misses the byte
+    fn clock(&mut self, player: usize, value: u8) -> u8 {
+        let port = &mut self.ports[player];
+
+        if let Some(external) = port.external.take() {
+            port.received = Some(value);
+
+            external
+        } else {
+            0xff
+        }
+    }
+
+    // lower 3 bits are the player id, the upper 4 bits flag which players are connected
+    fn status(&self, player: usize) -> u8 {
+        let mut status = player as u8 + 1;
+
+        for (i, port) in self.ports.iter().enumerate() {
+            if port.connected {
+                status |= 1 << (4 + i);
+            }
+        }
+
+        status
+    }
+
+    fn transfer_byte(&mut self) {
+        match self.phase {
+            AdapterPhase::Ping => self.transfer_ping_byte(),
+            AdapterPhase::Transition => {
+                for player in 0..MAX_PLAYERS {
+                    self.clock(player, TRANSMISSION_HEADER);
+                }
+
+                self.byte_index += 1;
+
+                if self.byte_index == PING_PACKET_SIZE {
+                    self.start_transmission();
+                }
+            }
+            AdapterPhase::Transmission => self.transfer_data_byte()
+        }
+    }
+
+    /*
+     * ping packets are 0xfe followed by STAT1, STAT2 and STAT3. a game boy answers with
+     * two ACK bytes followed by the RATE and SIZE it wants, though only player 1's
+     * RATE and SIZE are used. player 1 ends the ping phase by answering with 0xaa
+     * for an entire packet.
+     */
+    fn transfer_ping_byte(&mut self) {
+        let index = self.byte_index;
+
+        for player in 0..MAX_PLAYERS {
+            let value = if index == 0 { PING_HEADER } else { self.status(player) };
+
+            self.ping_replies[player][index] = self.clock(player, value);
+        }
+
+        self.byte_index += 1;
+
+        if self.byte_index < PING_PACKET_SIZE {
+            return;
+        }
+
+        self.byte_index = 0;
+
+        let player1_replies = self.ping_replies[0];
+
+        // player 1 stays connected while starting the transmission, even though it didn't ACK this packet
+        if player1_replies.iter().all(|reply| *reply == START_TRANSMISSION) {
+            self.phase = AdapterPhase::Transition;
+
+            return;
+        }
+
+        for player in 0..MAX_PLAYERS {
+            let replies = self.ping_replies[player];
+
+            self.ports[player].connected = replies[0] == ACK && replies[1] == ACK;
+        }
+
+        if self.ports[0].connected {
+            self.rate = player1_replies[2];
+            self.size = player1_replies[3];
+        }
+    }
+
+    fn start_transmission(&mut self) {
+        let packet_size = self.packet_size();
+
+        self.phase = AdapterPhase::Transmission;
+        self.byte_index = 0;
+
+        self.outgoing = vec![0; packet_size * MAX_PLAYERS];
+
+        for incoming in self.incoming.iter_mut() {
+            *incoming = vec![0; packet_size];
+        }
+    }
+
+    /*
+     * during transmission every game boy sends SIZE bytes at the start of each frame, while
+     * the adapter sends everyone the packets from the previous frame, player 1's first.
+     * if player 1 sends nothing but 0xff, the adapter goes back to pinging.
+     */
+    fn transfer_data_byte(&mut self) {
+        let packet_size = self.packet_size();
+
+        let index = self.byte_index;
+
+        for player in 0..MAX_PLAYERS {
+            let reply = self.clock(player, self.outgoing[index]);
+
+            if index < packet_size {
+                self.incoming[player][index] = reply;
+            }
+        }
+
+        self.byte_index += 1;
+
+        if self.byte_index < self.outgoing.len() {
+            return;
+        }
+
+        self.byte_index = 0;
+
+        if self.incoming[0].iter().all(|value| *value == RESTART_PING) {
+            self.phase = AdapterPhase::Ping;
+
+            return;
+        }
+
+        for player in 0..MAX_PLAYERS {
+            let start = player * packet_size;
+
+            if self.ports[player].connected {
+                self.outgoing[start..start + packet_size].copy_from_slice(&self.incoming[player]);
+            } else {
+                self.outgoing[start..start + packet_size].fill(0);
+            }
+        }
+    }
+}
+
+/*
+ * DMG-07 four player adapter. The adapter is always the clock master, so each game boy
+ * plugs in to one of its ports and waits on external transfers. Player 1's port is the
+ * one that drives the adapter's clock, so player 1 should always be connected.
+ */
+pub struct FourPlayerAdapter;
+
+impl FourPlayerAdapter {
+    pub fn create_ports() -> Vec<FourPlayerPort> {
+        let state = Arc::new(Mutex::new(AdapterState::new()));
+
+        (0..MAX_PLAYERS)
+            .map(|player| FourPlayerPort {
+                state: state.clone(),
+                player
+            })
+            .collect()
+    }
+}
+
+pub struct FourPlayerPort {
+    state: Arc<Mutex<AdapterState>>,
+    player: usize
+}
+
+impl FourPlayerPort {
+    pub fn player(&self) -> usize {
+        self.player
+    }
+}
+
+impl LinkCable for FourPlayerPort {
+    // nothing answers a game boy that tries to drive the clock itself
+    fn exchange(&mut self, _value: u8) -> u8 {
+        0xff
+    }
+
+    fn set_external(&mut self, value: Option<u8>) {
+        self.state.lock().unwrap().ports[self.player].external = value;
+    }
+
+    fn receive(&mut self) -> Option<u8> {
+        self.state.lock().unwrap().ports[self.player].received.take()
+    }
+
+    fn tick(&mut self, cycles: usize) {
+        if self.player == 0 {
+            self.state.lock().unwrap().tick(cycles);
+        }
+    }
+}
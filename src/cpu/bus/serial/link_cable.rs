@@ -0,0 +1,28 @@
+/*
+ * A link cable hands bytes between the serial port and whatever is plugged into it.
+ * The side driving the clock calls exchange(), while the side using an external clock
+ * offers its byte with set_external() and gets notified through receive() once the
+ * other side has clocked the transfer. Devices that generate their own clock get
+ * ticked along with the serial port.
+ */
+pub trait LinkCable: Send {
+    fn exchange(&mut self, value: u8) -> u8;
+    fn set_external(&mut self, value: Option<u8>);
+    fn receive(&mut self) -> Option<u8>;
+    fn tick(&mut self, _cycles: usize) {}
+}
+
+// nothing plugged in, the data line is pulled high so every byte reads back as 0xff
+pub struct NullLinkCable;
+
+impl LinkCable for NullLinkCable {
+    fn exchange(&mut self, _value: u8) -> u8 {
+        0xff
+    }
+
+    fn set_external(&mut self, _value: Option<u8>) {}
+
+    fn receive(&mut self) -> Option<u8> {
+        None
+    }
+}
@@ -0,0 +1,59 @@
+use std::sync::{Arc, Mutex};
+
+use super::link_cable::LinkCable;
+
+#[derive(Copy, Clone)]
+struct LinkPort {
+    external: Option<u8>,
+    received: Option<u8>
+}
+
+impl LinkPort {
+    fn new() -> Self {
+        Self {
+            external: None,
+            received: None
+        }
+    }
+}
+
+// connects two CPU instances running in the same process
+pub struct LocalLinkCable {
+    ports: Arc<Mutex<[LinkPort; 2]>>,
+    index: usize
+}
+
+impl LocalLinkCable {
+    pub fn new_pair() -> (Self, Self) {
+        let ports = Arc::new(Mutex::new([LinkPort::new(); 2]));
+
+        (
+            Self { ports: ports.clone(), index: 0 },
+            Self { ports, index: 1 }
+        )
+    }
+}
+
+impl LinkCable for LocalLinkCable {
+    fn exchange(&mut self, value: u8) -> u8 {
+        let mut ports = self.ports.lock().unwrap();
+
+        let other = &mut ports[1 - self.index];
+
+        if let Some(external) = other.external.take() {
+            other.received = Some(value);
+
+            external
+        } else {
+            0xff
+        }
+    }
+
+    fn set_external(&mut self, value: Option<u8>) {
+        self.ports.lock().unwrap()[self.index].external = value;
+    }
+
+    fn receive(&mut self) -> Option<u8> {
+        self.ports.lock().unwrap()[self.index].received.take()
+    }
+}
@@ -0,0 +1,128 @@
+use link_cable::{LinkCable, NullLinkCable};
+use serde::{Deserialize, Serialize};
+use serial_control_register::SerialControlRegister;
+
+use super::interrupt_register::InterruptRegister;
+
+pub mod serial_control_register;
+pub mod link_cable;
+pub mod local_link_cable;
+pub mod tcp_link_cable;
+pub mod printer;
+pub mod four_player_adapter;
+
+// the internal clock runs at 8192 Hz, or 262144 Hz with the CGB fast clock selected
+const BIT_CYCLES: usize = 512;
+const FAST_BIT_CYCLES: usize = 16;
+
+#[derive(Serialize, Deserialize)]
+pub struct Serial {
+    pub sb: u8,
+    pub sc: SerialControlRegister,
+    cycles: usize,
+    transfer_cycles: usize,
+    #[serde(skip_serializing)]
+    #[serde(skip_deserializing)]
+    #[serde(default = "Serial::null_link_cable")]
+    pub link_cable: Box<dyn LinkCable>
+}
+
+impl Serial {
+    pub fn new() -> Self {
+        Self {
+            sb: 0,
+            sc: SerialControlRegister::from_bits_retain(0),
+            cycles: 0,
+            transfer_cycles: 0,
+            link_cable: Self::null_link_cable()
+        }
+    }
+
+    fn null_link_cable() -> Box<dyn LinkCable> {
+        Box::new(NullLinkCable)
+    }
+
+    pub fn connect(&mut self, link_cable: Box<dyn LinkCable>) {
+        self.link_cable = link_cable;
+
+        if self.is_external_transfer() {
+            self.link_cable.set_external(Some(self.sb));
+        }
+    }
+
+    pub fn disconnect(&mut self) -> Box<dyn LinkCable> {
+        std::mem::replace(&mut self.link_cable, Self::null_link_cable())
+    }
+
+    fn is_external_transfer(&self) -> bool {
+        self.sc.contains(SerialControlRegister::TRANSFER_ENABLE) && !self.sc.contains(SerialControlRegister::INTERNAL_CLOCK)
+    }
+
+    pub fn write_data(&mut self, value: u8) {
+        self.sb = value;
+
+        if self.is_external_transfer() {
+            self.link_cable.set_external(Some(self.sb));
+        }
+    }
+
+    pub fn write_control(&mut self, value: u8, cgb_mode: bool) {
+        self.sc = SerialControlRegister::from_bits_truncate(value);
+
+        if !cgb_mode {
+            self.sc.remove(SerialControlRegister::FAST_CLOCK);
+        }
+
+        self.cycles = 0;
+
+        if self.sc.contains(SerialControlRegister::TRANSFER_ENABLE) {
+            if self.sc.contains(SerialControlRegister::INTERNAL_CLOCK) {
+                self.link_cable.set_external(None);
+
+                let bit_cycles = if self.sc.contains(SerialControlRegister::FAST_CLOCK) { FAST_BIT_CYCLES } else { BIT_CYCLES };
+
+                self.transfer_cycles = bit_cycles * 8;
+            } else {
+                // wait for the other side to drive the clock
+                self.link_cable.set_external(Some(self.sb));
+            }
+        } else {
+            self.link_cable.set_external(None);
+        }
+    }
+
+    pub fn read_control(&self, cgb_mode: bool) -> u8 {
+        self.sc.read(cgb_mode)
+    }
+
+    // cycles are in cpu clocks, so transfers on the internal clock speed up along with double speed mode
+    pub fn tick(&mut self, cycles: usize, interrupt_register: &mut InterruptRegister) {
+        self.link_cable.tick(cycles);
+
+        if !self.sc.contains(SerialControlRegister::TRANSFER_ENABLE) {
+            return;
+        }
+
+        if self.sc.contains(SerialControlRegister::INTERNAL_CLOCK) {
+            self.cycles += cycles;
+
+            if self.cycles >= self.transfer_cycles {
+                self.cycles = 0;
+
+                self.sb = self.link_cable.exchange(self.sb);
+
+                self.finish_transfer(interrupt_register);
+            }
+        } else if let Some(value) = self.link_cable.receive() {
+            self.sb = value;
+
+            self.finish_transfer(interrupt_register);
+        }
+    }
+
+    fn finish_transfer(&mut self, interrupt_register: &mut InterruptRegister) {
+        self.sc.remove(SerialControlRegister::TRANSFER_ENABLE);
+
+        interrupt_register.set(InterruptRegister::SERIAL, true);
+    }
+}
@@ -0,0 +1,396 @@
+use std::sync::{Arc, Mutex};
+
+use bitflags::bitflags;
+
+use crate::cpu::bus::ppu::{picture::Color, GRAYSCALE, SCREEN_WIDTH};
+
+use super::link_cable::LinkCable;
+
+const MAGIC1: u8 = 0x88;
+const MAGIC2: u8 = 0x33;
+
+const ALIVE: u8 = 0x81;
+
+const COMMAND_INIT: u8 = 0x1;
+const COMMAND_PRINT: u8 = 0x2;
+const COMMAND_DATA: u8 = 0x4;
+const COMMAND_STATUS: u8 = 0xf;
+
+const TILES_PER_ROW: usize = SCREEN_WIDTH / 8;
+const TILE_ROW_BYTES: usize = TILES_PER_ROW * 16;
+
+// the printer holds up to 9 data packets, which is enough for a full 160x144 screen
+const BUFFER_SIZE: usize = 0x280 * 9;
+
+// how many status requests the printer stays busy for after a print command
+const PRINT_BUSY_POLLS: usize = 32;
+
+// if the game goes quiet for a second after printing without feeding any paper, the page is done
+const PAGE_TIMEOUT_CYCLES: usize = 4194304;
+
+bitflags! {
+    #[derive(Copy, Clone)]
+    pub struct PrinterStatus: u8 {
+        const CHECKSUM_ERROR = 1;
+        const BUSY = 1 << 1;
+        const IMAGE_FULL = 1 << 2;
+        const UNPROCESSED = 1 << 3;
+        const PACKET_ERROR = 1 << 4;
+        const PAPER_JAM = 1 << 5;
+        const OTHER_ERROR = 1 << 6;
+        const LOW_BATTERY = 1 << 7;
+    }
+}
+
+#[derive(Copy, Clone, PartialEq)]
+enum PacketState {
+    Magic1,
+    Magic2,
+    Command,
+    Compression,
+    LengthLow,
+    LengthHigh,
+    Data,
+    ChecksumLow,
+    ChecksumHigh,
+    Alive,
+    Status
+}
+
+pub struct PrintedImage {
+    pub width: usize,
+    pub height: usize,
+    pub data: Vec<u8>
+}
+
+impl PrintedImage {
+    fn new() -> Self {
+        Self {
+            width: SCREEN_WIDTH,
+            height: 0,
+            data: Vec::new()
+        }
+    }
+
+    fn push_pixel(&mut self, pixel: Color) {
+        self.data.push(pixel.r);
+        self.data.push(pixel.g);
+        self.data.push(pixel.b);
+        self.data.push(0xff);
+    }
+
+    pub fn to_png(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+
+        {
+            let mut encoder = png::Encoder::new(&mut bytes, self.width as u32, self.height as u32);
+
+            encoder.set_color(png::ColorType::Rgba);
+            encoder.set_depth(png::BitDepth::Eight);
+
+            let mut writer = encoder.write_header().unwrap();
+
+            writer.write_image_data(&self.data).unwrap();
+        }
+
+        bytes
+    }
+}
+
+/*
+ * Game Boy Printer, plugged in to the serial port. The game always drives the clock,
+ * sending packets of the form:
+ *
+ * 0x88 0x33 <command> <compression> <length lo> <length hi> <data...> <checksum lo> <checksum hi> 0x00 0x00
+ *
+ * The printer answers every byte with 0 except for the final two, where it replies with
+ * 0x81 to say it's alive followed by its status. Finished pages end up in `finished_prints`
+ * for the frontend to pick up.
+ */
+pub struct Printer {
+    state: PacketState,
+    command: u8,
+    compressed: bool,
+    length: usize,
+    packet_data: Vec<u8>,
+    checksum: u16,
+    received_checksum: u16,
+    status: PrinterStatus,
+    busy_polls: usize,
+    image_data: Vec<u8>,
+    current_page: Option<PrintedImage>,
+    idle_cycles: usize,
+    finished_prints: Arc<Mutex<Vec<PrintedImage>>>
+}
+
+impl Printer {
+    pub fn new() -> Self {
+        Self {
+            state: PacketState::Magic1,
+            command: 0,
+            compressed: false,
+            length: 0,
+            packet_data: Vec::new(),
+            checksum: 0,
+            received_checksum: 0,
+            status: PrinterStatus::empty(),
+            busy_polls: 0,
+            image_data: Vec::new(),
+            current_page: None,
+            idle_cycles: 0,
+            finished_prints: Arc::new(Mutex::new(Vec::new()))
+        }
+    }
+
+    pub fn finished_prints(&self) -> Arc<Mutex<Vec<PrintedImage>>> {
+        self.finished_prints.clone()
+    }
+
+    fn process_packet(&mut self) {
+        if self.checksum != self.received_checksum {
+            self.status.insert(PrinterStatus::CHECKSUM_ERROR);
+
+            return;
+        }
+
+        self.status.remove(PrinterStatus::CHECKSUM_ERROR);
+
+        match self.command {
+            COMMAND_INIT => {
+                self.image_data.clear();
+                self.busy_polls = 0;
+                self.status = PrinterStatus::empty();
+            }
+            COMMAND_DATA => {
+                let data = if self.compressed {
+                    Self::decompress(&self.packet_data)
+                } else {
+                    self.packet_data.clone()
+                };
+
+                let remaining = BUFFER_SIZE - self.image_data.len();
+
+                self.image_data.extend_from_slice(&data[..data.len().min(remaining)]);
+
+                if !self.image_data.is_empty() {
+                    self.status.insert(PrinterStatus::UNPROCESSED);
+                }
+                if self.image_data.len() == BUFFER_SIZE {
+                    self.status.insert(PrinterStatus::IMAGE_FULL);
+                }
+            }
+            COMMAND_PRINT => if self.packet_data.len() >= 4 {
+                self.print(
+                    self.packet_data[0],
+                    self.packet_data[1],
+                    self.packet_data[2]
+                );
+            }
+            COMMAND_STATUS => (),
+            _ => self.status.insert(PrinterStatus::PACKET_ERROR)
+        }
+    }
+
+    // run length encoding: bit 7 set means repeat the next byte (n & 0x7f) + 2 times, otherwise copy n + 1 bytes
+    fn decompress(data: &[u8]) -> Vec<u8> {
+        let mut decompressed = Vec::new();
+
+        let mut i = 0;
+
+        while i < data.len() {
+            let control = data[i];
+
+            i += 1;
+
+            if control & 0x80 != 0 {
+                let length = (control & 0x7f) as usize + 2;
+
+                if let Some(value) = data.get(i) {
+                    decompressed.extend(std::iter::repeat_n(*value, length));
+                }
+
+                i += 1;
+            } else {
+                let length = control as usize + 1;
+                let end = (i + length).min(data.len());
+
+                decompressed.extend_from_slice(&data[i..end]);
+
+                i = end;
+            }
+        }
+
+        decompressed
+    }
+
+    fn print(&mut self, sheets: u8, margins: u8, palette: u8) {
+        let margin_before = margins >> 4;
+        let margin_after = margins & 0xf;
+
+        // a margin before the image means the paper was fed, so whatever was printed so far is its own page
+        if margin_before != 0 {
+            self.finish_page();
+        }
+
+        // palette 0 is treated the same as the default 0xe4 mapping
+        let palette = if palette == 0 { 0xe4 } else { palette };
+
+        for _ in 0..sheets {
+            self.print_image_data(palette);
+        }
+
+        self.image_data.clear();
+
+        self.status.remove(PrinterStatus::UNPROCESSED);
+        self.status.insert(PrinterStatus::BUSY | PrinterStatus::IMAGE_FULL);
+
+        self.busy_polls = PRINT_BUSY_POLLS;
+
+        if margin_after != 0 {
+            self.finish_page();
+        }
+    }
+
+    fn print_image_data(&mut self, palette: u8) {
+        let page = self.current_page.get_or_insert_with(PrintedImage::new);
+
+        for tile_row in self.image_data.chunks_exact(TILE_ROW_BYTES) {
+            for y in 0..8 {
+                for x in 0..SCREEN_WIDTH {
+                    let tile_address = (x / 8) * 16 + y * 2;
+
+                    let lower = tile_row[tile_address];
+                    let upper = tile_row[tile_address + 1];
+
+                    let shift = 7 - (x % 8);
+
+                    let palette_index = ((upper >> shift) & 0x1) << 1 | (lower >> shift) & 0x1;
+
+                    let shade = (palette >> (palette_index * 2)) & 0x3;
+
+                    page.push_pixel(GRAYSCALE[shade as usize]);
+                }
+            }
+
+            page.height += 8;
+        }
+    }
+
+    fn finish_page(&mut self) {
+        if let Some(page) = self.current_page.take() {
+            if page.height > 0 {
+                self.finished_prints.lock().unwrap().push(page);
+            }
+        }
+    }
+
+    fn read_status(&mut self) -> u8 {
+        let status = self.status.bits();
+
+        if self.command == COMMAND_STATUS && self.busy_polls > 0 {
+            self.busy_polls -= 1;
+
+            if self.busy_polls == 0 {
+                self.status.remove(PrinterStatus::BUSY);
+            }
+        }
+
+        status
+    }
+}
+
+impl LinkCable for Printer {
+    fn exchange(&mut self, value: u8) -> u8 {
+        let mut reply = 0;
+
+        self.idle_cycles = 0;
+
+        self.state = match self.state {
+            PacketState::Magic1 => if value == MAGIC1 { PacketState::Magic2 } else { PacketState::Magic1 },
+            PacketState::Magic2 => if value == MAGIC2 { PacketState::Command } else { PacketState::Magic1 },
+            PacketState::Command => {
+                self.command = value;
+                self.checksum = value as u16;
+
+                PacketState::Compression
+            }
+            PacketState::Compression => {
+                self.compressed = value & 0x1 == 1;
+                self.checksum = self.checksum.wrapping_add(value as u16);
+
+                PacketState::LengthLow
+            }
+            PacketState::LengthLow => {
+                self.length = value as usize;
+                self.checksum = self.checksum.wrapping_add(value as u16);
+
+                PacketState::LengthHigh
+            }
+            PacketState::LengthHigh => {
+                self.length |= (value as usize) << 8;
+                self.checksum = self.checksum.wrapping_add(value as u16);
+
+                self.packet_data.clear();
+
+                if self.length > 0 { PacketState::Data } else { PacketState::ChecksumLow }
+            }
+            PacketState::Data => {
+                self.packet_data.push(value);
+                self.checksum = self.checksum.wrapping_add(value as u16);
+
+                if self.packet_data.len() == self.length { PacketState::ChecksumLow } else { PacketState::Data }
+            }
+            PacketState::ChecksumLow => {
+                self.received_checksum = value as u16;
+
+                PacketState::ChecksumHigh
+            }
+            PacketState::ChecksumHigh => {
+                self.received_checksum |= (value as u16) << 8;
+
+                PacketState::Alive
+            }
+            PacketState::Alive => {
+                reply = ALIVE;
+
+                PacketState::Status
+            }
+            PacketState::Status => {
+                // the status reply reflects the printer before it gets around to handling the packet
+                reply = self.read_status();
+
+                self.process_packet();
+
+                PacketState::Magic1
+            }
+        };
+
+        reply
+    }
+
+    // the game boy always drives the clock when talking to the printer
+    fn set_external(&mut self, _value: Option<u8>) {}
+
+    fn receive(&mut self) -> Option<u8> {
+        None
+    }
+
+    fn tick(&mut self, cycles: usize) {
+        if self.current_page.is_none() {
+            return;
+        }
+
+        self.idle_cycles += cycles;
+
+        if self.idle_cycles >= PAGE_TIMEOUT_CYCLES {
+            self.finish_page();
+        }
+    }
+}
+
+// unplugging the printer tears off whatever page it was still working on
+impl Drop for Printer {
+    fn drop(&mut self) {
+        self.finish_page();
+    }
+}
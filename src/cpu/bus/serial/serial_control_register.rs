@@ -0,0 +1,21 @@
+use bitflags::bitflags;
+use serde::{Deserialize, Serialize};
+
+bitflags! {
+    #[derive(Copy, Clone, Serialize, Deserialize)]
+    pub struct SerialControlRegister: u8 {
+        const INTERNAL_CLOCK = 1;
+        const FAST_CLOCK = 1 << 1;
+        const TRANSFER_ENABLE = 1 << 7;
+    }
+}
+
+impl SerialControlRegister {
+    pub fn read(&self, cgb_mode: bool) -> u8 {
+        if cgb_mode {
+            self.bits() | 0x7c
+        } else {
+            self.bits() | 0x7e
+        }
+    }
+}
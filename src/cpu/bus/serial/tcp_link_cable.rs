@@ -0,0 +1,210 @@
+use std::{
+    io::{self, ErrorKind, Read, Write},
+    net::{Shutdown, TcpListener, TcpStream},
+    sync::{
+        mpsc::{self, Receiver, Sender},
+        Arc,
+        Mutex
+    },
+    thread::{self, JoinHandle},
+    time::Duration
+};
+
+use super::link_cable::LinkCable;
+
+const TRANSFER: u8 = 0x1;
+const REPLY: u8 = 0x2;
+
+// how long the clock master waits for the other instance before giving up on a byte
+const REPLY_TIMEOUT: Duration = Duration::from_millis(500);
+
+// how often the host checks whether it should stop waiting for the other instance
+const ACCEPT_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+struct TcpLinkState {
+    external: Option<u8>,
+    received: Option<u8>,
+    writer: Option<TcpStream>,
+    // set once the cable gets unplugged, so the background thread knows to wind down
+    stopped: bool
+}
+
+impl TcpLinkState {
+    fn send(&mut self, message: [u8; 2]) {
+        let mut disconnected = false;
+
+        if let Some(writer) = &mut self.writer {
+            disconnected = writer.write_all(&message).is_err();
+        }
+
+        if disconnected {
+            self.writer = None;
+        }
+    }
+}
+
+/*
+ * Link cable between two emulator instances over TCP, mainly meant for
+ * two desktop instances on the same machine. Every message is two bytes long:
+ * a transfer from the clock master followed by a reply with the byte shifted back.
+ * Incoming messages are handled on a background thread so the other side gets
+ * its reply right away instead of waiting for this instance's next frame.
+ */
+pub struct TcpLinkCable {
+    state: Arc<Mutex<TcpLinkState>>,
+    replies: Receiver<u8>,
+    thread: Option<JoinHandle<()>>
+}
+
+impl TcpLinkCable {
+    pub fn host(port: u16) -> io::Result<Self> {
+        let listener = TcpListener::bind(("127.0.0.1", port))?;
+
+        // accepting without blocking lets the thread notice the cable getting unplugged before anyone connects
+        listener.set_nonblocking(true)?;
+
+        let (state, sender, replies) = Self::create_state();
+
+        let thread_state = state.clone();
+
+        let thread = thread::spawn(move || {
+            if let Some(stream) = Self::accept(listener, &thread_state) {
+                Self::start_connection(stream, thread_state, sender);
+            }
+        });
+
+        Ok(Self { state, replies, thread: Some(thread) })
+    }
+
+    pub fn connect(address: &str) -> io::Result<Self> {
+        let stream = TcpStream::connect(address)?;
+
+        let (state, sender, replies) = Self::create_state();
+
+        let thread_state = state.clone();
+
+        let thread = thread::spawn(move || Self::start_connection(stream, thread_state, sender));
+
+        Ok(Self { state, replies, thread: Some(thread) })
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.state.lock().unwrap().writer.is_some()
+    }
+
+    fn create_state() -> (Arc<Mutex<TcpLinkState>>, Sender<u8>, Receiver<u8>) {
+        let (sender, replies) = mpsc::channel();
+
+        let state = Arc::new(Mutex::new(TcpLinkState {
+            external: None,
+            received: None,
+            writer: None,
+            stopped: false
+        }));
+
+        (state, sender, replies)
+    }
+
+    // the listener gets dropped as soon as this returns, so the port is free again once a peer connects
+    fn accept(listener: TcpListener, state: &Arc<Mutex<TcpLinkState>>) -> Option<TcpStream> {
+        while !state.lock().unwrap().stopped {
+            match listener.accept() {
+                Ok((stream, _)) => return stream.set_nonblocking(false).ok().map(|_| stream),
+                Err(err) if err.kind() == ErrorKind::WouldBlock => thread::sleep(ACCEPT_POLL_INTERVAL),
+                Err(_) => return None
+            }
+        }
+
+        None
+    }
+
+    fn start_connection(mut stream: TcpStream, state: Arc<Mutex<TcpLinkState>>, sender: Sender<u8>) {
+        stream.set_nodelay(true).unwrap_or(());
+
+        {
+            let mut state = state.lock().unwrap();
+
+            // the cable may have been unplugged while the connection was being made
+            if state.stopped {
+                return;
+            }
+
+            match stream.try_clone() {
+                Ok(writer) => state.writer = Some(writer),
+                Err(_) => return
+            }
+        }
+
+        let mut message = [0; 2];
+
+        while stream.read_exact(&mut message).is_ok() {
+            match message[0] {
+                TRANSFER => {
+                    let mut state = state.lock().unwrap();
+
+                    let reply = if let Some(external) = state.external.take() {
+                        state.received = Some(message[1]);
+
+                        external
+                    } else {
+                        0xff
+                    };
+
+                    state.send([REPLY, reply]);
+                }
+                REPLY => if sender.send(message[1]).is_err() {
+                    break;
+                }
+                _ => println!("[WARN] (TcpLinkCable): unknown message received: 0x{:x}", message[0])
+            }
+        }
+
+        state.lock().unwrap().writer = None;
+    }
+}
+
+impl LinkCable for TcpLinkCable {
+    fn exchange(&mut self, value: u8) -> u8 {
+        {
+            let mut state = self.state.lock().unwrap();
+
+            if state.writer.is_none() {
+                return 0xff;
+            }
+
+            // drop any reply that showed up after a previous transfer already timed out
+            while self.replies.try_recv().is_ok() {}
+
+            state.send([TRANSFER, value]);
+        }
+
+        self.replies.recv_timeout(REPLY_TIMEOUT).unwrap_or(0xff)
+    }
+
+    fn set_external(&mut self, value: Option<u8>) {
+        self.state.lock().unwrap().external = value;
+    }
+
+    fn receive(&mut self) -> Option<u8> {
+        self.state.lock().unwrap().received.take()
+    }
+}
+
+// shutting the socket down wakes the background thread up from waiting on the next message
+impl Drop for TcpLinkCable {
+    fn drop(&mut self) {
+        {
+            let mut state = self.state.lock().unwrap();
+
+            state.stopped = true;
+
+            if let Some(writer) = &state.writer {
+                writer.shutdown(Shutdown::Both).unwrap_or(());
+            }
+        }
+
+        if let Some(thread) = self.thread.take() {
+            thread.join().unwrap_or(());
+        }
+    }
+}
@@ -0,0 +1,165 @@
+use serde::{Deserialize, Serialize};
+
+pub const MAP_WIDTH: usize = 20;
+pub const MAP_HEIGHT: usize = 18;
+
+// an attribute file packs the palettes for every tile on the screen in to 2 bits each
+pub const ATTRIBUTE_FILE_SIZE: usize = MAP_WIDTH * MAP_HEIGHT / 4;
+
+// the SGB picks one of its 4 palettes for every 8x8 tile on the screen
+#[derive(Serialize, Deserialize)]
+pub struct AttributeMap {
+    palettes: Box<[u8]>
+}
+
+impl AttributeMap {
+    pub fn new() -> Self {
+        Self {
+            palettes: vec![0; MAP_WIDTH * MAP_HEIGHT].into_boxed_slice()
+        }
+    }
+
+    pub fn palette_at(&self, x: usize, y: usize) -> usize {
+        self.palettes[x + y * MAP_WIDTH] as usize
+    }
+
+    fn set(&mut self, x: usize, y: usize, palette: u8) {
+        if x < MAP_WIDTH && y < MAP_HEIGHT {
+            self.palettes[x + y * MAP_WIDTH] = palette & 0x3;
+        }
+    }
+
+    /*
+     * ATTR_BLK. every data set is a rectangle with separate palettes for the tiles inside it,
+     * on its edges and outside of it. if only the inside or only the outside gets changed, the
+     * edges take on that palette too.
+     */
+    pub fn apply_blocks(&mut self, data: &[u8]) {
+        let count = data[1] as usize;
+
+        for block in data[2..].chunks_exact(6).take(count) {
+            let control = block[0] & 0x7;
+
+            let inside_palette = block[1] & 0x3;
+            let outside_palette = (block[1] >> 4) & 0x3;
+
+            let border_palette = match control {
+                0x1 => inside_palette,
+                0x4 => outside_palette,
+                _ => (block[1] >> 2) & 0x3
+            };
+
+            let change_inside = control & 0x1 != 0;
+            let change_border = control & 0x2 != 0 || control == 0x1 || control == 0x4;
+            let change_outside = control & 0x4 != 0;
+
+            let (x1, y1, x2, y2) = (block[2] as usize, block[3] as usize, block[4] as usize, block[5] as usize);
+
+            for y in 0..MAP_HEIGHT {
+                for x in 0..MAP_WIDTH {
+                    let within = (x1..=x2).contains(&x) && (y1..=y2).contains(&y);
+                    let on_border = within && (x == x1 || x == x2 || y == y1 || y == y2);
+
+                    if on_border {
+                        if change_border {
+                            self.set(x, y, border_palette);
+                        }
+                    } else if within {
+                        if change_inside {
+                            self.set(x, y, inside_palette);
+                        }
+                    } else if change_outside {
+                        self.set(x, y, outside_palette);
+                    }
+                }
+            }
+        }
+    }
+
+    // ATTR_LIN. every byte sets a whole row or column to one palette
+    pub fn apply_lines(&mut self, data: &[u8]) {
+        let count = data[1] as usize;
+
+        for line in data[2..].iter().take(count) {
+            let index = (line & 0x1f) as usize;
+            let palette = (line >> 5) & 0x3;
+
+            if (line >> 7) & 0x1 == 1 {
+                for x in 0..MAP_WIDTH {
+                    self.set(x, index, palette);
+                }
+            } else {
+                for y in 0..MAP_HEIGHT {
+                    self.set(index, y, palette);
+                }
+            }
+        }
+    }
+
+    // ATTR_DIV. splits the screen in two along a row or column, with a third palette for the line itself
+    pub fn apply_division(&mut self, data: &[u8]) {
+        let after_palette = data[1] & 0x3;
+        let before_palette = (data[1] >> 2) & 0x3;
+        let line_palette = (data[1] >> 4) & 0x3;
+
+        let horizontal = (data[1] >> 6) & 0x1 == 1;
+
+        let line = data[2] as usize;
+
+        for y in 0..MAP_HEIGHT {
+            for x in 0..MAP_WIDTH {
+                let position = if horizontal { y } else { x };
+
+                let palette = match position.cmp(&line) {
+                    std::cmp::Ordering::Less => before_palette,
+                    std::cmp::Ordering::Equal => line_palette,
+                    std::cmp::Ordering::Greater => after_palette
+                };
+
+                self.set(x, y, palette);
+            }
+        }
+    }
+
+    // ATTR_CHR. sets palettes tile by tile starting from a position, going either right or down
+    pub fn apply_tiles(&mut self, data: &[u8]) {
+        let mut x = data[1] as usize;
+        let mut y = data[2] as usize;
+
+        let count = (u16::from_le_bytes([data[3], data[4]]) as usize).min(MAP_WIDTH * MAP_HEIGHT);
+
+        let vertical = data[5] & 0x1 == 1;
+
+        for i in 0..count {
+            let Some(byte) = data.get(6 + i / 4) else {
+                break;
+            };
+
+            let palette = (byte >> (6 - (i % 4) * 2)) & 0x3;
+
+            self.set(x, y, palette);
+
+            if vertical {
+                y += 1;
+
+                if y == MAP_HEIGHT {
+                    y = 0;
+                    x += 1;
+                }
+            } else {
+                x += 1;
+
+                if x == MAP_WIDTH {
+                    x = 0;
+                    y += 1;
+                }
+            }
+        }
+    }
+
+    pub fn load_file(&mut self, file: &[u8]) {
+        for (i, palette) in self.palettes.iter_mut().enumerate() {
+            *palette = (file[i / 4] >> (6 - (i % 4) * 2)) & 0x3;
+        }
+    }
+}
@@ -0,0 +1,89 @@
+use serde::{Deserialize, Serialize};
+
+pub const MAP_WIDTH: usize = 32;
+pub const MAP_HEIGHT: usize = 28;
+
+const NUM_TILES: usize = 256;
+
+// SNES tiles are 4 bits per pixel, so 32 bytes each
+const TILE_SIZE: usize = 32;
+
+// PCT_TRN has the tile map first, followed by the palettes at 0x800
+const PALETTES_OFFSET: usize = 0x800;
+
+/*
+ * the border is a SNES background layer drawn over the game screen. tiles come in through
+ * CHR_TRN, while the map and the 4 border palettes come in through PCT_TRN. color 0 of
+ * every palette is transparent.
+ */
+#[derive(Serialize, Deserialize)]
+pub struct Border {
+    tiles: Box<[u8]>,
+    map: Box<[u16]>,
+    palettes: [[u16; 16]; 4]
+}
+
+impl Border {
+    pub fn new() -> Self {
+        Self {
+            tiles: vec![0; NUM_TILES * TILE_SIZE].into_boxed_slice(),
+            map: vec![0; MAP_WIDTH * MAP_HEIGHT].into_boxed_slice(),
+            palettes: [[0; 16]; 4]
+        }
+    }
+
+    // CHR_TRN sends 128 tiles at a time
+    pub fn load_tiles(&mut self, first_tile: usize, data: &[u8]) {
+        let start = first_tile * TILE_SIZE;
+        let length = (NUM_TILES / 2) * TILE_SIZE;
+
+        self.tiles[start..start + length].copy_from_slice(&data[..length]);
+    }
+
+    pub fn load_map(&mut self, data: &[u8]) {
+        for (i, entry) in self.map.iter_mut().enumerate() {
+            *entry = u16::from_le_bytes([data[i * 2], data[i * 2 + 1]]);
+        }
+
+        for (i, palette) in self.palettes.iter_mut().enumerate() {
+            for (j, color) in palette.iter_mut().enumerate() {
+                let address = PALETTES_OFFSET + (i * 16 + j) * 2;
+
+                *color = u16::from_le_bytes([data[address], data[address + 1]]);
+            }
+        }
+    }
+
+    // returns None for transparent pixels
+    pub fn pixel(&self, x: usize, y: usize) -> Option<u16> {
+        let entry = self.map[(y / 8) * MAP_WIDTH + x / 8];
+
+        let tile = (entry & 0xff) as usize;
+
+        // the map uses palettes 4-7
+        let palette = ((entry >> 10) & 0x3) as usize;
+
+        let x_flip = (entry >> 14) & 0x1 == 1;
+        let y_flip = (entry >> 15) & 0x1 == 1;
+
+        let shift = if x_flip { x % 8 } else { 7 - x % 8 };
+        let row = if y_flip { 7 - y % 8 } else { y % 8 };
+
+        // planes 0 and 1 come first for every row, then planes 2 and 3
+        let address = tile * TILE_SIZE + row * 2;
+
+        let mut color_index = 0;
+
+        for plane in 0..4 {
+            let byte = self.tiles[address + (plane / 2) * 16 + plane % 2];
+
+            color_index |= ((byte >> shift) & 0x1) << plane;
+        }
+
+        if color_index == 0 {
+            None
+        } else {
+            Some(self.palettes[palette][color_index as usize])
+        }
+    }
+}
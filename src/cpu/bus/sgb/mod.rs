@@ -0,0 +1,303 @@
+use attribute_map::{AttributeMap, ATTRIBUTE_FILE_SIZE};
+use border::Border;
+use packet_reader::PacketReader;
+use serde::{Deserialize, Serialize};
+
+use super::joypad::Joypad;
+use super::ppu::{picture::Picture, PPU, SCREEN_HEIGHT, SCREEN_WIDTH};
+
+pub mod packet_reader;
+pub mod attribute_map;
+pub mod border;
+
+pub const SGB_SCREEN_WIDTH: usize = 256;
+pub const SGB_SCREEN_HEIGHT: usize = 224;
+
+// where the game screen sits inside the border
+const GAME_X: usize = 48;
+const GAME_Y: usize = 40;
+
+// VRAM transfers always send 4KB, which is 256 tiles worth of the screen
+const TRANSFER_SIZE: usize = 0x1000;
+const TRANSFER_TILES: usize = TRANSFER_SIZE / 16;
+
+const NUM_SYSTEM_PALETTES: usize = 512;
+const NUM_ATTRIBUTE_FILES: usize = 45;
+
+// white, light gray, dark gray and black in RGB555
+const DEFAULT_PALETTE: [u16; 4] = [0x7fff, 0x56b5, 0x294a, 0x0000];
+
+const PAL01: u8 = 0x00;
+const PAL23: u8 = 0x01;
+const PAL03: u8 = 0x02;
+const PAL12: u8 = 0x03;
+const ATTR_BLK: u8 = 0x04;
+const ATTR_LIN: u8 = 0x05;
+const ATTR_DIV: u8 = 0x06;
+const ATTR_CHR: u8 = 0x07;
+const PAL_SET: u8 = 0x0a;
+const PAL_TRN: u8 = 0x0b;
+const MLT_REQ: u8 = 0x11;
+const CHR_TRN: u8 = 0x13;
+const PCT_TRN: u8 = 0x14;
+const ATTR_TRN: u8 = 0x15;
+const ATTR_SET: u8 = 0x16;
+const MASK_EN: u8 = 0x17;
+
+#[derive(Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub enum MaskMode {
+    None,
+    Freeze,
+    Black,
+    Color0
+}
+
+#[derive(Copy, Clone, PartialEq, Serialize, Deserialize)]
+enum VramTransfer {
+    SystemPalettes,
+    AttributeFiles,
+    BorderTiles(usize),
+    BorderMap
+}
+
+/*
+ * the Super Game Boy. games talk to it through command packets sent over P1, and bigger
+ * chunks of data like palettes and the border get sent by putting them on the screen for
+ * a frame. the SGB colors the game screen using 4 palettes, picking one for every tile,
+ * and draws it in the middle of a 256x224 border.
+ */
+#[derive(Serialize, Deserialize)]
+pub struct Sgb {
+    packet_reader: PacketReader,
+    p15_high: bool,
+    pub commands_enabled: bool,
+    palettes: [[u16; 4]; 4],
+    system_palettes: Box<[u16]>,
+    attribute_map: AttributeMap,
+    attribute_files: Box<[u8]>,
+    border: Border,
+    pub mask: MaskMode,
+    pending_transfer: Option<VramTransfer>,
+    screen: Box<[u8]>,
+    pub picture: Picture
+}
+
+impl Sgb {
+    pub fn new() -> Self {
+        Self {
+            packet_reader: PacketReader::new(),
+            p15_high: true,
+            commands_enabled: false,
+            palettes: [DEFAULT_PALETTE; 4],
+            system_palettes: vec![0; NUM_SYSTEM_PALETTES * 4].into_boxed_slice(),
+            attribute_map: AttributeMap::new(),
+            attribute_files: vec![0; NUM_ATTRIBUTE_FILES * ATTRIBUTE_FILE_SIZE].into_boxed_slice(),
+            border: Border::new(),
+            mask: MaskMode::None,
+            pending_transfer: None,
+            screen: vec![0; SCREEN_WIDTH * SCREEN_HEIGHT].into_boxed_slice(),
+            picture: Picture::new(SGB_SCREEN_WIDTH, SGB_SCREEN_HEIGHT)
+        }
+    }
+
+    pub fn write_joypad(&mut self, value: u8, joypad: &mut Joypad) {
+        let p15_high = (value >> 5) & 0x1 == 1;
+
+        // with more than one player, every time P15 goes back high the SGB moves on to the next controller
+        if p15_high && !self.p15_high && !self.packet_reader.is_receiving() {
+            joypad.next_player();
+        }
+
+        self.p15_high = p15_high;
+
+        if let Some(command) = self.packet_reader.write(value) {
+            // the SGB ignores games that don't say they support it in their header
+            if self.commands_enabled {
+                self.handle_command(&command, joypad);
+            }
+        }
+    }
+
+    fn handle_command(&mut self, data: &[u8], joypad: &mut Joypad) {
+        match data[0] >> 3 {
+            PAL01 => self.set_palette_pair(data, 0, 1),
+            PAL23 => self.set_palette_pair(data, 2, 3),
+            PAL03 => self.set_palette_pair(data, 0, 3),
+            PAL12 => self.set_palette_pair(data, 1, 2),
+            ATTR_BLK => self.attribute_map.apply_blocks(data),
+            ATTR_LIN => self.attribute_map.apply_lines(data),
+            ATTR_DIV => self.attribute_map.apply_division(data),
+            ATTR_CHR => self.attribute_map.apply_tiles(data),
+            PAL_SET => self.set_system_palettes(data),
+            PAL_TRN => self.pending_transfer = Some(VramTransfer::SystemPalettes),
+            MLT_REQ => joypad.set_num_players(match data[1] & 0x3 {
+                1 => 2,
+                3 => 4,
+                _ => 1
+            }),
+            // only BG tiles are supported, OBJ tiles are meant for SNES programs
+            CHR_TRN if (data[1] >> 1) & 0x1 == 0 => {
+                self.pending_transfer = Some(VramTransfer::BorderTiles((data[1] & 0x1) as usize * 0x80));
+            }
+            PCT_TRN => self.pending_transfer = Some(VramTransfer::BorderMap),
+            ATTR_TRN => self.pending_transfer = Some(VramTransfer::AttributeFiles),
+            ATTR_SET => {
+                self.load_attribute_file((data[1] & 0x3f) as usize);
+
+                if (data[1] >> 6) & 0x1 == 1 {
+                    self.mask = MaskMode::None;
+                }
+            }
+            MASK_EN => self.mask = match data[1] & 0x3 {
+                0 => MaskMode::None,
+                1 => MaskMode::Freeze,
+                2 => MaskMode::Black,
+                3 => MaskMode::Color0,
+                _ => unreachable!()
+            },
+            _ => ()
+        }
+    }
+
+    // color 0 is shared by every palette, so setting it for one sets it for all of them
+    fn set_color0(&mut self, color: u16) {
+        for palette in self.palettes.iter_mut() {
+            palette[0] = color;
+        }
+    }
+
+    // PAL01, PAL23, PAL03 and PAL12
+    fn set_palette_pair(&mut self, data: &[u8], first: usize, second: usize) {
+        let color = |index: usize| u16::from_le_bytes([data[1 + index * 2], data[2 + index * 2]]);
+
+        self.set_color0(color(0));
+
+        for i in 1..4 {
+            self.palettes[first][i] = color(i);
+            self.palettes[second][i] = color(i + 3);
+        }
+    }
+
+    // PAL_SET picks 4 of the palettes sent with PAL_TRN, and can apply an attribute file at the same time
+    fn set_system_palettes(&mut self, data: &[u8]) {
+        for i in 0..4 {
+            let id = (u16::from_le_bytes([data[1 + i * 2], data[2 + i * 2]]) & 0x1ff) as usize;
+
+            self.palettes[i].copy_from_slice(&self.system_palettes[id * 4..id * 4 + 4]);
+        }
+
+        self.set_color0(self.palettes[0][0]);
+
+        let flags = data[9];
+
+        if (flags >> 7) & 0x1 == 1 {
+            self.load_attribute_file((flags & 0x3f) as usize);
+        }
+
+        if (flags >> 6) & 0x1 == 1 {
+            self.mask = MaskMode::None;
+        }
+    }
+
+    fn load_attribute_file(&mut self, file: usize) {
+        if file < NUM_ATTRIBUTE_FILES {
+            let start = file * ATTRIBUTE_FILE_SIZE;
+
+            self.attribute_map.load_file(&self.attribute_files[start..start + ATTRIBUTE_FILE_SIZE]);
+        }
+    }
+
+    /*
+     * the SGB reads VRAM transfers off the screen, so the data gets rebuilt from the first 256
+     * tiles of the frame. games lay tiles 0-255 out across the screen and set BGP to 0xe4 so
+     * the shades match the tile data.
+     */
+    fn read_transfer(shades: &[u8]) -> Vec<u8> {
+        let mut data = vec![0; TRANSFER_SIZE];
+
+        for tile in 0..TRANSFER_TILES {
+            let tile_x = (tile % (SCREEN_WIDTH / 8)) * 8;
+            let tile_y = (tile / (SCREEN_WIDTH / 8)) * 8;
+
+            for row in 0..8 {
+                let mut lower = 0;
+                let mut upper = 0;
+
+                for column in 0..8 {
+                    let shade = shades[tile_x + column + (tile_y + row) * SCREEN_WIDTH];
+
+                    lower |= (shade & 0x1) << (7 - column);
+                    upper |= ((shade >> 1) & 0x1) << (7 - column);
+                }
+
+                data[tile * 16 + row * 2] = lower;
+                data[tile * 16 + row * 2 + 1] = upper;
+            }
+        }
+
+        data
+    }
+
+    fn transfer(&mut self, transfer: VramTransfer, data: &[u8]) {
+        match transfer {
+            VramTransfer::SystemPalettes => for (i, color) in self.system_palettes.iter_mut().enumerate() {
+                *color = u16::from_le_bytes([data[i * 2], data[i * 2 + 1]]);
+            }
+            VramTransfer::AttributeFiles => {
+                let length = self.attribute_files.len();
+
+                self.attribute_files.copy_from_slice(&data[..length]);
+            }
+            VramTransfer::BorderTiles(first_tile) => self.border.load_tiles(first_tile, data),
+            VramTransfer::BorderMap => self.border.load_map(data)
+        }
+    }
+
+    // called at the end of every frame with the shades the PPU drew
+    pub fn finish_frame(&mut self, shades: &[u8]) {
+        if let Some(transfer) = self.pending_transfer.take() {
+            let data = Self::read_transfer(shades);
+
+            self.transfer(transfer, &data);
+        }
+
+        if self.mask != MaskMode::Freeze {
+            self.screen.copy_from_slice(shades);
+        }
+
+        self.render();
+    }
+
+    fn game_pixel(&self, x: usize, y: usize) -> u16 {
+        match self.mask {
+            MaskMode::Black => 0,
+            MaskMode::Color0 => self.palettes[0][0],
+            MaskMode::None | MaskMode::Freeze => {
+                let palette = self.attribute_map.palette_at(x / 8, y / 8);
+
+                self.palettes[palette][self.screen[x + y * SCREEN_WIDTH] as usize]
+            }
+        }
+    }
+
+    fn render(&mut self) {
+        let game_area_x = GAME_X..GAME_X + SCREEN_WIDTH;
+        let game_area_y = GAME_Y..GAME_Y + SCREEN_HEIGHT;
+
+        for y in 0..SGB_SCREEN_HEIGHT {
+            for x in 0..SGB_SCREEN_WIDTH {
+                // anything the border leaves transparent shows either the game or the shared color 0
+                let color = match self.border.pixel(x, y) {
+                    Some(color) => color,
+                    None => if game_area_x.contains(&x) && game_area_y.contains(&y) {
+                        self.game_pixel(x - GAME_X, y - GAME_Y)
+                    } else {
+                        self.palettes[0][0]
+                    }
+                };
+
+                self.picture.set_pixel(x, y, PPU::convert_pixel(color));
+            }
+        }
+    }
+}
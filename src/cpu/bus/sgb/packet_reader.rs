@@ -0,0 +1,97 @@
+use serde::{Deserialize, Serialize};
+
+pub const PACKET_SIZE: usize = 16;
+
+/*
+ * SGB games send commands by pulsing P14 and P15. pulling both low starts a packet, then every
+ * bit is sent by pulling just one of them low (P14 for a 0, P15 for a 1) followed by both going
+ * high again. a packet is 128 bits sent LSB first and ends with a 0 stop bit. the lower 3 bits of
+ * the first byte say how many packets the whole command is made of.
+ */
+#[derive(Serialize, Deserialize)]
+pub struct PacketReader {
+    packet: [u8; PACKET_SIZE],
+    bit_index: usize,
+    receiving: bool,
+    ready_for_bit: bool,
+    command: Vec<u8>,
+    packets_left: usize
+}
+
+impl PacketReader {
+    pub fn new() -> Self {
+        Self {
+            packet: [0; PACKET_SIZE],
+            bit_index: 0,
+            receiving: false,
+            ready_for_bit: false,
+            command: Vec::new(),
+            packets_left: 0
+        }
+    }
+
+    pub fn is_receiving(&self) -> bool {
+        self.receiving
+    }
+
+    // returns the whole command once its last packet has come in
+    pub fn write(&mut self, value: u8) -> Option<Vec<u8>> {
+        let p14_low = (value >> 4) & 0x1 == 0;
+        let p15_low = (value >> 5) & 0x1 == 0;
+
+        match (p14_low, p15_low) {
+            (true, true) => {
+                self.packet = [0; PACKET_SIZE];
+                self.bit_index = 0;
+                self.receiving = true;
+                self.ready_for_bit = false;
+
+                None
+            }
+            (false, false) => {
+                self.ready_for_bit = true;
+
+                None
+            }
+            _ if self.receiving && self.ready_for_bit => {
+                self.ready_for_bit = false;
+
+                self.receive_bit(p15_low as u8)
+            }
+            _ => None
+        }
+    }
+
+    fn receive_bit(&mut self, bit: u8) -> Option<Vec<u8>> {
+        // the stop bit after the last data bit
+        if self.bit_index == PACKET_SIZE * 8 {
+            self.receiving = false;
+
+            return self.finish_packet();
+        }
+
+        self.packet[self.bit_index / 8] |= bit << (self.bit_index % 8);
+
+        self.bit_index += 1;
+
+        None
+    }
+
+    fn finish_packet(&mut self) -> Option<Vec<u8>> {
+        if self.packets_left == 0 {
+            self.command.clear();
+
+            self.packets_left = (self.packet[0] & 0x7).max(1) as usize;
+        }
+
+        self.command.extend_from_slice(&self.packet);
+
+        self.packets_left -= 1;
+
+        if self.packets_left == 0 {
+            Some(std::mem::take(&mut self.command))
+        } else {
+            None
+        }
+    }
+}
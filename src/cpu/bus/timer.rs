@@ -14,74 +14,142 @@ impl TimerControl {
     pub fn clock_select(&self) -> u8 {
         self.bits() & 0x3
     }
+
+    // the bit of the system counter that clocks TIMA whenever it falls
+    pub fn counter_bit(&self) -> u16 {
+        match self.clock_select() {
+            0 => 1 << 9,
+            1 => 1 << 3,
+            2 => 1 << 5,
+            3 => 1 << 7,
+            _ => unreachable!()
+        }
+    }
 }
 
-pub const DIV_CYCLES: usize = 256;
+// the frame sequencer is clocked by DIV bit 4, or bit 5 in double speed mode
+const FRAME_SEQUENCER_BIT: u16 = 1 << 12;
+const DOUBLE_SPEED_FRAME_SEQUENCER_BIT: u16 = 1 << 13;
 
+/*
+ * DIV is the upper 8 bits of a 16 bit system counter that goes up every cycle. TIMA
+ * isn't clocked directly, it's incremented whenever the counter bit selected by TAC
+ * (ANDed with the enable bit) goes from 1 to 0. That means resetting DIV or changing
+ * TAC can tick TIMA as well.
+ */
 #[derive(Serialize, Deserialize)]
 pub struct Timer {
-    pub div: u8,
+    pub counter: u16,
     pub tima: u8,
     pub tma: u8,
     pub tac: TimerControl,
-    pub interval: usize,
-    cycles: usize,
-    div_cycles: usize
+    reload_pending: bool,
+    reloading: bool,
+    frame_sequencer_ticks: usize
 }
 
 impl Timer {
     pub fn new() -> Self {
         Self {
-            div: 0,
+            counter: 0,
             tima: 0,
             tma: 0,
             tac: TimerControl::from_bits_retain(0),
-            interval: 0,
-            cycles: 0,
-            div_cycles: 0
+            reload_pending: false,
+            reloading: false,
+            frame_sequencer_ticks: 0
         }
     }
 
-    pub fn tick(&mut self, cycles: usize, interrupt_register: &mut InterruptRegister) {
-        self.div_cycles += cycles;
-        if self.tac.contains(TimerControl::ENABLE) {
-            self.cycles += cycles;
-
-            if self.cycles >= self.interval {
-                self.cycles -= self.interval;
+    pub fn tick(&mut self, cycles: usize, interrupt_register: &mut InterruptRegister, double_speed: bool) {
+        for _ in 0..cycles / 4 {
+            self.reloading = false;
 
-                let (result, overflow) = self.tima.overflowing_add(1);
+            // TIMA reads 0 for a cycle after overflowing before TMA gets loaded and the interrupt is requested
+            if self.reload_pending {
+                self.reload_pending = false;
+                self.reloading = true;
 
-                self.tima = result;
+                self.tima = self.tma;
 
-                if overflow {
-                    self.tima = self.tma;
-                    // request interrupt
-                    interrupt_register.set(InterruptRegister::TIMER, true);
-                }
+                interrupt_register.set(InterruptRegister::TIMER, true);
             }
+
+            self.set_counter(self.counter.wrapping_add(4), double_speed);
+        }
+    }
+
+    pub fn div(&self) -> u8 {
+        (self.counter >> 8) as u8
+    }
+
+    pub fn reset_div(&mut self, double_speed: bool) {
+        self.set_counter(0, double_speed);
+    }
+
+    // returns how many times the APU's frame sequencer needs to be clocked since the last call
+    pub fn take_frame_sequencer_ticks(&mut self) -> usize {
+        std::mem::replace(&mut self.frame_sequencer_ticks, 0)
+    }
+
+    fn timer_signal(&self) -> bool {
+        self.tac.contains(TimerControl::ENABLE) && self.counter & self.tac.counter_bit() != 0
+    }
+
+    fn set_counter(&mut self, value: u16, double_speed: bool) {
+        let previous_signal = self.timer_signal();
+        let previous_counter = self.counter;
+
+        self.counter = value;
+
+        if previous_signal && !self.timer_signal() {
+            self.increment_tima();
         }
 
-        if self.div_cycles >= DIV_CYCLES {
-            self.div_cycles -= DIV_CYCLES;
-            self.div += 1;
+        let frame_sequencer_bit = if double_speed { DOUBLE_SPEED_FRAME_SEQUENCER_BIT } else { FRAME_SEQUENCER_BIT };
+
+        if previous_counter & frame_sequencer_bit != 0 && self.counter & frame_sequencer_bit == 0 {
+            self.frame_sequencer_ticks += 1;
         }
     }
 
+    fn increment_tima(&mut self) {
+        let (result, overflow) = self.tima.overflowing_add(1);
+
+        self.tima = result;
+
+        if overflow {
+            self.reload_pending = true;
+        }
+    }
+
+    // writing TIMA right after an overflow cancels the reload, but writes on the cycle TMA gets loaded are ignored
     pub fn write_tima(&mut self, value: u8) {
-        self.tima = value;
+        if !self.reloading {
+            self.tima = value;
+            self.reload_pending = false;
+        }
+    }
+
+    pub fn write_tma(&mut self, value: u8) {
+        self.tma = value;
+
+        if self.reloading {
+            self.tima = value;
+        }
+    }
+
+    pub fn read_tac(&self) -> u8 {
+        self.tac.bits() | 0xf8
     }
 
     pub fn update_tac(&mut self, val: u8) {
-        self.tac = TimerControl::from_bits_retain(val);
-
-        // numbers are in m-cycles, but emulator counts in t-cycles, so we need to multiply by 4
-        match self.tac.clock_select() {
-            0 => self.interval = 256 * 4,
-            1 => self.interval = 4 * 4,
-            2 => self.interval = 16 * 4,
-            3 => self.interval = 64 * 4,
-            _ => unreachable!()
+        let previous_signal = self.timer_signal();
+
+        self.tac = TimerControl::from_bits_retain(val & 0x7);
+
+        if previous_signal && !self.timer_signal() {
+            self.increment_tima();
         }
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,112 @@
+use serde::{Deserialize, Serialize};
+
+pub const BLOCK_SIZE: u16 = 0x10;
+
+// every block takes 32 system cycles, which is 8 m-cycles in normal speed or 16 m-cycles in double speed
+pub const BLOCK_CYCLES: usize = 32;
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum HdmaMode {
+    General,
+    Hblank
+}
+
+/*
+ * CGB VRAM DMA. General DMA copies everything at once and stalls the CPU until it's done,
+ * while HBlank DMA copies a 0x10 byte block at the start of every HBlank. An HBlank transfer
+ * can be cancelled by writing HDMA5 with bit 7 clear, in which case HDMA5 reads back the
+ * remaining length with bit 7 set.
+ */
+#[derive(Serialize, Deserialize)]
+pub struct VramDma {
+    source: u16,
+    destination: u16,
+    current_source: u16,
+    current_destination: u16,
+    remaining_blocks: u16,
+    hblank_active: bool
+}
+
+impl VramDma {
+    pub fn new() -> Self {
+        Self {
+            source: 0,
+            destination: 0,
+            current_source: 0,
+            current_destination: 0,
+            remaining_blocks: 0,
+            hblank_active: false
+        }
+    }
+
+    pub fn write_source_high(&mut self, value: u8) {
+        self.source = (self.source & 0xff) | (value as u16) << 8;
+    }
+
+    // the lower 4 bits are ignored
+    pub fn write_source_low(&mut self, value: u8) {
+        self.source = (self.source & 0xff00) | (value & 0xf0) as u16;
+    }
+
+    // the destination is always in VRAM, so only bits 4-12 are used
+    pub fn write_destination_high(&mut self, value: u8) {
+        self.destination = (self.destination & 0xff) | ((value & 0x1f) as u16) << 8;
+    }
+
+    pub fn write_destination_low(&mut self, value: u8) {
+        self.destination = (self.destination & 0xff00) | (value & 0xf0) as u16;
+    }
+
+    pub fn read_status(&self) -> u8 {
+        let remaining = (self.remaining_blocks.wrapping_sub(1) & 0x7f) as u8;
+
+        if self.hblank_active {
+            remaining
+        } else {
+            // 0xff once a transfer has finished, otherwise what was left when it got cancelled
+            0x80 | remaining
+        }
+    }
+
+    // returns the mode of the transfer that needs to start, or None if the write cancelled a transfer
+    pub fn write_control(&mut self, value: u8) -> Option<HdmaMode> {
+        let mode = if (value >> 7) & 0x1 == 1 { HdmaMode::Hblank } else { HdmaMode::General };
+
+        if self.hblank_active && mode == HdmaMode::General {
+            self.hblank_active = false;
+
+            return None;
+        }
+
+        self.current_source = self.source;
+        self.current_destination = self.destination;
+        self.remaining_blocks = (value & 0x7f) as u16 + 1;
+        self.hblank_active = mode == HdmaMode::Hblank;
+
+        Some(mode)
+    }
+
+    pub fn hblank_active(&self) -> bool {
+        self.hblank_active
+    }
+
+    pub fn remaining_blocks(&self) -> u16 {
+        self.remaining_blocks
+    }
+
+    // returns the source and destination of the next block, then moves on to the one after
+    pub fn next_block(&mut self) -> (u16, u16) {
+        let block = (self.current_source, 0x8000 | self.current_destination);
+
+        self.current_source = self.current_source.wrapping_add(BLOCK_SIZE);
+        self.current_destination = (self.current_destination + BLOCK_SIZE) & 0x1ff0;
+
+        self.remaining_blocks -= 1;
+
+        if self.remaining_blocks == 0 {
+            self.hblank_active = false;
+        }
+
+        block
+    }
+}
@@ -0,0 +1,36 @@
+use serde::{Deserialize, Serialize};
+
+// DMG, MGB and SGB boot ROMs are 256 bytes, CGB and AGB ones are 2304 bytes with the cartridge header in between
+pub const DMG_BOOT_ROM_SIZE: usize = 0x100;
+pub const CGB_BOOT_ROM_SIZE: usize = 0x900;
+
+#[derive(Copy, Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub enum HardwareModel {
+    Dmg,
+    Mgb,
+    Sgb,
+    Cgb,
+    Agb
+}
+
+impl HardwareModel {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "dmg" => Some(HardwareModel::Dmg),
+            "mgb" => Some(HardwareModel::Mgb),
+            "sgb" => Some(HardwareModel::Sgb),
+            "cgb" => Some(HardwareModel::Cgb),
+            "agb" => Some(HardwareModel::Agb),
+            _ => None
+        }
+    }
+
+    // the GBA runs CGB games the same way a CGB does
+    pub fn is_cgb(&self) -> bool {
+        matches!(self, HardwareModel::Cgb | HardwareModel::Agb)
+    }
+
+    pub fn boot_rom_size(&self) -> usize {
+        if self.is_cgb() { CGB_BOOT_ROM_SIZE } else { DMG_BOOT_ROM_SIZE }
+    }
+}
@@ -132,9 +132,45 @@ impl CPU {
         4
     }
 
+    /*
+     * STOP enters low-power mode until a joypad line goes low. whether the byte after it
+     * gets skipped depends on held buttons and pending interrupts:
+     *
+     * button held, interrupt pending: 1 byte opcode, nothing happens
+     * button held, no interrupt pending: 2 byte opcode, HALT mode is entered instead
+     * no button held, interrupt pending: 1 byte opcode, STOP mode is entered
+     * no button held, no interrupt pending: 2 byte opcode, STOP mode is entered
+     *
+     * on CGB, if a speed switch was armed through KEY1, STOP performs the switch instead.
+     */
     fn stop(&mut self) -> usize {
-        // NOP, TODO: actually implement for GBC
-        0
+        if self.bus.switch_speed() {
+            self.pc += 1;
+
+            return 4;
+        }
+
+        let button_held = self.bus.joypad.is_any_line_low();
+        let interrupt_pending = self.has_pending_interrupts();
+
+        if !interrupt_pending {
+            self.pc += 1;
+        }
+
+        if button_held {
+            if !interrupt_pending {
+                self.is_halted = true;
+            }
+
+            return 4;
+        }
+
+        // DIV is reset when entering STOP mode
+        self.bus.mem_write8(0xff04, 0);
+
+        self.is_stopped = true;
+
+        4
     }
 
     fn jr(&mut self, flag: JumpFlags) -> usize {
@@ -156,11 +192,13 @@ impl CPU {
             }
         };
 
-        let signed_imm = self.bus.mem_read8(self.pc) as i8;
+        let signed_imm = self.read8(self.pc) as i8;
 
         let cycles: usize = if condition_met {
             self.pc = ((self.pc as i32) + 1 + signed_imm as i32) as u16;
 
+            self.internal_cycle();
+
             12
         } else {
             self.pc += 1;
@@ -175,16 +213,18 @@ impl CPU {
         if reg1 == Register::SP {
             self.sp = self.hl();
 
+            self.internal_cycle();
+
             8
         } else {
             if reg1 == Register::HLPointer {
                 let value = self.registers[reg2 as usize];
 
-                self.bus.mem_write8(self.hl(), value);
+                self.write8(self.hl(), value);
 
                 8
             } else if reg2 == Register::HLPointer {
-                let value = self.bus.mem_read8(self.hl());
+                let value = self.read8(self.hl());
 
                 self.registers[reg1 as usize] = value;
 
@@ -198,13 +238,13 @@ impl CPU {
                     }
                     LoadType::LeftPointer => {
                         let value = self.get_register16(reg1);
-                        self.bus.mem_write8(value, self.registers[reg2 as usize]);
+                        self.write8(value, self.registers[reg2 as usize]);
 
                         8
                     }
                     LoadType::RightPointer => {
                         let address = self.get_register16(reg2);
-                        let value = self.bus.mem_read8(address);
+                        let value = self.read8(address);
 
                         self.registers[reg1 as usize] = value;
 
@@ -216,11 +256,11 @@ impl CPU {
     }
 
     fn ld_immediate_sp(&mut self) -> usize {
-        let address = self.bus.mem_read16(self.pc);
+        let address = self.read16(self.pc);
 
         self.pc += 2;
 
-        self.bus.mem_write16(address, self.sp);
+        self.write16(address, self.sp);
 
         20
     }
@@ -229,7 +269,7 @@ impl CPU {
         let (immediate, cycles) = if reg1 != Register::HLPointer && reg1 != Register::SP {
             if reg1 as usize > 6 || load_type != LoadType::Normal {
                 // 16 bit value
-                let val = self.bus.mem_read16(self.pc);
+                let val = self.read16(self.pc);
                 self.pc += 2;
 
                 let cycles = if load_type == LoadType::Normal {
@@ -241,19 +281,19 @@ impl CPU {
                 (val, cycles)
             } else {
                 // 8 bit value
-                let val = self.bus.mem_read8(self.pc) as u16;
+                let val = self.read8(self.pc) as u16;
                 self.pc += 1;
 
                 (val, 8)
             }
         } else {
             if reg1 == Register::HLPointer {
-                let immediate = self.bus.mem_read8(self.pc);
+                let immediate = self.read8(self.pc);
                 self.pc += 1;
 
                 (immediate as u16, 12)
             } else {
-                let immediate = self.bus.mem_read16(self.pc);
+                let immediate = self.read16(self.pc);
                 self.pc += 2;
 
                 (immediate, 12)
@@ -262,7 +302,7 @@ impl CPU {
 
 
         if reg1 == Register::HLPointer {
-            self.bus.mem_write8(self.hl(), immediate as u8);
+            self.write8(self.hl(), immediate as u8);
         } else if reg1 == Register::SP {
             self.sp = immediate;
         } else {
@@ -278,14 +318,14 @@ impl CPU {
                     if reg1 as usize > 6 {
                         panic!("invalid register given to ld_immediate with LeftPointer: {:?}", reg1);
                     } else {
-                        self.bus.mem_write8(immediate, self.registers[reg1 as usize]);
+                        self.write8(immediate, self.registers[reg1 as usize]);
                     }
                 }
                 LoadType::RightPointer => {
                     if reg1 as usize > 6 {
                         panic!("invalid register given to ld_immediate with RightPointer: {:?}", reg1);
                     } else {
-                        self.registers[reg1 as usize] = self.bus.mem_read8(immediate);
+                        self.registers[reg1 as usize] = self.read8(immediate);
                     }
                 }
             }
@@ -298,7 +338,7 @@ impl CPU {
         let (offset, cycles) = if use_c {
             (self.registers[Register::C as usize], 8)
         } else {
-            let (offset, cycles) = (self.bus.mem_read8(self.pc), 12);
+            let (offset, cycles) = (self.read8(self.pc), 12);
 
             self.pc += 1;
 
@@ -307,10 +347,10 @@ impl CPU {
 
         match load_type {
             LoadType::LeftPointer => {
-                self.bus.mem_write8(0xff00 + offset as u16, self.registers[reg1 as usize]);
+                self.write8(0xff00 + offset as u16, self.registers[reg1 as usize]);
             }
             LoadType::RightPointer => {
-                self.registers[reg1 as usize] = self.bus.mem_read8(0xff00 + offset as u16);
+                self.registers[reg1 as usize] = self.read8(0xff00 + offset as u16);
             }
             _ => panic!("invalid load type for ld_upper given: {:?}", load_type)
         }
@@ -328,6 +368,8 @@ impl CPU {
         self.f.set(FlagRegister::SUBTRACT, false);
         self.f.set(FlagRegister::HALF_CARRY, (result & 0xfff) < (old_hl & 0xfff));
 
+        self.internal_cycle();
+
         8
     }
 
@@ -335,7 +377,7 @@ impl CPU {
         let old_a = self.registers[Register::A as usize];
         let cycles = if let Some(register) = register {
             if register == Register::HLPointer {
-                self.registers[Register::A as usize] += self.bus.mem_read8(self.hl());
+                self.registers[Register::A as usize] += self.read8(self.hl());
 
                 8
             } else {
@@ -345,7 +387,7 @@ impl CPU {
                 4
             }
         } else {
-            let value = self.bus.mem_read8(self.pc);
+            let value = self.read8(self.pc);
 
             self.pc += 1;
 
@@ -367,11 +409,13 @@ impl CPU {
         let carry_bit = self.f.contains(FlagRegister::CARRY) as u8;
         let (cycles, result_full, operand) = if let Some(register) = register {
             if register == Register::HLPointer {
-                let result_full = self.registers[Register::A as usize] as u16 + self.bus.mem_read8(self.hl()) as u16 + carry_bit as u16;
+                let operand = self.read8(self.hl());
+
+                let result_full = self.registers[Register::A as usize] as u16 + operand as u16 + carry_bit as u16;
 
                 self.registers[Register::A as usize] = result_full as u8;
 
-                (8, result_full, self.bus.mem_read8(self.hl()))
+                (8, result_full, operand)
             } else {
                 let result_full = self.registers[Register::A as usize] as u16 + carry_bit as u16 + self.registers[register as usize] as u16;
                 self.registers[Register::A as usize] = result_full as u8;
@@ -379,7 +423,7 @@ impl CPU {
                 (4, result_full, self.registers[register as usize])
             }
         } else {
-            let operand = self.bus.mem_read8(self.pc);
+            let operand = self.read8(self.pc);
 
             let result_full = self.registers[Register::A as usize] as u16 + operand as u16 + carry_bit as u16;
 
@@ -402,7 +446,7 @@ impl CPU {
         let old_a = self.registers[Register::A as usize];
         let cycles = if let Some(register) = register {
             if register == Register::HLPointer {
-                self.registers[Register::A as usize] -= self.bus.mem_read8(self.hl());
+                self.registers[Register::A as usize] -= self.read8(self.hl());
 
                 8
             } else {
@@ -412,7 +456,7 @@ impl CPU {
                 4
             }
         } else {
-            let value = self.bus.mem_read8(self.pc);
+            let value = self.read8(self.pc);
 
             self.pc += 1;
 
@@ -434,7 +478,7 @@ impl CPU {
         let carry_bit = self.f.contains(FlagRegister::CARRY) as u8;
         let (cycles, operand) = if let Some(register) = register {
             if register == Register::HLPointer {
-                let operand = self.bus.mem_read8(self.hl());
+                let operand = self.read8(self.hl());
                 self.registers[Register::A as usize] = self.registers[Register::A as usize] - operand - carry_bit;
 
                 (8, operand)
@@ -444,7 +488,7 @@ impl CPU {
                 (4, self.registers[register as usize])
             }
         } else {
-            let operand = self.bus.mem_read8(self.pc);
+            let operand = self.read8(self.pc);
 
             self.pc += 1;
 
@@ -464,7 +508,7 @@ impl CPU {
     fn and(&mut self, register: Option<Register>) -> usize {
         let cycles = if let Some(register) = register {
             if register == Register::HLPointer {
-                self.registers[Register::A as usize] = self.registers[Register::A as usize] & self.bus.mem_read8(self.hl());
+                self.registers[Register::A as usize] = self.registers[Register::A as usize] & self.read8(self.hl());
 
                 8
             } else {
@@ -473,7 +517,7 @@ impl CPU {
                 4
             }
         } else {
-            let value = self.bus.mem_read8(self.pc);
+            let value = self.read8(self.pc);
 
             self.pc += 1;
 
@@ -493,7 +537,7 @@ impl CPU {
     fn xor(&mut self, register: Option<Register>) -> usize {
         let cycles = if let Some(register) = register {
             if register == Register::HLPointer {
-                self.registers[Register::A as usize] = self.registers[Register::A as usize] ^ self.bus.mem_read8(self.hl());
+                self.registers[Register::A as usize] = self.registers[Register::A as usize] ^ self.read8(self.hl());
 
                 8
             } else {
@@ -502,7 +546,7 @@ impl CPU {
                 4
             }
         } else {
-            let value = self.bus.mem_read8(self.pc);
+            let value = self.read8(self.pc);
 
             self.pc += 1;
 
@@ -523,7 +567,7 @@ impl CPU {
 
         let cycles = if let Some(register) = register {
             if register == Register::HLPointer {
-                let value = self.bus.mem_read8(self.hl());
+                let value = self.read8(self.hl());
 
                 self.registers[Register::A as usize] |= value;
 
@@ -535,7 +579,7 @@ impl CPU {
                 4
             }
         } else {
-            let value = self.bus.mem_read8(self.pc);
+            let value = self.read8(self.pc);
             self.pc += 1;
 
             self.registers[Register::A as usize] = self.registers[Register::A as usize] | value;
@@ -556,12 +600,12 @@ impl CPU {
 
         let (operand, cycles) = if let Some(register) = register {
             if register == Register::HLPointer {
-                (self.bus.mem_read8(self.hl()), 8)
+                (self.read8(self.hl()), 8)
             } else {
                 (self.registers[register as usize], 4)
             }
         } else {
-            let (operand, cycles) = (self.bus.mem_read8(self.pc), 8);
+            let (operand, cycles) = (self.read8(self.pc), 8);
 
             self.pc += 1;
 
@@ -587,29 +631,29 @@ impl CPU {
     }
 
     fn store_hl_ptr(&mut self, r1: Register, increment_mode: IncrementMode) -> usize {
-        self.bus.mem_write8(self.hl(), self.registers[r1 as usize]);
+        self.write8(self.hl(), self.registers[r1 as usize]);
 
         match increment_mode {
             IncrementMode::Decrement => self.dec_register16(Register::HL),
             IncrementMode::Increment => self.inc_register16(Register::HL)
         }
 
-        4
+        8
     }
 
     fn load_hl_ptr(&mut self, r1: Register, increment_mode: IncrementMode) -> usize {
-        self.registers[r1 as usize] = self.bus.mem_read8(self.hl());
+        self.registers[r1 as usize] = self.read8(self.hl());
 
         match increment_mode {
             IncrementMode::Decrement => self.dec_register16(Register::HL),
             IncrementMode::Increment => self.inc_register16(Register::HL)
         }
 
-        4
+        8
     }
 
     fn ld_hl_displacement(&mut self) -> usize {
-        let displacement = self.bus.mem_read8(self.pc) as i8 as i16;
+        let displacement = self.read8(self.pc) as i8 as i16;
 
         let old_sp = self.sp;
 
@@ -626,17 +670,19 @@ impl CPU {
         self.f.set(FlagRegister::HALF_CARRY, half_carry);
         self.f.set(FlagRegister::CARRY, carry);
 
+        self.internal_cycle();
+
         12
     }
 
     fn inc(&mut self, r1: Register) -> usize {
         let cycles = if r1 as usize > 6 {
             if r1 == Register::HLPointer {
-                let old_value = self.bus.mem_read8(self.hl());
+                let old_value = self.read8(self.hl());
 
                 let result = old_value + 1;
 
-                self.bus.mem_write8(self.hl(), result);
+                self.write8(self.hl(), result);
 
                 self.f.set(FlagRegister::ZERO, result == 0);
                 self.f.set(FlagRegister::SUBTRACT, false);
@@ -646,10 +692,14 @@ impl CPU {
             } else if r1 == Register::SP {
                 self.sp += 1;
 
+                self.internal_cycle();
+
                 8
             } else {
                 self.inc_register16(r1);
 
+                self.internal_cycle();
+
                 8
             }
         } else {
@@ -670,10 +720,10 @@ impl CPU {
     fn dec(&mut self, r1: Register) -> usize {
         let cycles = if r1 as usize > 6 {
             if r1 == Register::HLPointer {
-                let old_value = self.bus.mem_read8(self.hl());
+                let old_value = self.read8(self.hl());
                 let result = old_value - 1;
 
-                self.bus.mem_write8(self.hl(), result);
+                self.write8(self.hl(), result);
 
                 self.f.set(FlagRegister::ZERO, result == 0);
                 self.f.set(FlagRegister::SUBTRACT, true);
@@ -683,10 +733,14 @@ impl CPU {
             } else if r1 == Register::SP {
                 self.sp -= 1;
 
+                self.internal_cycle();
+
                 8
             } else {
                 self.dec_register16(r1);
 
+                self.internal_cycle();
+
                 8
             }
         } else {
@@ -820,10 +874,15 @@ impl CPU {
         4
     }
 
+    // HALT with IME off and an interrupt already pending doesn't halt at all, instead triggering the HALT bug
     fn halt(&mut self) -> usize {
-        self.is_halted = true;
+        if !self.bus.ime && self.has_pending_interrupts() {
+            self.halt_bug = true;
+        } else {
+            self.is_halted = true;
+        }
 
-        0
+        4
     }
 
     fn ret(&mut self, flags: JumpFlags) -> usize {
@@ -835,25 +894,26 @@ impl CPU {
             JumpFlags::NZ => !self.f.contains(FlagRegister::ZERO)
         };
 
-        let cycles = if condition_met {
-            if flags == JumpFlags::NoFlag {
-                16
-            } else {
-                20
-            }
+        // the conditional versions spend a cycle checking the condition
+        let cycles = if flags == JumpFlags::NoFlag {
+            16
         } else {
-            8
+            self.internal_cycle();
+
+            if condition_met { 20 } else { 8 }
         };
 
         if condition_met {
             self.pc = self.pop_from_stack();
+
+            self.internal_cycle();
         }
 
         cycles
     }
 
     fn add_sp(&mut self) -> usize {
-        let displacement = self.bus.mem_read8(self.pc) as i8 as i16;
+        let displacement = self.read8(self.pc) as i8 as i16;
 
         self.pc += 1;
 
@@ -868,6 +928,9 @@ impl CPU {
         self.f.set(FlagRegister::CARRY, carry);
         self.f.set(FlagRegister::HALF_CARRY, half_carry);
 
+        self.internal_cycle();
+        self.internal_cycle();
+
         16
     }
 
@@ -906,35 +969,38 @@ impl CPU {
             JumpFlags::C => self.f.contains(FlagRegister::CARRY)
         };
 
-        let address = self.bus.mem_read16(self.pc);
+        let address = self.read16(self.pc);
 
         let cycles = if condition_met {
             self.pc = address;
 
-            12
+            self.internal_cycle();
+
+            16
         } else {
             self.pc += 2;
 
-            8
+            12
         };
 
         cycles
     }
 
     fn ei(&mut self) -> usize {
-        self.bus.ime = true;
+        self.ime_scheduled = true;
 
         4
     }
 
     fn di(&mut self) -> usize {
         self.bus.ime = false;
+        self.ime_scheduled = false;
 
         4
     }
 
     fn call(&mut self, flags: JumpFlags) -> usize {
-        let address = self.bus.mem_read16(self.pc);
+        let address = self.read16(self.pc);
 
         let condition = match flags {
             JumpFlags::NoFlag => true,
@@ -947,13 +1013,15 @@ impl CPU {
         self.pc += 2;
 
         let cycles = if condition {
+            self.internal_cycle();
+
             self.push_to_stack(self.pc);
 
             self.pc = address;
 
             24
         } else {
-            18
+            12
         };
 
         cycles
@@ -966,12 +1034,16 @@ impl CPU {
             self.get_register16(r1)
         };
 
+        self.internal_cycle();
+
         self.push_to_stack(value);
 
         16
     }
 
     fn rst(&mut self, y: u8) -> usize {
+        self.internal_cycle();
+
         self.push_to_stack(self.pc);
 
         self.pc = y as u16;
@@ -981,7 +1053,7 @@ impl CPU {
 
     fn bit(&mut self, bit: u8, r1: Register) -> usize {
         let (value, cycles) = if r1 == Register::HLPointer {
-            (self.bus.mem_read8(self.hl()), 12)
+            (self.read8(self.hl()), 12)
         } else {
             (self.registers[r1 as usize], 8)
         };
@@ -995,11 +1067,11 @@ impl CPU {
 
     fn res(&mut self, bit: u8, r1: Register) -> usize {
         if r1 == Register::HLPointer {
-            let mut value = self.bus.mem_read8(self.hl());
+            let mut value = self.read8(self.hl());
 
             value &= !(1 << bit);
 
-            self.bus.mem_write8(self.hl(), value);
+            self.write8(self.hl(), value);
 
             16
         } else {
@@ -1010,11 +1082,11 @@ impl CPU {
 
     fn set(&mut self, bit: u8, r1: Register) -> usize {
         if r1 == Register::HLPointer {
-            let mut value = self.bus.mem_read8(self.hl());
+            let mut value = self.read8(self.hl());
 
             value |= 1 << bit;
 
-            self.bus.mem_write8(self.hl(), value);
+            self.write8(self.hl(), value);
 
             16
         } else {
@@ -1026,7 +1098,7 @@ impl CPU {
 
     fn rl(&mut self, r1: Register) -> usize {
          if r1 == Register::HLPointer {
-            let mut value = self.bus.mem_read8(self.hl());
+            let mut value = self.read8(self.hl());
 
             let bit7 = (value >> 7) & 0x1;
 
@@ -1041,7 +1113,7 @@ impl CPU {
             self.f.set(FlagRegister::ZERO, value == 0);
             self.f.set(FlagRegister::CARRY, bit7 == 1);
 
-            self.bus.mem_write8(self.hl(), value);
+            self.write8(self.hl(), value);
 
             16
         } else {
@@ -1064,13 +1136,13 @@ impl CPU {
 
     fn srl(&mut self, r1: Register) -> usize {
         let (result, cycles, carry) = if r1 == Register::HLPointer {
-            let value = self.bus.mem_read8(self.hl());
+            let value = self.read8(self.hl());
 
             let carry = value & 0b1 == 1;
 
             let result = value >> 1;
 
-            self.bus.mem_write8(self.hl(), result);
+            self.write8(self.hl(), result);
 
             (result, 16, carry)
         } else {
@@ -1093,7 +1165,7 @@ impl CPU {
 
     fn sra(&mut self, r1: Register) -> usize {
         if r1 == Register::HLPointer {
-            let mut value = self.bus.mem_read8(self.hl());
+            let mut value = self.read8(self.hl());
 
             let bit7 = (value >> 7) & 0x1;
 
@@ -1107,7 +1179,7 @@ impl CPU {
             self.f.set(FlagRegister::HALF_CARRY, false);
             self.f.set(FlagRegister::ZERO, value == 0);
 
-            self.bus.mem_write8(self.hl(), value);
+            self.write8(self.hl(), value);
 
             16
         } else {
@@ -1130,7 +1202,7 @@ impl CPU {
     fn rlc(&mut self, r1: Register) -> usize {
 
         let (cycles, bit7, value) = if r1 == Register::HLPointer {
-            let mut value = self.bus.mem_read8(self.hl());
+            let mut value = self.read8(self.hl());
 
             let bit7 = (value >> 7) & 0x1;
 
@@ -1138,7 +1210,7 @@ impl CPU {
 
             value |= bit7;
 
-            self.bus.mem_write8(self.hl(), value);
+            self.write8(self.hl(), value);
 
             (16, bit7, value)
         } else {
@@ -1162,7 +1234,7 @@ impl CPU {
 
     fn rrc(&mut self, r1: Register) -> usize {
         let (cycles, bit1, result) = if r1 == Register::HLPointer {
-            let mut value = self.bus.mem_read8(self.hl());
+            let mut value = self.read8(self.hl());
 
             let bit1 = value & 0x1;
 
@@ -1170,7 +1242,7 @@ impl CPU {
 
             value |= bit1 << 7;
 
-            self.bus.mem_write8(self.hl(), value);
+            self.write8(self.hl(), value);
 
             (16, bit1, value)
         } else {
@@ -1194,13 +1266,13 @@ impl CPU {
 
     fn sla(&mut self, r1: Register) -> usize {
         let (result, cycles, carry) = if r1 == Register::HLPointer {
-            let mut val = self.bus.mem_read8(self.hl());
+            let mut val = self.read8(self.hl());
 
             let carry = (val >> 7) & 0x1 == 1;
 
             val <<= 1;
 
-            self.bus.mem_write8(self.hl(), val);
+            self.write8(self.hl(), val);
 
             (val, 16, carry)
         } else {
@@ -1222,14 +1294,14 @@ impl CPU {
     fn rr(&mut self, r1: Register) -> usize {
 
         let (result, cycles, carry) = if r1 == Register::HLPointer {
-            let mut value = self.bus.mem_read8(self.hl());
+            let mut value = self.read8(self.hl());
 
             let carry_bit = value & 0x1;
             let carry = carry_bit == 1;
 
             value = (value >> 1) | ((self.f.contains(FlagRegister::CARRY) as u8) << 7);
 
-            self.bus.mem_write8(self.hl(), value);
+            self.write8(self.hl(), value);
 
             (value, 16, carry)
         } else {
@@ -1259,14 +1331,14 @@ impl CPU {
 
             (self.registers[r1 as usize], 8)
         } else {
-            let byte = self.bus.mem_read8(self.hl());
+            let byte = self.read8(self.hl());
 
             let upper = (byte >> 4) & 0xf;
             let lower = byte & 0xf;
 
             let result = (lower << 4) | upper;
 
-            self.bus.mem_write8(self.hl(), result);
+            self.write8(self.hl(), result);
 
             (result, 16)
         };
@@ -1400,7 +1472,7 @@ impl CPU {
                     3 => match y {
                         0 => self.jp(JumpFlags::NoFlag),
                         1 => {
-                            let cb_opcode = self.bus.mem_read8(self.pc);
+                            let cb_opcode = self.read8(self.pc);
                             self.pc += 1;
 
                             self.decode_cb_instruction(cb_opcode)
@@ -1,16 +1,25 @@
 use std::{collections::HashSet, sync::Arc};
 
 use bitflags::bitflags;
-use bus::{interrupt_register::InterruptRegister, Bus};
+use bus::{
+    cartridge::mbc::{pocket_camera::image_source::ImageSource, MBC},
+    infrared::{infrared_link::InfraredLink, local_infrared_link::LocalInfraredLink},
+    interrupt_register::InterruptRegister,
+    ppu::{colorization::CompatibilityPalettes, picture::Picture},
+    serial::{link_cable::LinkCable, local_link_cable::LocalLinkCable},
+    Bus
+};
+use hardware_model::HardwareModel;
 use ringbuf::{storage::Heap, wrap::caching::Caching, SharedRb};
 use serde::{Deserialize, Serialize};
 
 pub mod bus;
 pub mod instructions;
 pub mod disassembler;
+pub mod hardware_model;
 
 pub const CLOCK_SPEED: usize = 4194304;
-const CGB_ADDR: usize = 0x143;
+const CYCLES_PER_FRAME: usize = 70224;
 
 bitflags! {
     #[derive(Serialize, Deserialize)]
@@ -48,7 +57,12 @@ pub struct CPU {
     pub bus: Bus,
     found: HashSet<u16>,
     pub debug_on: bool,
-    is_halted: bool
+    is_halted: bool,
+    is_stopped: bool,
+    stopped_cycles: usize,
+    instruction_cycles: usize,
+    ime_scheduled: bool,
+    halt_bug: bool
 }
 
 impl CPU {
@@ -57,17 +71,23 @@ impl CPU {
         waveform_producer: Option<Caching<Arc<SharedRb<Heap<f32>>>, true, false>>,
         save_path: Option<String>,
         is_ios: bool,
-        is_desktop: bool
+        is_desktop: bool,
+        model: HardwareModel
     ) -> CPU {
         CPU {
             registers: [0x1, 0x0, 0x13, 0x0, 0xd8, 0x1, 0x4d],
             pc: 0x100,
             sp: 0xfffe,
             f: FlagRegister::from_bits_retain(0xb0),
-            bus: Bus::new(producer, waveform_producer, save_path, is_ios, is_desktop),
+            bus: Bus::new(producer, waveform_producer, save_path, is_ios, is_desktop, model),
             found: HashSet::new(),
             debug_on: false,
-            is_halted: false
+            is_halted: false,
+            is_stopped: false,
+            stopped_cycles: 0,
+            instruction_cycles: 0,
+            ime_scheduled: false,
+            halt_bug: false
         }
     }
 
@@ -83,45 +103,175 @@ impl CPU {
     }
 
     pub fn load_save_state(&mut self, data: &[u8]) {
+        // the link cable, infrared link and camera image source aren't part of the save state, keep whatever is currently plugged in
+        let link_cable = self.bus.serial.disconnect();
+        let infrared_link = self.bus.infrared.disconnect();
+        let image_source = self.bus.cartridge.disconnect_image_source();
+        let boot_rom = self.bus.boot_rom().to_vec();
+
         (*self, _) = bincode::serde::decode_from_slice(data, bincode::config::standard()).unwrap();
+
+        self.bus.serial.connect(link_cable);
+        self.bus.infrared.connect(infrared_link);
+
+        if let Some(image_source) = image_source {
+            self.bus.cartridge.connect_image_source(image_source);
+        }
+
+        self.bus.reload_boot_rom(boot_rom);
+    }
+
+    pub fn connect_link_cable(&mut self, link_cable: Box<dyn LinkCable>) {
+        self.bus.serial.connect(link_cable);
+    }
+
+    pub fn disconnect_link_cable(&mut self) -> Box<dyn LinkCable> {
+        self.bus.serial.disconnect()
+    }
+
+    pub fn link_with(&mut self, other: &mut CPU) {
+        let (cable1, cable2) = LocalLinkCable::new_pair();
+
+        self.connect_link_cable(Box::new(cable1));
+        other.connect_link_cable(Box::new(cable2));
+    }
+
+    pub fn connect_infrared_link(&mut self, link: Box<dyn InfraredLink>) {
+        self.bus.infrared.connect(link);
     }
 
+    pub fn disconnect_infrared_link(&mut self) -> Box<dyn InfraredLink> {
+        self.bus.infrared.disconnect()
+    }
+
+    pub fn infrared_link_with(&mut self, other: &mut CPU) {
+        let (link1, link2) = LocalInfraredLink::new_pair();
+
+        self.connect_infrared_link(Box::new(link1));
+        other.connect_infrared_link(Box::new(link2));
+    }
+
+    /*
+     * every memory access takes one m-cycle, so the rest of the system gets ticked
+     * as the access happens rather than after the whole instruction is done.
+     */
+    pub fn read8(&mut self, address: u16) -> u8 {
+        self.internal_cycle();
+
+        self.bus.cpu_read8(address)
+    }
+
+    pub fn write8(&mut self, address: u16, value: u8) {
+        self.internal_cycle();
+
+        self.bus.cpu_write8(address, value);
+    }
+
+    pub fn read16(&mut self, address: u16) -> u16 {
+        let lower = self.read8(address) as u16;
+        let upper = self.read8(address + 1) as u16;
+
+        upper << 8 | lower
+    }
+
+    pub fn write16(&mut self, address: u16, value: u16) {
+        self.write8(address, value as u8);
+        self.write8(address + 1, (value >> 8) as u8);
+    }
+
+    // an m-cycle where the CPU is busy without touching memory
+    pub fn internal_cycle(&mut self) {
+        self.bus.tick(4);
+
+        self.instruction_cycles += 4;
+    }
+
+    // the upper byte gets pushed first
     pub fn push_to_stack(&mut self, value: u16) {
-        self.sp -= 2;
+        self.sp -= 1;
+        self.write8(self.sp, (value >> 8) as u8);
 
-        self.bus.mem_write16(self.sp, value);
+        self.sp -= 1;
+        self.write8(self.sp, value as u8);
     }
 
     pub fn pop_from_stack(&mut self) -> u16 {
-        let value = self.bus.mem_read16(self.sp);
+        let value = self.read16(self.sp);
 
         self.sp += 2;
 
         value
     }
 
-    pub fn step(&mut self) {
-        self.handle_interrupts();
+    // returns the number of cycles the step took, including any interrupt dispatch
+    pub fn step(&mut self) -> usize {
+        if self.is_stopped {
+            return self.step_stopped();
+        }
+
+        let interrupt_cycles = self.handle_interrupts();
+
+        // EI only takes effect after the instruction following it, so it can't be interrupted
+        if self.ime_scheduled {
+            self.ime_scheduled = false;
+            self.bus.ime = true;
+        }
 
         let previous_pc = self.pc;
         if self.is_halted {
-            self.bus.tick(4);
+            self.bus.tick_halted(4);
 
-            return;
+            return interrupt_cycles + 4;
         }
 
-        let opcode = self.bus.mem_read8(self.pc);
+        self.instruction_cycles = 0;
+
+        let opcode = self.read8(self.pc);
 
-        self.pc += 1;
+        // with the HALT bug, the CPU fails to increment pc and the next byte gets read twice
+        if self.halt_bug {
+            self.halt_bug = false;
+        } else {
+            self.pc += 1;
+        }
 
-        if !self.found.contains(&(self.pc - 1)) && self.debug_on {
+        if !self.found.contains(&previous_pc) && self.debug_on {
             println!("[Opcode: 0x{:x}] [Address: 0x{:x}] {}", opcode, previous_pc, self.disassemble(opcode));
-            self.found.insert(self.pc - 1);
+            self.found.insert(previous_pc);
         }
 
         let cycles = self.decode_instruction(opcode);
 
-        self.bus.tick(cycles);
+        // memory accesses and internal cycles have already ticked the bus, this should never be needed
+        if cycles > self.instruction_cycles {
+            self.bus.tick(cycles - self.instruction_cycles);
+        }
+
+        interrupt_cycles + cycles.max(self.instruction_cycles)
+    }
+
+    /*
+     * the system clock doesn't run at all in STOP mode, only a joypad line going low wakes
+     * the CPU back up. the cycles are still counted so frames keep finishing and the
+     * frontends get to poll input.
+     */
+    fn step_stopped(&mut self) -> usize {
+        if self.bus.joypad.is_any_line_low() {
+            self.is_stopped = false;
+            self.stopped_cycles = 0;
+
+            return 0;
+        }
+
+        self.stopped_cycles += 4;
+
+        if self.stopped_cycles >= CYCLES_PER_FRAME {
+            self.stopped_cycles -= CYCLES_PER_FRAME;
+
+            self.bus.ppu.frame_finished = true;
+        }
+
+        4
     }
 
     pub fn step_frame(&mut self) {
@@ -130,11 +280,71 @@ impl CPU {
         }
     }
 
+    // runs for at least the given number of cycles, used to keep several linked instances in lockstep
+    pub fn run_for(&mut self, cycles: usize) {
+        let mut elapsed = 0;
+
+        while elapsed < cycles {
+            elapsed += self.step();
+        }
+    }
+
+    // has to be loaded before the game, the boot ROM then runs from 0x0000 instead of skipping straight to 0x100
+    pub fn load_boot_rom(&mut self, bytes: &[u8]) {
+        let expected_size = self.bus.model.boot_rom_size();
+
+        if bytes.len() != expected_size {
+            println!("[WARN] boot ROM for {:?} should be 0x{:x} bytes, got 0x{:x}. skipping it", self.bus.model, expected_size, bytes.len());
+
+            return;
+        }
+
+        self.bus.load_boot_rom(bytes);
+    }
+
     pub fn load_rom(&mut self, bytes: &[u8], logged_in: bool) {
         self.bus.cartridge.rom = bytes.to_vec();
 
-        self.check_cgb_header();
+        if self.bus.is_boot_rom_mapped() {
+            self.start_boot_rom();
+        } else {
+            self.check_cgb_header();
+            self.skip_boot_rom();
+        }
+
         self.bus.check_header(logged_in);
+
+        if !self.bus.ppu.cgb_mode {
+            self.bus.ppu.colorization_palettes = CompatibilityPalettes::from_cartridge(&self.bus.cartridge, &self.bus.joypad);
+        }
+
+        self.bus.sgb.commands_enabled = self.bus.model == HardwareModel::Sgb && self.bus.cartridge.sgb_support();
+    }
+
+    // the SGB draws the game inside of a bigger picture with the border around it
+    pub fn picture(&self) -> &Picture {
+        if self.bus.model == HardwareModel::Sgb {
+            &self.bus.sgb.picture
+        } else {
+            &self.bus.ppu.picture
+        }
+    }
+
+    // feeds the MBC7 accelerometer, in g. does nothing for other cartridges
+    pub fn set_tilt(&mut self, x: f32, y: f32) {
+        if let MBC::MBC7(mbc) = &mut self.bus.cartridge.mbc {
+            mbc.set_tilt(x, y);
+        }
+    }
+
+    // the rumble motor's strength over the last frame, from 0 to 1. always 0 for carts without one
+    pub fn rumble_strength(&self) -> f32 {
+        self.bus.cartridge.rumble_strength()
+    }
+
+    // what the Pocket Camera takes pictures of. does nothing for other cartridges
+    pub fn connect_image_source(&mut self, image_source: Box<dyn ImageSource>) {
+        self.bus.cartridge.connect_image_source(image_source);
     }
 
     pub fn reload_rom(&mut self, bytes: &[u8]) {
@@ -142,74 +352,143 @@ impl CPU {
     }
 
     fn check_cgb_header(&mut self) {
-        let cgb_flag = self.bus.cartridge.rom[CGB_ADDR];
+        if !self.bus.model.is_cgb() {
+            return;
+        }
+
+        let cgb_flag = self.bus.cartridge.cgb_flag();
+
+        // the boot ROM copies the CGB flag in to KEY0, or sets it to DMG compatibility mode for older games
         if [0x80, 0xc0].contains(&cgb_flag) {
             self.bus.ppu.cgb_mode = true;
-            self.update_cgb_registers();
+            self.bus.key0 = cgb_flag;
+        } else {
+            self.bus.key0 = 0x4;
         }
     }
 
-    fn update_cgb_registers(&mut self) {
+    // everything starts out cleared, the boot ROM sets the rest up itself
+    fn start_boot_rom(&mut self) {
+        self.registers = [0; 7];
+        self.f = FlagRegister::from_bits_retain(0);
+        self.sp = 0;
+        self.pc = 0;
+
+        self.bus.ime = false;
+
+        // CGB boot ROMs run in CGB mode until they decide what mode the game needs
+        self.bus.ppu.cgb_mode = self.bus.model.is_cgb();
+
+        self.bus.mem_write8(0xff40, 0);
+        self.bus.ppu.bgp.write(0);
+    }
+
+    // registers as each model's boot ROM leaves them when it jumps to 0x100
+    fn skip_boot_rom(&mut self) {
         use Register::*;
 
-        self.registers[Register::A as usize] = 0x11;
+        let cartridge = &self.bus.cartridge;
+
+        let (a, f, b, c, d, e, h, l) = match self.bus.model {
+            HardwareModel::Dmg | HardwareModel::Mgb => {
+                let a = if self.bus.model == HardwareModel::Mgb { 0xff } else { 0x01 };
+
+                // half carry and carry come from the boot ROM's header checksum
+                let f = if cartridge.header_checksum() == 0 { 0x80 } else { 0xb0 };
+
+                (a, f, 0x00, 0x13, 0x00, 0xd8, 0x01, 0x4d)
+            }
+            HardwareModel::Sgb => (0x01, 0x00, 0x00, 0x14, 0x00, 0x00, 0xc0, 0x60),
+            HardwareModel::Cgb | HardwareModel::Agb => if self.bus.ppu.cgb_mode {
+                (0x11, 0x80, 0x00, 0x00, 0xff, 0x56, 0x00, 0x0d)
+            } else {
+                // B is left with the title checksum the boot ROM used to pick palettes
+                let b = if cartridge.nintendo_licensee() { cartridge.title_checksum() } else { 0x00 };
+
+                let (h, l) = if [0x43, 0x58].contains(&b) { (0x99, 0x1a) } else { (0x00, 0x7c) };
+
+                (0x11, 0x80, b, 0x00, 0x00, 0x08, h, l)
+            }
+        };
+
+        self.registers[A as usize] = a;
+        self.f = FlagRegister::from_bits_retain(f);
+
+        self.registers[B as usize] = b;
+        self.registers[C as usize] = c;
+        self.registers[D as usize] = d;
+        self.registers[E as usize] = e;
 
-        self.f.set(FlagRegister::ZERO, true);
-        self.f.set(FlagRegister::SUBTRACT, false);
-        self.f.set(FlagRegister::CARRY, false);
-        self.f.set(FlagRegister::HALF_CARRY, false);
+        self.registers[H as usize] = h;
+        self.registers[L as usize] = l;
 
-        self.registers[B as usize] = 0x0;
-        self.registers[C as usize] = 0x0;
-        self.registers[D as usize] = 0xff;
-        self.registers[E as usize] = 0x56;
+        /*
+         * the AGB boot ROM does an extra INC B before jumping to the game, which is how games
+         * can tell they're running on a GBA and brighten their palettes for its screen.
+         */
+        if self.bus.model == HardwareModel::Agb {
+            let result = b.wrapping_add(1);
 
-        self.registers[H as usize] = 0x0;
-        self.registers[L as usize] = 0xd;
+            self.registers[B as usize] = result;
+
+            self.f.set(FlagRegister::ZERO, result == 0);
+            self.f.set(FlagRegister::SUBTRACT, false);
+            self.f.set(FlagRegister::HALF_CARRY, b & 0xf == 0xf);
+        }
 
         self.sp = 0xfffe;
         self.pc = 0x100;
+
+        self.bus.skip_boot_rom();
     }
 
-    pub fn handle_interrupts(&mut self) {
-        let fired_interrupts = self.bus.IF.bits() & self.bus.ie.bits();
+    pub fn has_pending_interrupts(&self) -> bool {
+        self.bus.IF.bits() & self.bus.ie.bits() & 0x1f != 0
+    }
 
-        if fired_interrupts != 0 {
-            self.is_halted = false;
+    pub fn handle_interrupts(&mut self) -> usize {
+        if !self.has_pending_interrupts() {
+            return 0;
         }
 
-        if self.bus.ime {
-            if fired_interrupts != 0 {
-                let irq_index = fired_interrupts.trailing_zeros();
+        self.is_halted = false;
 
+        if !self.bus.ime {
+            return 0;
+        }
 
-                self.bus.IF = InterruptRegister::from_bits_truncate(self.bus.IF.bits() & !(1 << irq_index));
-                self.bus.ime = false;
+        self.bus.ime = false;
 
-                // use the InterruptRegister struct to determine what interrupt fired
-                let temp = InterruptRegister::from_bits_retain(1 << irq_index);
+        self.internal_cycle();
+        self.internal_cycle();
 
-                self.bus.tick(8);
+        self.sp -= 1;
+        self.write8(self.sp, (self.pc >> 8) as u8);
 
-                self.push_to_stack(self.pc);
+        /*
+         * the interrupt to service is only picked after the upper byte of pc is pushed. if that push
+         * overwrote IE and nothing is pending anymore, the dispatch is cancelled and execution
+         * continues at 0x0000 instead.
+         */
+        let fired_interrupts = self.bus.IF.bits() & self.bus.ie.bits() & 0x1f;
 
-                self.bus.tick(8);
+        self.sp -= 1;
+        self.write8(self.sp, self.pc as u8);
 
-                if temp.contains(InterruptRegister::VBLANK) {
-                    self.pc = 0x40;
-                } else if temp.contains(InterruptRegister::LCD) {
-                    self.pc = 0x48;
-                } else if temp.contains(InterruptRegister::TIMER) {
-                    self.pc = 0x50;
-                } else if temp.contains(InterruptRegister::SERIAL) {
-                    self.pc = 0x58;
-                } else if temp.contains(InterruptRegister::JOYPAD) {
-                    self.pc = 0x60;
-                }
+        if fired_interrupts != 0 {
+            let irq_index = fired_interrupts.trailing_zeros();
 
-                self.bus.tick(4);
-            }
+            self.bus.IF = InterruptRegister::from_bits_truncate(self.bus.IF.bits() & !(1 << irq_index));
+
+            // vectors start at 0x40 for vblank and go up by 8 in the same order as the bits in IF
+            self.pc = 0x40 + irq_index as u16 * 8;
+        } else {
+            self.pc = 0;
         }
+
+        self.internal_cycle();
+
+        20
     }
 
     pub fn set_register16(&mut self, r1: Register, val: u16) {
@@ -1,7 +1,7 @@
 import JSZip from 'jszip'
 import init, { WebEmulator, InitOutput } from "../../pkg/gb_plus_web"
 import wasmData from '../../pkg/gb_plus_web_bg.wasm'
-import { SCREEN_HEIGHT, SCREEN_WIDTH, VideoInterface } from './output/video_interface'
+import { VideoInterface } from './output/video_interface'
 import { AudioInterface } from './output/audio_interface'
 import { Joypad } from './input/joypad'
 import { WaveformVisualizer } from './util/waveform_visualizer'
@@ -487,14 +487,19 @@ export class GBC {
 
   getImageUrl() {
     if (this.emulator != null && this.wasm != null) {
-      let screen = new Uint8Array(SCREEN_WIDTH * SCREEN_HEIGHT * 4)
-      screen = new Uint8Array(this.wasm.memory.buffer, this.emulator.get_screen(), SCREEN_WIDTH * SCREEN_HEIGHT * 4)
+      const width = this.emulator.get_screen_width()
+      const height = this.emulator.get_screen_height()
+
+      const screen = new Uint8Array(this.wasm.memory.buffer, this.emulator.get_screen(), width * height * 4)
       const canvas = document.getElementById("save-state-canvas") as HTMLCanvasElement
 
+      canvas.width = width
+      canvas.height = height
+
       const context = canvas.getContext("2d")
 
       if (context != null) {
-        const imageData = context.getImageData(0, 0, SCREEN_WIDTH, SCREEN_HEIGHT)
+        const imageData = context.getImageData(0, 0, width, height)
 
         let screenIndex = 0
         for (let i = 0; i < screen.length; i += 4) {
@@ -26,11 +26,20 @@ export class VideoInterface {
     const emu = this.emulator!
     const memory = new Uint8Array(this.wasm!.memory.buffer, emu.get_screen(), emu.get_screen_length())
 
-    const imageData = this.context.getImageData(0, 0, SCREEN_WIDTH, SCREEN_HEIGHT)
+    // the SGB draws a border around the game, so the screen can be bigger than 160x144
+    const width = emu.get_screen_width()
+    const height = emu.get_screen_height()
 
-    for (let y = 0; y < SCREEN_HEIGHT; y++) {
-      for (let x = 0; x < SCREEN_WIDTH; x++) {
-        const index = x * 4 + y * SCREEN_WIDTH * 4
+    if (this.canvas.width != width || this.canvas.height != height) {
+      this.canvas.width = width
+      this.canvas.height = height
+    }
+
+    const imageData = this.context.getImageData(0, 0, width, height)
+
+    for (let y = 0; y < height; y++) {
+      for (let x = 0; x < width; x++) {
+        const index = x * 4 + y * width * 4
 
         imageData.data[index] = memory[index]
         imageData.data[index + 1] = memory[index + 1]
@@ -1,7 +1,7 @@
 
 use std::{collections::HashMap, panic, sync::Arc};
 
-use gbc_plus::cpu::{bus::{apu::NUM_SAMPLES, cartridge::mbc::MBC, joypad::JoypadButtons}, CPU};
+use gbc_plus::cpu::{bus::{apu::NUM_SAMPLES, cartridge::mbc::MBC, joypad::JoypadButtons}, hardware_model::HardwareModel, CPU};
 use ringbuf::{storage::Heap, traits::{Consumer, Split}, wrap::caching::Caching, HeapRb, SharedRb};
 use wasm_bindgen::prelude::*;
 
@@ -50,7 +50,7 @@ impl WebEmulator {
         let (producer, consumer) = ringbuffer.split();
 
         Self {
-            cpu: CPU::new(producer, None, None, false, false),
+            cpu: CPU::new(producer, None, None, false, false, HardwareModel::Cgb),
             joypad_map,
             sample_buffer: Vec::new(),
             consumer,
@@ -67,9 +67,18 @@ impl WebEmulator {
         self.cpu.bus.ppu.set_dmg_palette(index);
     }
 
+    pub fn set_colorization(&mut self, enabled: bool) {
+        self.cpu.bus.ppu.set_colorization(enabled);
+    }
+
+    pub fn set_tilt(&mut self, x: f32, y: f32) {
+        self.cpu.set_tilt(x, y);
+    }
+
     pub fn has_timer(&self) -> bool {
         match &self.cpu.bus.cartridge.mbc {
             MBC::MBC3(mbc) => mbc.has_timer,
+            MBC::HuC3(_) => true,
             _ => false
         }
     }
@@ -77,6 +86,7 @@ impl WebEmulator {
     pub fn fetch_rtc(&self) -> String {
         match &self.cpu.bus.cartridge.mbc {
             MBC::MBC3(mbc) => mbc.save_rtc_web_mobile(),
+            MBC::HuC3(mbc) => mbc.save_rtc_web_mobile(),
             _ => "".to_string()
         }
     }
@@ -84,10 +94,15 @@ impl WebEmulator {
     pub fn load_rtc(&mut self, json: String) {
         match &mut self.cpu.bus.cartridge.mbc {
             MBC::MBC3(mbc) => mbc.load_rtc(json),
+            MBC::HuC3(mbc) => mbc.load_rtc(json),
             _ => ()
         }
     }
 
+    pub fn load_boot_rom(&mut self, data: &[u8]) {
+        self.cpu.load_boot_rom(data);
+    }
+
     pub fn load_rom(&mut self, data: &[u8]) {
         self.cpu.load_rom(data, false);
     }
@@ -125,11 +140,19 @@ impl WebEmulator {
     }
 
     pub fn get_screen(&self) -> *const u8 {
-        self.cpu.bus.ppu.picture.data.as_ptr()
+        self.cpu.picture().data.as_ptr()
     }
 
     pub fn get_screen_length(&self) -> usize {
-        self.cpu.bus.ppu.picture.data.len()
+        self.cpu.picture().data.len()
+    }
+
+    pub fn get_screen_width(&self) -> usize {
+        self.cpu.picture().width
+    }
+
+    pub fn get_screen_height(&self) -> usize {
+        self.cpu.picture().height
     }
 
     pub fn read_ringbuffer(&mut self) -> *mut f32 {
@@ -149,8 +172,14 @@ impl WebEmulator {
     pub fn load_save(&mut self, buf: &[u8]) {
         match &mut self.cpu.bus.cartridge.mbc {
             MBC::MBC1(mbc) => mbc.backup_file.load_save(buf),
+            MBC::MBC2(mbc) => mbc.backup_file.load_save(buf),
             MBC::MBC3(mbc) => mbc.backup_file.load_save(buf),
             MBC::MBC5(mbc) => mbc.backup_file.load_save(buf),
+            MBC::MBC7(mbc) => mbc.backup_file.load_save(buf),
+            MBC::MMM01(mbc) => mbc.backup_file.load_save(buf),
+            MBC::HuC1(mbc) => mbc.backup_file.load_save(buf),
+            MBC::HuC3(mbc) => mbc.backup_file.load_save(buf),
+            MBC::PocketCamera(mbc) => mbc.backup_file.load_save(buf),
             _ => ()
         }
     }
@@ -158,8 +187,14 @@ impl WebEmulator {
     pub fn has_saved(&mut self) -> bool {
         match &mut self.cpu.bus.cartridge.mbc {
             MBC::MBC1(mbc) => mbc.has_saved(),
+            MBC::MBC2(mbc) => mbc.has_saved(),
             MBC::MBC3(mbc) => mbc.has_saved(),
             MBC::MBC5(mbc) => mbc.has_saved(),
+            MBC::MBC7(mbc) => mbc.has_saved(),
+            MBC::MMM01(mbc) => mbc.has_saved(),
+            MBC::HuC1(mbc) => mbc.has_saved(),
+            MBC::HuC3(mbc) => mbc.has_saved(),
+            MBC::PocketCamera(mbc) => mbc.has_saved(),
             _ => false
         }
     }
@@ -167,8 +202,14 @@ impl WebEmulator {
     pub fn get_save_length(&self) -> usize {
         match &self.cpu.bus.cartridge.mbc {
             MBC::MBC1(mbc) => mbc.backup_file.ram.len(),
+            MBC::MBC2(mbc) => mbc.backup_file.ram.len(),
             MBC::MBC3(mbc) => mbc.backup_file.ram.len(),
             MBC::MBC5(mbc) => mbc.backup_file.ram.len(),
+            MBC::MBC7(mbc) => mbc.backup_file.ram.len(),
+            MBC::MMM01(mbc) => mbc.backup_file.ram.len(),
+            MBC::HuC1(mbc) => mbc.backup_file.ram.len(),
+            MBC::HuC3(mbc) => mbc.backup_file.ram.len(),
+            MBC::PocketCamera(mbc) => mbc.backup_file.ram.len(),
             _ => 0
         }
     }
@@ -176,8 +217,14 @@ impl WebEmulator {
     pub fn save_game(&mut self) -> *const u8 {
         match &mut self.cpu.bus.cartridge.mbc {
             MBC::MBC1(mbc) => mbc.backup_file.ram.as_ptr(),
+            MBC::MBC2(mbc) => mbc.backup_file.ram.as_ptr(),
             MBC::MBC3(mbc) => mbc.backup_file.ram.as_ptr(),
             MBC::MBC5(mbc) => mbc.backup_file.ram.as_ptr(),
+            MBC::MBC7(mbc) => mbc.backup_file.ram.as_ptr(),
+            MBC::MMM01(mbc) => mbc.backup_file.ram.as_ptr(),
+            MBC::HuC1(mbc) => mbc.backup_file.ram.as_ptr(),
+            MBC::HuC3(mbc) => mbc.backup_file.ram.as_ptr(),
+            MBC::PocketCamera(mbc) => mbc.backup_file.ram.as_ptr(),
             _ => {
                 let vec = Vec::new();
 
@@ -189,6 +236,7 @@ impl WebEmulator {
     pub fn is_rtc_dirty(&self) -> bool {
         match &self.cpu.bus.cartridge.mbc {
             MBC::MBC3(mbc3) => mbc3.is_dirty,
+            MBC::HuC3(huc3) => huc3.is_dirty,
             _ => false
         }
     }
@@ -196,6 +244,7 @@ impl WebEmulator {
     pub fn clear_rtc_dirty(&mut self) {
         match &mut self.cpu.bus.cartridge.mbc {
             MBC::MBC3(mbc3) => mbc3.is_dirty = false,
+            MBC::HuC3(huc3) => huc3.is_dirty = false,
             _ => ()
         }
     }
@@ -206,7 +255,7 @@ impl WebEmulator {
 
     pub fn update_input(&mut self, button: String, pressed: bool) {
         if pressed {
-            self.cpu.bus.joypad.press_button(*self.joypad_map.get(&button).unwrap());
+            self.cpu.bus.joypad.press_button(*self.joypad_map.get(&button).unwrap(), &mut self.cpu.bus.IF);
         } else {
             self.cpu.bus.joypad.release_button(*self.joypad_map.get(&button).unwrap());
         }